@@ -0,0 +1,41 @@
+// Timestamped log of user actions, kept apart from the scan output
+use crate::scan::timestamp;
+use std::sync::{Arc, Mutex};
+
+// Older entries are dropped above this limit
+const ACTIVITY_LIMIT: usize = 1000;
+
+#[derive(Clone)]
+pub struct ActivityEntry {
+    pub time: String,
+    pub message: String,
+}
+
+#[derive(Clone, Default)]
+pub struct ActivityLog {
+    entries: Arc<Mutex<Vec<ActivityEntry>>>,
+}
+
+impl ActivityLog {
+    pub fn push(&self, message: impl Into<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(ActivityEntry {
+            time: timestamp(),
+            message: message.into(),
+        });
+        let excess = entries.len().saturating_sub(ACTIVITY_LIMIT);
+        entries.drain(..excess);
+    }
+
+    pub fn last(&self) -> Option<ActivityEntry> {
+        self.entries.lock().unwrap().last().cloned()
+    }
+
+    pub fn entries(&self) -> Vec<ActivityEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,686 @@
+// Archives and container images in YARA scans: zip, tar and tar.gz files, RPM payloads and
+// OCI image tarballs or layers are unpacked into a private temporary directory, the unpacked
+// files are scanned with the others and their matches are reported with the path inside the
+// archive, e.g. "/srv/image.tar!blobs/sha256/…!usr/bin/tool".
+use crate::findings::Finding;
+use crate::pool::{format_bytes, ProgressTracker};
+use crate::scan::{launch_error, OutputStream, ScanMonitor, YaraOptions, CANCELLED};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Archives inside archives are opened down to this level, e.g. image tarball, layer, rpm
+const MAX_DEPTH: usize = 3;
+// Files unpacked by one scan at most, beside the size limit of the options
+const MAX_MEMBERS: usize = 200_000;
+// Longest entry name of a cpio archive, PATH_MAX, checked before the name is read
+const MAX_CPIO_NAME: u64 = 4096;
+// Separates the archive from the path inside it
+pub const SEPARATOR: &str = "!";
+
+// Numbers the sandboxes of concurrent scans
+static SANDBOXES: AtomicU64 = AtomicU64::new(0);
+
+// Where a matched file came from. The metadata is the one of the archive entry, the unpacked
+// copy only exists while the scan runs.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ArchiveMember {
+    // Archive file on disk
+    pub archive: String,
+    // Path inside the archive, with the paths of nested archives before it
+    pub member: String,
+    pub mode: u32,
+    // Empty when the archive does not record them
+    pub owner: String,
+    pub group: String,
+    // Seconds since the epoch, 0 if unknown
+    pub mtime: i64,
+    #[serde(skip)]
+    pub extracted: PathBuf,
+}
+
+impl ArchiveMember {
+    pub fn location(&self) -> String {
+        format!("{}{}{}", self.archive, SEPARATOR, self.member)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Zip,
+    Tar,
+    Gzip,
+    Rpm,
+    // OCI layers may be compressed with zstd, which is not unpacked
+    Zstd,
+}
+
+fn is_tar(head: &[u8]) -> bool {
+    head.len() >= 262 && &head[257..262] == b"ustar"
+}
+
+// Archive type from the file signature. OCI layers are tar or tar.gz blobs named by their digest.
+fn kind(path: &Path) -> Option<Kind> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut head = Vec::with_capacity(512);
+    file.by_ref().take(512).read_to_end(&mut head).ok()?;
+    if head.starts_with(b"PK\x03\x04") {
+        Some(Kind::Zip)
+    } else if head.starts_with(b"\x1f\x8b") {
+        Some(Kind::Gzip)
+    } else if head.starts_with(b"\xed\xab\xee\xdb") {
+        Some(Kind::Rpm)
+    } else if head.starts_with(b"\x28\xb5\x2f\xfd") {
+        Some(Kind::Zstd)
+    } else if is_tar(&head) {
+        Some(Kind::Tar)
+    } else {
+        None
+    }
+}
+
+// Relative path of an entry, None for absolute names climbing out with ".."
+fn safe_path(path: &Path) -> Option<PathBuf> {
+    let mut safe = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => safe.push(name),
+            // "./usr/bin" of cpio and tar is fine
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) | Component::ParentDir => return None,
+        }
+    }
+    (!safe.as_os_str().is_empty()).then_some(safe)
+}
+
+// Archive being unpacked
+struct Origin {
+    archive: String,
+    // Location of the archive inside its parents, empty for a file on disk
+    prefix: String,
+    depth: usize,
+}
+
+impl Origin {
+    fn location(&self) -> String {
+        match self.prefix.as_str() {
+            "" => self.archive.clone(),
+            prefix => format!("{}{}{}", self.archive, SEPARATOR, prefix),
+        }
+    }
+}
+
+// Regular file entry of an archive
+struct Entry {
+    path: PathBuf,
+    mode: u32,
+    owner: String,
+    group: String,
+    mtime: i64,
+}
+
+struct Unpacker<'a> {
+    monitor: &'a dyn ScanMonitor,
+    root: PathBuf,
+    limit: u64,
+    unpacked_bytes: u64,
+    limit_reached: bool,
+    directories: usize,
+    members: HashMap<PathBuf, ArchiveMember>,
+    files: Vec<PathBuf>,
+    // Unpacked archives waiting to be opened in turn
+    nested: Vec<(PathBuf, Kind, Origin)>,
+    archives: usize,
+    skipped_links: usize,
+    unsafe_paths: usize,
+    failed: usize,
+    // Entries that could not be read or written, with the archive location and the error
+    skipped_members: Vec<String>,
+    zstd_archives: usize,
+    // Set once rpm2cpio could not be started, the other RPM files are then left packed
+    rpm2cpio_error: Option<String>,
+}
+
+fn create_dir(path: &Path) -> std::io::Result<()> {
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(path)
+}
+
+impl<'a> Unpacker<'a> {
+    fn new(monitor: &'a dyn ScanMonitor, root: PathBuf, limit: u64) -> Self {
+        Self {
+            monitor,
+            root,
+            limit,
+            unpacked_bytes: 0,
+            limit_reached: false,
+            directories: 0,
+            members: HashMap::new(),
+            files: Vec::new(),
+            nested: Vec::new(),
+            archives: 0,
+            skipped_links: 0,
+            unsafe_paths: 0,
+            failed: 0,
+            skipped_members: Vec::new(),
+            zstd_archives: 0,
+            rpm2cpio_error: None,
+        }
+    }
+
+    fn next_dir(&mut self) -> Result<PathBuf, String> {
+        self.directories += 1;
+        let dir = self.root.join(self.directories.to_string());
+        create_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        Ok(dir)
+    }
+
+    // An entry left out while the rest of the archive is unpacked
+    fn skip_member(&mut self, origin: &Origin, member: &str, error: impl std::fmt::Display) {
+        let error = format!("{} в архиве {} не распакован: {}", member, origin.location(), error);
+        self.monitor.output(OutputStream::Stderr, &error);
+        self.skipped_members.push(error);
+    }
+
+    // Copy an entry into the archive directory. Entries past the limits are left out, and so
+    // are entries that cannot be created, such as "x/payload" after a file "x".
+    fn extract(&mut self, dir: &Path, entry: Entry, reader: &mut dyn Read, origin: &Origin) -> Result<(), String> {
+        if self.monitor.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        let Some(path) = safe_path(&entry.path) else {
+            self.unsafe_paths += 1;
+            return Ok(());
+        };
+        if self.limit_reached || self.files.len() >= MAX_MEMBERS {
+            self.limit_reached = true;
+            return Ok(());
+        }
+
+        let target = dir.join(&path);
+        // Unpacked files are private to the user and never executable
+        let created = target.parent().map_or(Ok(()), create_dir).and_then(|_| {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(&target)
+        });
+        let mut file = match created {
+            Ok(file) => file,
+            Err(e) => {
+                self.skip_member(origin, &path.display().to_string(), e);
+                return Ok(());
+            }
+        };
+        let remaining = self.limit - self.unpacked_bytes;
+        let copied = std::io::copy(&mut reader.take(remaining.saturating_add(1)), &mut file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if copied > remaining {
+            // A truncated file would be scanned as something it is not
+            drop(file);
+            let _ = std::fs::remove_file(&target);
+            self.limit_reached = true;
+            return Ok(());
+        }
+        self.unpacked_bytes += copied;
+
+        let member = match origin.prefix.as_str() {
+            "" => path.display().to_string(),
+            prefix => format!("{}{}{}", prefix, SEPARATOR, path.display()),
+        };
+        if let Some(kind) = kind(&target).filter(|_| origin.depth < MAX_DEPTH) {
+            let nested = Origin {
+                archive: origin.archive.clone(),
+                prefix: member.clone(),
+                depth: origin.depth + 1,
+            };
+            self.nested.push((target.clone(), kind, nested));
+        }
+        // A later entry with the same path replaces the earlier one, as tar does
+        if self.members.contains_key(&target) {
+            self.files.retain(|file| *file != target);
+        }
+        self.files.push(target.clone());
+        self.members.insert(
+            target.clone(),
+            ArchiveMember {
+                archive: origin.archive.clone(),
+                member,
+                mode: entry.mode,
+                owner: entry.owner,
+                group: entry.group,
+                mtime: entry.mtime,
+                extracted: target,
+            },
+        );
+        Ok(())
+    }
+
+    fn extract_tar(&mut self, reader: impl Read, dir: &Path, origin: &Origin) -> Result<(), String> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(|e| e.to_string())? {
+            let mut entry = entry.map_err(|e| e.to_string())?;
+            let header = entry.header();
+            let entry_type = header.entry_type();
+            if entry_type.is_dir() {
+                continue;
+            }
+            // Links and devices are not unpacked, the files they point to are scanned anyway
+            if !entry_type.is_file() && !entry_type.is_contiguous() {
+                self.skipped_links += 1;
+                continue;
+            }
+            let id_or_name = |name: Option<&str>, id: std::io::Result<u64>| {
+                name.filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .or_else(|| id.ok().map(|id| id.to_string()))
+                    .unwrap_or_default()
+            };
+            let meta = Entry {
+                path: entry.path().map_err(|e| e.to_string())?.into_owned(),
+                mode: header.mode().unwrap_or(0) | 0o100000,
+                owner: id_or_name(header.username().ok().flatten(), header.uid()),
+                group: id_or_name(header.groupname().ok().flatten(), header.gid()),
+                mtime: header.mtime().map_or(0, |mtime| mtime as i64),
+            };
+            self.extract(dir, meta, &mut entry, origin)?;
+        }
+        Ok(())
+    }
+
+    // A gzip stream is either a tar.gz archive or a single compressed file
+    fn extract_gzip(&mut self, path: &Path, dir: &Path, origin: &Origin) -> Result<(), String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut decoder = flate2::read::MultiGzDecoder::new(file);
+        let mut head = Vec::with_capacity(512);
+        decoder.by_ref().take(512).read_to_end(&mut head).map_err(|e| e.to_string())?;
+        let mut stream = std::io::Cursor::new(head).chain(decoder);
+        if is_tar(stream.get_ref().0.get_ref()) {
+            return self.extract_tar(stream, dir, origin);
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+        let meta = Entry {
+            path: PathBuf::from(if name.is_empty() { "data" } else { name }),
+            mode: 0o100644,
+            owner: String::new(),
+            group: String::new(),
+            mtime: 0,
+        };
+        self.extract(dir, meta, &mut stream, origin)
+    }
+
+    fn extract_zip(&mut self, path: &Path, dir: &Path, origin: &Origin) -> Result<(), String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+        for index in 0..archive.len() {
+            // A damaged entry does not keep the others from being unpacked
+            let mut entry = match archive.by_index(index) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    self.skip_member(origin, &format!("Элемент {}", index + 1), e);
+                    continue;
+                }
+            };
+            if entry.is_dir() {
+                continue;
+            }
+            let mode = entry.unix_mode().unwrap_or(0o100644);
+            if mode & 0o170000 != 0 && mode & 0o170000 != 0o100000 {
+                self.skipped_links += 1;
+                continue;
+            }
+            let meta = Entry {
+                path: PathBuf::from(entry.name()),
+                mode: mode | 0o100000,
+                owner: String::new(),
+                group: String::new(),
+                mtime: 0,
+            };
+            self.extract(dir, meta, &mut entry, origin)?;
+        }
+        Ok(())
+    }
+
+    // The payload is read from rpm2cpio, which knows every compression RPM uses
+    fn extract_rpm(&mut self, path: &Path, dir: &Path, origin: &Origin) -> Result<(), String> {
+        if let Some(error) = &self.rpm2cpio_error {
+            return Err(error.clone());
+        }
+        let mut child = match Command::new("rpm2cpio").arg(path).stdout(Stdio::piped()).stderr(Stdio::null()).spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                let error = launch_error("rpm2cpio", e);
+                self.rpm2cpio_error = Some(error.clone());
+                return Err(error);
+            }
+        };
+        let result = match child.stdout.take() {
+            Some(mut stdout) => self.extract_cpio(&mut stdout, dir, origin),
+            None => Err("нет вывода rpm2cpio".to_string()),
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        result
+    }
+
+    // cpio "newc" archive as written by rpm2cpio
+    fn extract_cpio(&mut self, reader: &mut dyn Read, dir: &Path, origin: &Origin) -> Result<(), String> {
+        let truncated = |e: std::io::Error| format!("неполный архив cpio: {}", e);
+        // Header, name and data are each padded to four bytes
+        let skip_padding = |reader: &mut dyn Read, len: u64| -> Result<(), String> {
+            let mut padding = [0u8; 3];
+            reader.read_exact(&mut padding[..((4 - len % 4) % 4) as usize]).map_err(truncated)
+        };
+        loop {
+            let mut header = [0u8; 110];
+            reader.read_exact(&mut header).map_err(truncated)?;
+            if &header[..6] != b"070701" && &header[..6] != b"070702" {
+                return Err("неизвестный формат cpio".to_string());
+            }
+            let field = |index: usize| {
+                std::str::from_utf8(&header[6 + index * 8..14 + index * 8])
+                    .ok()
+                    .and_then(|text| u64::from_str_radix(text, 16).ok())
+                    .ok_or_else(|| "поврежденный заголовок cpio".to_string())
+            };
+            let (mode, uid, gid, mtime, size, name_size) = (field(1)?, field(2)?, field(3)?, field(5)?, field(6)?, field(11)?);
+            if name_size > MAX_CPIO_NAME {
+                return Err(format!("поврежденный заголовок cpio: длина имени {} байт", name_size));
+            }
+
+            let mut name = vec![0u8; name_size as usize];
+            reader.read_exact(&mut name).map_err(truncated)?;
+            skip_padding(reader, 110 + name_size)?;
+            let name = String::from_utf8_lossy(&name).trim_end_matches('\0').to_string();
+            if name == "TRAILER!!!" {
+                return Ok(());
+            }
+
+            let mut data = Read::take(&mut *reader, size);
+            if mode & 0o170000 == 0o100000 {
+                let meta = Entry {
+                    path: PathBuf::from(name),
+                    mode: mode as u32,
+                    owner: uid.to_string(),
+                    group: gid.to_string(),
+                    mtime: mtime as i64,
+                };
+                self.extract(dir, meta, &mut data, origin)?;
+            } else if mode & 0o170000 != 0o040000 {
+                self.skipped_links += 1;
+            }
+            // Whatever the entry left unread, e.g. past the limit
+            std::io::copy(&mut data, &mut std::io::sink()).map_err(truncated)?;
+            skip_padding(reader, size)?;
+        }
+    }
+
+    fn unpack(&mut self, path: &Path, kind: Kind, origin: &Origin) -> Result<(), String> {
+        let dir = self.next_dir()?;
+        match kind {
+            Kind::Zip => self.extract_zip(path, &dir, origin),
+            Kind::Tar => {
+                let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+                self.extract_tar(file, &dir, origin)
+            }
+            Kind::Gzip => self.extract_gzip(path, &dir, origin),
+            Kind::Rpm => self.extract_rpm(path, &dir, origin),
+            Kind::Zstd => Err("сжатие zstd не поддерживается".to_string()),
+        }
+    }
+
+    // An archive on disk together with the archives found inside it
+    fn unpack_all(&mut self, path: &Path, kind: Kind) -> Result<(), String> {
+        let mut pending = vec![(
+            path.to_path_buf(),
+            kind,
+            Origin {
+                archive: path.display().to_string(),
+                prefix: String::new(),
+                depth: 1,
+            },
+        )];
+        while let Some((path, kind, origin)) = pending.pop() {
+            if self.limit_reached {
+                break;
+            }
+            if let Kind::Zstd = kind {
+                self.zstd_archives += 1;
+                continue;
+            }
+            self.archives += 1;
+            if let Err(e) = self.unpack(&path, kind, &origin) {
+                if self.monitor.is_cancelled() {
+                    return Err(CANCELLED.to_string());
+                }
+                // Files unpacked before the error are still scanned
+                self.failed += 1;
+                self.monitor.output(OutputStream::Stderr, &format!("Не удалось распаковать {}: {}", origin.location(), e));
+            }
+            pending.append(&mut self.nested);
+        }
+        Ok(())
+    }
+}
+
+// Temporary directory of the unpacked files, removed with everything in it when dropped
+struct Sandbox(PathBuf);
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+// Files unpacked for a scan. They exist until this is dropped, after the scan.
+#[derive(Default)]
+pub struct Unpacked {
+    // Only held so the directory lives as long as the scan needs it
+    _sandbox: Option<Sandbox>,
+    members: HashMap<PathBuf, ArchiveMember>,
+    pub files: Vec<PathBuf>,
+    pub notes: Vec<String>,
+}
+
+impl Unpacked {
+    // Report matches of unpacked files under the archive and the path inside it
+    pub fn locate(&self, findings: &mut [Finding]) {
+        for finding in findings.iter_mut().filter(|f| f.process.is_none()) {
+            if let Some(member) = self.members.get(Path::new(&finding.target)) {
+                finding.target = member.location();
+                finding.archive = Some(member.clone());
+            }
+        }
+    }
+}
+
+// Unpack the archives among the walked files when the options ask for it
+pub fn unpack(files: &[PathBuf], options: &YaraOptions, monitor: &dyn ScanMonitor) -> Result<Unpacked, String> {
+    if !options.scan_archives {
+        return Ok(Unpacked::default());
+    }
+    let archives: Vec<(&PathBuf, Kind)> = files.iter().filter_map(|file| Some((file, kind(file)?))).collect();
+    if archives.is_empty() {
+        return Ok(Unpacked {
+            notes: vec!["Архивов среди файлов не найдено".to_string()],
+            ..Default::default()
+        });
+    }
+
+    // The sandbox must not exist yet so nobody else can have prepared it
+    let root = std::env::temp_dir().join(format!(
+        "yara-archives-{}-{}",
+        std::process::id(),
+        SANDBOXES.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&root)
+        .map_err(|e| format!("Не удалось создать каталог для распаковки {}: {}", root.display(), e))?;
+    let mut unpacked = Unpacked {
+        _sandbox: Some(Sandbox(root.clone())),
+        ..Default::default()
+    };
+
+    let mut unpacker = Unpacker::new(monitor, root, options.archive_limit_mb.saturating_mul(1024 * 1024));
+    let sizes: Vec<u64> = archives.iter().map(|(file, _)| file.metadata().map_or(0, |meta| meta.len())).collect();
+    let tracker = ProgressTracker::new(monitor, "Распаковка архивов", archives.len(), sizes.iter().sum());
+    for ((file, kind), size) in archives.iter().zip(&sizes) {
+        tracker.checkpoint()?;
+        tracker.start(&file.display().to_string());
+        unpacker.unpack_all(file, *kind)?;
+        tracker.finish(1, *size);
+    }
+    tracker.done();
+
+    unpacked.notes.push(format!(
+        "Распаковано архивов: {}, извлечено файлов: {} ({})",
+        unpacker.archives - unpacker.failed,
+        unpacker.files.len(),
+        format_bytes(unpacker.unpacked_bytes)
+    ));
+    if unpacker.limit_reached {
+        unpacked.notes.push(format!(
+            "Распаковка остановлена на пределе {} или {} файлов, часть содержимого архивов не проверена",
+            format_bytes(unpacker.limit),
+            MAX_MEMBERS
+        ));
+    }
+    if unpacker.skipped_links > 0 {
+        unpacked.notes.push(format!("Пропущено ссылок и специальных файлов в архивах: {}", unpacker.skipped_links));
+    }
+    if unpacker.unsafe_paths > 0 {
+        unpacked.notes.push(format!(
+            "Пропущено записей архивов с абсолютными путями или '..': {}",
+            unpacker.unsafe_paths
+        ));
+    }
+    if unpacker.failed > 0 {
+        unpacked.notes.push(format!("Не удалось распаковать архивов: {}", unpacker.failed));
+    }
+    if let Some(first) = unpacker.skipped_members.first() {
+        unpacked.notes.push(format!(
+            "Не распаковано записей архивов: {}. {}",
+            unpacker.skipped_members.len(),
+            first
+        ));
+    }
+    if unpacker.zstd_archives > 0 {
+        unpacked.notes.push(format!(
+            "Не проверено архивов и слоев образов, сжатых zstd (формат не поддерживается): {}",
+            unpacker.zstd_archives
+        ));
+    }
+    if let Some(error) = &unpacker.rpm2cpio_error {
+        unpacked.notes.push(format!("Пакеты RPM не распакованы: {}", error));
+    }
+    unpacked.files = std::mem::take(&mut unpacker.files);
+    unpacked.members = std::mem::take(&mut unpacker.members);
+    Ok(unpacked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::NoMonitor;
+
+    // cpio "newc" header of an entry with the given mode, data size and name length
+    fn cpio_header(mode: u64, size: u64, name_size: u64) -> Vec<u8> {
+        let mut fields = [0u64; 13];
+        fields[1] = mode;
+        fields[6] = size;
+        fields[11] = name_size;
+        let mut header = b"070701".to_vec();
+        for field in fields {
+            header.extend(format!("{:08x}", field).bytes());
+        }
+        header
+    }
+
+    fn cpio_entry(name: &str, mode: u64, data: &[u8]) -> Vec<u8> {
+        let name = format!("{}\0", name);
+        let mut entry = cpio_header(mode, data.len() as u64, name.len() as u64);
+        entry.extend(name.bytes());
+        entry.resize(entry.len() + (4 - entry.len() % 4) % 4, 0);
+        entry.extend(data);
+        entry.resize(entry.len() + (4 - entry.len() % 4) % 4, 0);
+        entry
+    }
+
+    // Run the cpio parser on the bytes in a fresh sandbox, removed afterwards
+    fn parse_cpio(bytes: &[u8]) -> (Result<(), String>, Vec<PathBuf>, usize) {
+        let root = std::env::temp_dir().join(format!(
+            "yara-archives-test-{}-{}",
+            std::process::id(),
+            SANDBOXES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _sandbox = Sandbox(root.clone());
+        let mut unpacker = Unpacker::new(&NoMonitor, root, u64::MAX);
+        let origin = Origin {
+            archive: "test.rpm".to_string(),
+            prefix: String::new(),
+            depth: 1,
+        };
+        let result = unpacker.next_dir().and_then(|dir| unpacker.extract_cpio(&mut &bytes[..], &dir, &origin));
+        let mut members: Vec<PathBuf> = unpacker.members.values().map(|member| PathBuf::from(&member.member)).collect();
+        members.sort();
+        (result, members, unpacker.skipped_members.len())
+    }
+
+    #[test]
+    fn safe_paths_stay_inside() {
+        assert_eq!(safe_path(Path::new("./usr/bin/tool")), Some(PathBuf::from("usr/bin/tool")));
+        assert_eq!(safe_path(Path::new("etc/passwd")), Some(PathBuf::from("etc/passwd")));
+        assert_eq!(safe_path(Path::new("../etc/passwd")), None);
+        assert_eq!(safe_path(Path::new("usr/../../etc/passwd")), None);
+        assert_eq!(safe_path(Path::new("/etc/passwd")), None);
+        assert_eq!(safe_path(Path::new("./")), None);
+    }
+
+    #[test]
+    fn cpio_entries_are_unpacked() {
+        let mut archive = cpio_entry("./usr", 0o040755, b"");
+        archive.extend(cpio_entry("./usr/bin/tool", 0o100755, b"evil"));
+        archive.extend(cpio_entry("TRAILER!!!", 0, b""));
+        let (result, members, _) = parse_cpio(&archive);
+        assert_eq!(result, Ok(()));
+        assert_eq!(members, vec![PathBuf::from("usr/bin/tool")]);
+    }
+
+    #[test]
+    fn conflicting_entry_is_skipped() {
+        // "x/payload" cannot be created under the file "x", the entries after it still are
+        let mut archive = cpio_entry("x", 0o100644, b"file");
+        archive.extend(cpio_entry("x/payload", 0o100644, b"evil"));
+        archive.extend(cpio_entry("y", 0o100644, b"evil"));
+        archive.extend(cpio_entry("TRAILER!!!", 0, b""));
+        let (result, members, skipped) = parse_cpio(&archive);
+        assert_eq!(result, Ok(()));
+        assert_eq!(members, vec![PathBuf::from("x"), PathBuf::from("y")]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn truncated_cpio_is_rejected() {
+        let archive = cpio_entry("./usr/bin/tool", 0o100755, b"evil");
+        let (result, _, _) = parse_cpio(&archive[..60]);
+        assert!(result.unwrap_err().starts_with("неполный архив cpio"));
+        // The entry is there but the trailer is missing
+        let (result, _, _) = parse_cpio(&archive);
+        assert!(result.unwrap_err().starts_with("неполный архив cpio"));
+    }
+
+    #[test]
+    fn oversized_cpio_name_is_rejected() {
+        let mut archive = cpio_header(0o100644, 0, u32::MAX as u64);
+        archive.extend(b"name");
+        let (result, members, _) = parse_cpio(&archive);
+        assert!(result.unwrap_err().contains("длина имени"));
+        assert!(members.is_empty());
+    }
+}
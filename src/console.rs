@@ -0,0 +1,509 @@
+// Log console: line-tagged output buffers and a searchable, filterable view of them
+use crate::pool::ScanProgress;
+use crate::scan::{OutputStream, ScanMonitor};
+use eframe::egui;
+use eframe::egui::text::{LayoutJob, TextFormat};
+use regex::Regex;
+use rfd::FileDialog;
+use std::sync::{Arc, Mutex};
+
+// Oldest lines are dropped in chunks once a console grows past this limit
+const MAX_LINES: usize = 1_000_000;
+// Consoles of older jobs are dropped past this number
+const MAX_JOB_CONSOLES: usize = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineKind {
+    Stdout,
+    Stderr,
+    // Messages of the application itself
+    App,
+}
+
+impl From<OutputStream> for LineKind {
+    fn from(stream: OutputStream) -> Self {
+        match stream {
+            OutputStream::Stdout => LineKind::Stdout,
+            OutputStream::Stderr => LineKind::Stderr,
+        }
+    }
+}
+
+struct Line {
+    kind: LineKind,
+    text: String,
+}
+
+#[derive(Default)]
+pub struct Console {
+    lines: Vec<Line>,
+    // Lines dropped from the start, keeps line numbers stable
+    dropped: usize,
+    // Changes whenever lines are removed so views rebuild their filters
+    generation: u64,
+}
+
+impl Console {
+    pub fn push(&mut self, kind: LineKind, text: &str) {
+        self.lines.extend(text.lines().map(|line| Line {
+            kind,
+            text: line.to_string(),
+        }));
+
+        if self.lines.len() > MAX_LINES {
+            let excess = self.lines.len() - MAX_LINES + MAX_LINES / 10;
+            self.lines.drain(..excess);
+            self.dropped += excess;
+            self.generation += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.dropped = 0;
+        self.generation += 1;
+    }
+}
+
+// Output of the recent jobs of one tool, each job writes to its own console
+#[derive(Default)]
+pub struct JobConsoles {
+    consoles: Vec<(u64, Arc<Mutex<Console>>)>,
+}
+
+impl JobConsoles {
+    // Console of the job, created on first use
+    pub fn open(&mut self, id: u64) -> Arc<Mutex<Console>> {
+        if let Some((_, console)) = self.consoles.iter().find(|(job, _)| *job == id) {
+            return Arc::clone(console);
+        }
+        let console = Arc::new(Mutex::new(Console::default()));
+        self.consoles.push((id, Arc::clone(&console)));
+        if self.consoles.len() > MAX_JOB_CONSOLES {
+            self.consoles.remove(0);
+        }
+        console
+    }
+
+    fn get(&self, id: u64) -> Option<Arc<Mutex<Console>>> {
+        self.consoles.iter().find(|(job, _)| *job == id).map(|(_, console)| Arc::clone(console))
+    }
+}
+
+// Copies scan output into a console in addition to the job's own buffer
+pub struct Tee<'a> {
+    pub monitor: &'a dyn ScanMonitor,
+    pub console: &'a Mutex<Console>,
+}
+
+impl ScanMonitor for Tee<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.monitor.is_cancelled()
+    }
+
+    fn is_paused(&self) -> bool {
+        self.monitor.is_paused()
+    }
+
+    fn output(&self, stream: OutputStream, line: &str) {
+        self.console.lock().unwrap().push(stream.into(), line);
+        self.monitor.output(stream, line);
+    }
+
+    fn progress(&self, fraction: f32) {
+        self.monitor.progress(fraction);
+    }
+
+    fn scan_progress(&self, progress: &ScanProgress) {
+        self.monitor.scan_progress(progress);
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct FilterKey {
+    generation: u64,
+    filter: String,
+    search: String,
+    stdout: bool,
+    stderr: bool,
+    app: bool,
+}
+
+// View settings and caches of one console widget
+pub struct ConsoleView {
+    id: &'static str,
+    search: String,
+    filter: String,
+    show_stdout: bool,
+    show_stderr: bool,
+    show_app: bool,
+    line_numbers: bool,
+    wrap: bool,
+    auto_scroll: bool,
+    // Indices of the console lines passing the filters
+    visible: Vec<usize>,
+    // Positions in `visible` of the lines containing the search text
+    hits: Vec<usize>,
+    // Filters and number of console lines the caches were built for
+    key: Option<FilterKey>,
+    scanned: usize,
+    filter_regex: Option<Regex>,
+    filter_error: Option<String>,
+    search_regex: Option<Regex>,
+    // Current position in `hits`
+    current_hit: Option<usize>,
+    // Anchor and end of the selected range, positions in `visible`
+    selection: Option<(usize, usize)>,
+    scroll_to: Option<usize>,
+    status: String,
+    // Job chosen in `show_jobs`, the latest one if None
+    job: Option<u64>,
+    // Job whose console the caches were built for
+    shown_job: Option<u64>,
+}
+
+impl ConsoleView {
+    pub fn new(id: &'static str) -> Self {
+        Self {
+            id,
+            search: String::new(),
+            filter: String::new(),
+            show_stdout: true,
+            show_stderr: true,
+            show_app: true,
+            line_numbers: true,
+            wrap: false,
+            auto_scroll: true,
+            visible: Vec::new(),
+            hits: Vec::new(),
+            key: None,
+            scanned: 0,
+            filter_regex: None,
+            filter_error: None,
+            search_regex: None,
+            current_hit: None,
+            selection: None,
+            scroll_to: None,
+            status: String::new(),
+            job: None,
+            shown_job: None,
+        }
+    }
+
+    // Forget the caches, e.g. when the view switches to another console
+    pub fn reset(&mut self) {
+        self.key = None;
+    }
+
+    fn passes(&self, line: &Line) -> bool {
+        let kind = match line.kind {
+            LineKind::Stdout => self.show_stdout,
+            LineKind::Stderr => self.show_stderr,
+            LineKind::App => self.show_app,
+        };
+        kind && self.filter_regex.as_ref().is_none_or(|regex| regex.is_match(&line.text))
+    }
+
+    // Filter only the lines added since the last frame unless the filters changed
+    fn refresh(&mut self, console: &Console) {
+        let key = FilterKey {
+            generation: console.generation,
+            filter: self.filter.clone(),
+            search: self.search.clone(),
+            stdout: self.show_stdout,
+            stderr: self.show_stderr,
+            app: self.show_app,
+        };
+
+        if self.key.as_ref() != Some(&key) {
+            let filter_changed = self.key.as_ref().is_none_or(|old| old.filter != key.filter);
+            if filter_changed {
+                self.filter_error = None;
+                self.filter_regex = if self.filter.is_empty() {
+                    None
+                } else {
+                    Regex::new(&self.filter)
+                        .map_err(|e| self.filter_error = Some(e.to_string()))
+                        .ok()
+                };
+            }
+            // The search is plain text, case-insensitive
+            self.search_regex = if self.search.is_empty() {
+                None
+            } else {
+                Regex::new(&format!("(?i){}", regex::escape(&self.search))).ok()
+            };
+
+            self.visible.clear();
+            self.hits.clear();
+            self.scanned = 0;
+            self.current_hit = None;
+            self.selection = None;
+            self.key = Some(key);
+        }
+
+        for index in self.scanned..console.lines.len() {
+            let line = &console.lines[index];
+            if self.passes(line) {
+                if self.search_regex.as_ref().is_some_and(|regex| regex.is_match(&line.text)) {
+                    self.hits.push(self.visible.len());
+                }
+                self.visible.push(index);
+            }
+        }
+        self.scanned = console.lines.len();
+    }
+
+    fn jump(&mut self, forward: bool) {
+        if self.hits.is_empty() {
+            return;
+        }
+        let last = self.hits.len() - 1;
+        let hit = match (self.current_hit, forward) {
+            (None, true) => 0,
+            (None, false) => last,
+            (Some(hit), true) => if hit == last { 0 } else { hit + 1 },
+            (Some(hit), false) => if hit == 0 { last } else { hit - 1 },
+        };
+        self.current_hit = Some(hit);
+        self.scroll_to = Some(self.hits[hit]);
+        self.auto_scroll = false;
+    }
+
+    fn selected_rows(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        self.selection.map(|(a, b)| a.min(b)..=a.max(b))
+    }
+
+    fn collect_text(&self, console: &Console, rows: impl Iterator<Item = usize>) -> String {
+        let mut text = String::new();
+        for row in rows {
+            if let Some(line) = self.visible.get(row).and_then(|index| console.lines.get(*index)) {
+                text.push_str(&line.text);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    fn layout_line(&self, ui: &egui::Ui, row: usize, number: usize, line: &Line) -> LayoutJob {
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let selected = self.selected_rows().is_some_and(|rows| rows.contains(&row));
+        let current = self.current_hit.map(|hit| self.hits[hit]) == Some(row);
+        let background = if selected {
+            ui.visuals().selection.bg_fill
+        } else {
+            egui::Color32::TRANSPARENT
+        };
+        let color = match line.kind {
+            LineKind::Stdout => ui.visuals().text_color(),
+            LineKind::Stderr => egui::Color32::from_rgb(230, 120, 90),
+            LineKind::App => egui::Color32::LIGHT_BLUE,
+        };
+
+        let mut job = LayoutJob::default();
+        if self.line_numbers {
+            job.append(
+                &format!("{:>7} ", number),
+                0.0,
+                TextFormat {
+                    font_id: font_id.clone(),
+                    color: ui.visuals().weak_text_color(),
+                    background,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let format = TextFormat {
+            font_id,
+            color,
+            background,
+            ..Default::default()
+        };
+        let highlight = TextFormat {
+            color: egui::Color32::BLACK,
+            background: if current { egui::Color32::from_rgb(255, 150, 0) } else { egui::Color32::YELLOW },
+            ..format.clone()
+        };
+
+        let mut last = 0;
+        if let Some(regex) = &self.search_regex {
+            for found in regex.find_iter(&line.text) {
+                job.append(&line.text[last..found.start()], 0.0, format.clone());
+                job.append(found.as_str(), 0.0, highlight.clone());
+                last = found.end();
+            }
+        }
+        job.append(&line.text[last..], 0.0, format);
+        job
+    }
+
+    // Shows the console of one job with a selector of the recent jobs
+    pub fn show_jobs(&mut self, ui: &mut egui::Ui, consoles: &Mutex<JobConsoles>, height: f32) {
+        let (ids, console) = {
+            let consoles = consoles.lock().unwrap();
+            let ids: Vec<u64> = consoles.consoles.iter().map(|(id, _)| *id).collect();
+            if self.job.is_some_and(|job| !ids.contains(&job)) {
+                self.job = None;
+            }
+            let shown = self.job.or(ids.last().copied());
+            (ids, shown.and_then(|id| consoles.get(id).map(|console| (id, console))))
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Задание:");
+            let selected = match (self.job, &console) {
+                (Some(id), _) => format!("#{}", id),
+                (None, Some((id, _))) => format!("последнее (#{})", id),
+                (None, None) => "последнее".to_string(),
+            };
+            egui::ComboBox::from_id_source((self.id, "job"))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.job, None, "последнее");
+                    for id in ids.iter().rev() {
+                        ui.selectable_value(&mut self.job, Some(*id), format!("#{}", id));
+                    }
+                });
+        });
+
+        let shown = console.as_ref().map(|(id, _)| *id);
+        if shown != self.shown_job {
+            self.shown_job = shown;
+            self.reset();
+        }
+        match console {
+            Some((_, console)) => self.show(ui, &console, height),
+            None => {
+                ui.label("Вывод появится после запуска сканирования.");
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, console: &Mutex<Console>, height: f32) {
+        let mut save = false;
+        let mut copy_selected = false;
+        let mut copy_all = false;
+        let mut clear = false;
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Поиск:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.search).desired_width(160.0));
+            let enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.small_button("▲").clicked() {
+                self.jump(false);
+            }
+            if ui.small_button("▼").clicked() || enter {
+                self.jump(true);
+            }
+            if !self.search.is_empty() {
+                match self.current_hit {
+                    Some(hit) => ui.label(format!("{}/{}", hit + 1, self.hits.len())),
+                    None => ui.label(format!("совпадений: {}", self.hits.len())),
+                };
+            }
+
+            ui.separator();
+            ui.label("Фильтр (regex):");
+            ui.add(egui::TextEdit::singleline(&mut self.filter).desired_width(160.0));
+            if let Some(error) = &self.filter_error {
+                ui.colored_label(egui::Color32::RED, error.lines().last().unwrap_or_default());
+            }
+        });
+
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.show_stdout, "stdout");
+            ui.checkbox(&mut self.show_stderr, "stderr");
+            ui.checkbox(&mut self.show_app, "сообщения");
+            ui.separator();
+            ui.checkbox(&mut self.line_numbers, "Номера строк");
+            ui.checkbox(&mut self.wrap, "Перенос строк");
+            ui.checkbox(&mut self.auto_scroll, "Автопрокрутка");
+            ui.separator();
+            copy_selected = ui
+                .add_enabled(self.selection.is_some(), egui::Button::new("Копировать выделенное"))
+                .clicked();
+            copy_all = ui.button("Копировать все").clicked();
+            save = ui.button("Сохранить в файл").clicked();
+            clear = ui.button("Очистить").clicked();
+        });
+
+        // Ctrl+C copies the selected lines unless a text field has the focus
+        if self.selection.is_some()
+            && ui.memory(|memory| memory.focus().is_none())
+            && ui.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::Copy)))
+        {
+            copy_selected = true;
+        }
+
+        let mut console = console.lock().unwrap();
+        if clear {
+            console.clear();
+        }
+        self.refresh(&console);
+
+        if copy_selected {
+            if let Some(rows) = self.selected_rows() {
+                let text = self.collect_text(&console, rows);
+                ui.output_mut(|output| output.copied_text = text);
+            }
+        }
+        if copy_all {
+            let text = self.collect_text(&console, 0..self.visible.len());
+            ui.output_mut(|output| output.copied_text = text);
+        }
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        // Wrapped lines are taller than one row, the scroll bar is approximate then
+        let mut area = if self.wrap {
+            egui::ScrollArea::vertical()
+        } else {
+            egui::ScrollArea::both()
+        }
+        .id_source(self.id)
+        .auto_shrink([false, false])
+        .max_height(height)
+        .stick_to_bottom(self.auto_scroll);
+        if let Some(row) = self.scroll_to.take() {
+            area = area.vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+        }
+
+        let mut clicked = None;
+        area.show_rows(ui, row_height, self.visible.len(), |ui, rows| {
+            for row in rows {
+                let index = self.visible[row];
+                let job = self.layout_line(ui, row, index + console.dropped + 1, &console.lines[index]);
+                let response = ui.add(egui::Label::new(job).wrap(self.wrap).sense(egui::Sense::click()));
+                if response.clicked() {
+                    clicked = Some((row, ui.input(|i| i.modifiers.shift)));
+                }
+            }
+        });
+
+        match (clicked, self.selection) {
+            (Some((row, true)), Some((anchor, _))) => self.selection = Some((anchor, row)),
+            (Some((row, _)), _) => self.selection = Some((row, row)),
+            _ => {}
+        }
+
+        // Saves the lines passing the filters
+        if save {
+            let text = self.collect_text(&console, 0..self.visible.len());
+            drop(console);
+            self.status = match FileDialog::new()
+                .add_filter("Text", &["log", "txt"])
+                .set_title("Сохранить журнал как")
+                .save_file()
+            {
+                Some(path) => match std::fs::write(&path, text) {
+                    Ok(_) => format!("Журнал сохранен: {}", path.display()),
+                    Err(e) => format!("Не удалось сохранить журнал: {}", e),
+                },
+                None => String::new(),
+            };
+        }
+        if !self.status.is_empty() {
+            ui.small(&self.status);
+        }
+    }
+}
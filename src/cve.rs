@@ -0,0 +1,121 @@
+// Offline CVE data used to fill in CVSS scores missing from the OVAL content
+use crate::findings::{CveInfo, Finding};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub struct CveDatabase {
+    entries: HashMap<String, CveInfo>,
+}
+
+impl CveDatabase {
+    // Supported formats: NVD JSON 1.1 feeds, NVD API 2.0 responses and
+    // CSV files with "cve,score,severity,vector" columns
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Не удалось прочитать файл CVE: {}", e))?;
+
+        let entries = if text.trim_start().starts_with('{') {
+            let json: Value = serde_json::from_str(&text).map_err(|e| format!("Ошибка разбора JSON: {}", e))?;
+            parse_nvd_json(&json)
+        } else {
+            parse_csv(&text)
+        };
+
+        if entries.is_empty() {
+            return Err("Файл не содержит сведений о CVE".to_string());
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Fill in scores, vectors and severities missing from the findings
+    pub fn enrich(&self, findings: &mut [Finding]) {
+        for cve in findings.iter_mut().flat_map(|f| f.cves.iter_mut()) {
+            if let Some(entry) = self.entries.get(&cve.id) {
+                if cve.cvss_score.is_none() {
+                    cve.cvss_score = entry.cvss_score;
+                    cve.cvss_vector = entry.cvss_vector.clone();
+                }
+                if cve.severity.is_empty() {
+                    cve.severity = entry.severity.clone();
+                }
+            }
+        }
+    }
+}
+
+fn parse_nvd_json(json: &Value) -> HashMap<String, CveInfo> {
+    let mut entries = HashMap::new();
+
+    // NVD JSON 1.1 feed
+    for item in json["CVE_Items"].as_array().into_iter().flatten() {
+        let id = item["cve"]["CVE_data_meta"]["ID"].as_str().unwrap_or_default();
+        let (cvss, severity) = if item["impact"]["baseMetricV3"].is_object() {
+            let metric = &item["impact"]["baseMetricV3"];
+            (&metric["cvssV3"], metric["cvssV3"]["baseSeverity"].as_str())
+        } else {
+            let metric = &item["impact"]["baseMetricV2"];
+            (&metric["cvssV2"], metric["severity"].as_str())
+        };
+        insert_entry(&mut entries, id, cvss, severity);
+    }
+
+    // NVD API 2.0 response
+    for item in json["vulnerabilities"].as_array().into_iter().flatten() {
+        let cve = &item["cve"];
+        let id = cve["id"].as_str().unwrap_or_default();
+        let metric = ["cvssMetricV31", "cvssMetricV30", "cvssMetricV2"]
+            .iter()
+            .find_map(|key| cve["metrics"][key].get(0));
+        if let Some(metric) = metric {
+            let severity = metric["cvssData"]["baseSeverity"]
+                .as_str()
+                .or_else(|| metric["baseSeverity"].as_str());
+            insert_entry(&mut entries, id, &metric["cvssData"], severity);
+        }
+    }
+
+    entries
+}
+
+fn insert_entry(entries: &mut HashMap<String, CveInfo>, id: &str, cvss: &Value, severity: Option<&str>) {
+    if id.is_empty() {
+        return;
+    }
+    entries.insert(
+        id.to_string(),
+        CveInfo {
+            id: id.to_string(),
+            cvss_score: cvss["baseScore"].as_f64().map(|score| score as f32),
+            cvss_vector: cvss["vectorString"].as_str().unwrap_or_default().to_string(),
+            severity: severity.unwrap_or_default().to_lowercase(),
+        },
+    );
+}
+
+fn parse_csv(text: &str) -> HashMap<String, CveInfo> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            let id = fields.next()?;
+            // Also skips the header line
+            if !id.starts_with("CVE-") {
+                return None;
+            }
+            let cvss_score = fields.next().and_then(|score| score.parse().ok());
+            let severity = fields.next().unwrap_or_default().to_lowercase();
+            let cvss_vector = fields.next().unwrap_or_default().to_string();
+            Some((
+                id.to_string(),
+                CveInfo {
+                    id: id.to_string(),
+                    cvss_score,
+                    cvss_vector,
+                    severity,
+                },
+            ))
+        })
+        .collect()
+}
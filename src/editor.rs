@@ -0,0 +1,486 @@
+// YARA rule editor: highlighting, auto-indentation, live syntax check and a test against a sample file
+use crate::findings::{self, StringMatch};
+use crate::jobs::JobManager;
+use crate::rules::{self, Compilation, Severity};
+use crate::scan::{self, ExternalVariable};
+use crate::storage;
+use crate::yara_syntax::{self, TokenKind};
+use eframe::egui;
+use eframe::egui::text::{CCursor, CCursorRange, LayoutJob, TextFormat};
+use rfd::FileDialog;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Pause in typing before the syntax check runs
+const CHECK_DELAY: Duration = Duration::from_millis(700);
+const INDENT: &str = "    ";
+// Scratch file of the syntax check, one check runs at a time
+const CHECK_FILE: &str = "check.yar";
+
+// Numbers the scratch files of concurrent sample tests
+static TEST_FILES: AtomicU64 = AtomicU64::new(0);
+
+const TEMPLATE: &str = "rule new_rule\n{\n    meta:\n        author = \"\"\n        description = \"\"\n    strings:\n        $a = \"\"\n    condition:\n        $a\n}\n";
+
+// Rules and strings that matched the sample
+pub struct SampleResult {
+    pub rules: Vec<String>,
+    pub strings: Vec<StringMatch>,
+}
+
+fn highlight(ui: &egui::Ui, text: &str, error_lines: &HashSet<usize>, wrap_width: f32) -> Arc<egui::Galley> {
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let plain = ui.visuals().text_color();
+    let format = |color: egui::Color32, line: usize| {
+        let mut format = TextFormat::simple(font.clone(), color);
+        if error_lines.contains(&line) {
+            format.underline = egui::Stroke::new(1.0, egui::Color32::RED);
+        }
+        format
+    };
+
+    let tokens = yara_syntax::tokenize(text);
+    let mut job = LayoutJob::default();
+    let mut pos = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        let source = &text[token.start..token.end];
+        let mut end = token.end;
+        let color = match token.kind {
+            TokenKind::Comment => egui::Color32::GRAY,
+            TokenKind::Str => egui::Color32::from_rgb(206, 145, 120),
+            TokenKind::Regex => egui::Color32::from_rgb(209, 105, 105),
+            TokenKind::Word if yara_syntax::KEYWORDS.contains(&source) => egui::Color32::from_rgb(86, 156, 214),
+            TokenKind::Word if source.starts_with(|c: char| c.is_ascii_digit()) => egui::Color32::from_rgb(181, 206, 168),
+            TokenKind::Word => plain,
+            // String identifiers: $a, #a, @a, !a and the anonymous $
+            TokenKind::Punct if matches!(source, "$" | "#" | "@" | "!") => {
+                if let Some(next) = tokens.get(i + 1).filter(|next| next.kind == TokenKind::Word && next.start == token.end) {
+                    end = next.end;
+                    i += 1;
+                }
+                if source == "!" && end == token.end {
+                    plain
+                } else {
+                    egui::Color32::from_rgb(156, 220, 254)
+                }
+            }
+            TokenKind::Punct => plain,
+        };
+        job.append(&text[pos..token.start], 0.0, TextFormat::simple(font.clone(), plain));
+        job.append(&text[token.start..end], 0.0, format(color, token.line));
+        pos = end;
+        i += 1;
+    }
+    job.append(&text[pos..], 0.0, TextFormat::simple(font.clone(), plain));
+    job.wrap.max_width = wrap_width;
+    ui.fonts(|fonts| fonts.layout_job(job))
+}
+
+// Character index of the start of a 1-based line
+fn line_start(text: &str, line: usize) -> usize {
+    text.split_inclusive('\n').take(line.saturating_sub(1)).map(|l| l.chars().count()).sum()
+}
+
+// Action waiting for the confirmation to drop the unsaved changes
+enum Pending {
+    New,
+    Open(PathBuf, usize),
+}
+
+pub struct RuleEditor {
+    path: Option<PathBuf>,
+    text: String,
+    modified: bool,
+    // Text of the last syntax check and the time of the last edit
+    checked_text: String,
+    edited_at: Instant,
+    check: Arc<Mutex<Option<Result<Compilation, String>>>>,
+    checking: Arc<Mutex<bool>>,
+    sample: String,
+    test: Arc<Mutex<Option<Result<SampleResult, String>>>>,
+    goto_line: Option<usize>,
+    pending: Option<Pending>,
+    message: String,
+}
+
+impl Default for RuleEditor {
+    fn default() -> Self {
+        Self {
+            path: None,
+            text: TEMPLATE.to_string(),
+            modified: false,
+            checked_text: String::new(),
+            edited_at: Instant::now(),
+            check: Arc::new(Mutex::new(None)),
+            checking: Arc::new(Mutex::new(false)),
+            sample: String::new(),
+            test: Arc::new(Mutex::new(None)),
+            goto_line: None,
+            pending: None,
+            message: String::new(),
+        }
+    }
+}
+
+impl RuleEditor {
+    // Open a file and put the cursor on a line, 0 keeps the cursor at the start.
+    // Unsaved changes are dropped only after a confirmation.
+    pub fn open(&mut self, path: &Path, line: usize) {
+        if self.modified {
+            self.pending = Some(Pending::Open(path.to_path_buf(), line));
+        } else {
+            self.load(path, line);
+        }
+    }
+
+    fn new_file(&mut self) {
+        *self = Self {
+            sample: std::mem::take(&mut self.sample),
+            ..Self::default()
+        };
+    }
+
+    fn load(&mut self, path: &Path, line: usize) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                self.text = text;
+                self.path = Some(path.to_path_buf());
+                self.modified = false;
+                self.checked_text.clear();
+                self.goto_line = (line > 0).then_some(line);
+                *self.test.lock().unwrap() = None;
+                self.message = format!("Открыт {}", path.display());
+            }
+            Err(e) => self.message = format!("Не удалось открыть {}: {}", path.display(), e),
+        }
+    }
+
+    fn save_to(&mut self, path: PathBuf) {
+        match std::fs::write(&path, &self.text) {
+            Ok(_) => {
+                self.message = format!("Сохранено: {}", path.display());
+                self.path = Some(path);
+                self.modified = false;
+            }
+            Err(e) => self.message = format!("Не удалось сохранить {}: {}", path.display(), e),
+        }
+    }
+
+    fn save_as(&mut self) {
+        if let Some(path) = FileDialog::new()
+            .add_filter("YARA Rules", &rules::RULE_EXTENSIONS)
+            .set_title("Сохранить правила как")
+            .save_file()
+        {
+            self.save_to(path);
+        }
+    }
+
+    // Copy of the text for yarac and yara. Relative includes keep pointing next to the opened file.
+    fn scratch_file(&self, name: &str) -> Result<PathBuf, String> {
+        let text = match self.path.as_ref().and_then(|path| path.parent()) {
+            Some(dir) => yara_syntax::absolute_includes(&self.text, dir),
+            None => self.text.clone(),
+        };
+        let path = storage::path(&format!("editor/{}", name));
+        std::fs::write(&path, text).map_err(|e| format!("Не удалось записать {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    fn start_check(&mut self, defines: &[ExternalVariable]) {
+        self.checked_text = self.text.clone();
+        let path = match self.scratch_file(CHECK_FILE) {
+            Ok(path) => path,
+            Err(e) => {
+                *self.check.lock().unwrap() = Some(Err(e));
+                return;
+            }
+        };
+
+        let check = Arc::clone(&self.check);
+        let checking = Arc::clone(&self.checking);
+        let defines = defines.to_vec();
+        *checking.lock().unwrap() = true;
+        std::thread::spawn(move || {
+            let result = rules::check(&path, &defines, &scan::NoMonitor);
+            *check.lock().unwrap() = Some(result);
+            *checking.lock().unwrap() = false;
+        });
+    }
+
+    fn start_test(&mut self, jobs: &JobManager, defines: &[ExternalVariable]) {
+        let number = TEST_FILES.fetch_add(1, Ordering::Relaxed);
+        let rules_path = match self.scratch_file(&format!("test-{}-{}.yar", std::process::id(), number)) {
+            Ok(path) => path,
+            Err(e) => {
+                self.message = e;
+                return;
+            }
+        };
+        let sample = self.sample.clone();
+        let test = Arc::clone(&self.test);
+        let defines = defines.to_vec();
+        *test.lock().unwrap() = None;
+
+        jobs.spawn("YARA", &format!("Проверка на образце {}", sample), move |job| {
+            let mut command = Command::new("yara");
+            command.arg("-s").arg("-w");
+            for define in &defines {
+                command.arg(format!("--define={}={}", define.name, define.value));
+            }
+            command.arg(&rules_path).arg(&sample);
+
+            let result = scan::run_command(&mut command, job)
+                .map_err(|e| scan::launch_error("YARA", e))
+                .and_then(|(stdout, stderr, status)| {
+                    if status.success() {
+                        Ok(stdout)
+                    } else {
+                        Err(format!("yara завершился с ошибкой: {}", stderr.trim()))
+                    }
+                })
+                .map(|stdout| SampleResult {
+                    rules: findings::parse_yara_output(&stdout).into_iter().map(|f| f.rule_id).collect(),
+                    strings: findings::parse_yara_strings(&stdout),
+                });
+            let _ = std::fs::remove_file(&rules_path);
+            let message = match &result {
+                Ok(sample) => Ok(format!("Сработало правил: {}, совпадений строк: {}", sample.rules.len(), sample.strings.len())),
+                Err(e) => Err(e.clone()),
+            };
+            *test.lock().unwrap() = Some(result);
+            message
+        });
+    }
+
+    // Repeat the indentation of the previous line after Enter, one level deeper after "{" or a section
+    fn auto_indent(&mut self, ui: &egui::Ui, output: egui::text_edit::TextEditOutput) {
+        let Some(range) = output.cursor_range else {
+            return;
+        };
+        let cursor = range.primary.ccursor.index;
+        let byte = self.text.char_indices().nth(cursor).map_or(self.text.len(), |(index, _)| index);
+        if !self.text[..byte].ends_with('\n') {
+            return;
+        }
+
+        let previous = self.text[..byte - 1].rsplit('\n').next().unwrap_or("");
+        let mut indent: String = previous.chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        let trimmed = previous.trim_end();
+        if trimmed.ends_with('{') || ["meta:", "strings:", "condition:"].iter().any(|s| trimmed.ends_with(s)) {
+            indent.push_str(INDENT);
+        }
+        if indent.is_empty() {
+            return;
+        }
+
+        self.text.insert_str(byte, &indent);
+        let mut state = output.state;
+        state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(cursor + indent.chars().count()))));
+        state.store(ui.ctx(), output.response.id);
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, jobs: &JobManager, defines: &[ExternalVariable]) {
+        ui.horizontal(|ui| {
+            if ui.button("Новый").clicked() {
+                if self.modified {
+                    self.pending = Some(Pending::New);
+                } else {
+                    self.new_file();
+                }
+            }
+            if ui.button("Открыть").clicked() {
+                if let Some(path) = FileDialog::new().add_filter("YARA Rules", &rules::RULE_EXTENSIONS).pick_file() {
+                    self.open(&path, 0);
+                }
+            }
+            if ui.button("Сохранить").clicked() {
+                match self.path.clone() {
+                    Some(path) => self.save_to(path),
+                    None => self.save_as(),
+                }
+            }
+            if ui.button("Сохранить как").clicked() {
+                self.save_as();
+            }
+            let name = self.path.as_ref().map_or("без имени".to_string(), |path| path.display().to_string());
+            ui.label(if self.modified { format!("{} *", name) } else { name });
+        });
+
+        if self.pending.is_some() {
+            ui.horizontal(|ui| {
+                ui.colored_label(egui::Color32::YELLOW, "Изменения не сохранены и будут потеряны.");
+                if ui.button("Продолжить без сохранения").clicked() {
+                    match self.pending.take() {
+                        Some(Pending::New) => self.new_file(),
+                        Some(Pending::Open(path, line)) => self.load(&path, line),
+                        None => {}
+                    }
+                }
+                if ui.button("Отмена").clicked() {
+                    self.pending = None;
+                }
+            });
+        }
+
+        // Syntax check after a pause in typing
+        if self.text != self.checked_text && !*self.checking.lock().unwrap() {
+            let waited = self.edited_at.elapsed();
+            if waited >= CHECK_DELAY {
+                self.start_check(defines);
+            } else {
+                ui.ctx().request_repaint_after(CHECK_DELAY - waited);
+            }
+        }
+        if *self.checking.lock().unwrap() {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+
+        // Only messages about the scratch copy point into the text, included files have their own lines
+        let check = self.check.lock().unwrap().clone();
+        let scratch = storage::data_dir().join("editor").join(CHECK_FILE);
+        let (diagnostics, included): (Vec<_>, Vec<_>) = match check.as_ref() {
+            Some(Ok(compilation)) => compilation.diagnostics.iter().cloned().partition(|d| Path::new(&d.file) == scratch),
+            _ => (Vec::new(), Vec::new()),
+        };
+        let error_lines: HashSet<usize> = diagnostics.iter().filter(|d| d.severity == Severity::Error).map(|d| d.line).collect();
+        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| highlight(ui, text, &error_lines, wrap_width);
+
+        let id = egui::Id::new("rule_editor_text");
+        let goto = self.goto_line.take().map(|line| {
+            let index = line_start(&self.text, line);
+            let mut state = egui::text_edit::TextEditState::load(ui.ctx(), id).unwrap_or_default();
+            state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(index))));
+            state.store(ui.ctx(), id);
+            ui.memory_mut(|memory| memory.request_focus(id));
+            index
+        });
+
+        let editor_height = (ui.available_height() - 220.0).max(200.0);
+        let output = egui::ScrollArea::vertical()
+            .id_source("rule_editor_scroll")
+            .max_height(editor_height)
+            .show(ui, |ui| {
+                egui::TextEdit::multiline(&mut self.text)
+                    .id(id)
+                    .code_editor()
+                    .lock_focus(true)
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(20)
+                    .layouter(&mut layouter)
+                    .show(ui)
+            })
+            .inner;
+
+        if let Some(index) = goto {
+            let rect = output.galley.pos_from_cursor(&output.galley.from_ccursor(CCursor::new(index))).translate(output.text_draw_pos.to_vec2());
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+        }
+        if output.response.changed() {
+            self.modified = true;
+            self.edited_at = Instant::now();
+            if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                self.auto_indent(ui, output);
+            }
+        }
+
+        // Compiler messages, a click moves the cursor to the line
+        match check.as_ref() {
+            _ if *self.checking.lock().unwrap() => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Проверка синтаксиса...");
+                });
+            }
+            None => {}
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            Some(Ok(compilation)) => {
+                if compilation.diagnostics.is_empty() && compilation.other.is_empty() {
+                    ui.colored_label(egui::Color32::GREEN, "Ошибок нет");
+                }
+                egui::ScrollArea::vertical().id_source("rule_editor_diagnostics").max_height(80.0).show(ui, |ui| {
+                    for diagnostic in &diagnostics {
+                        let color = match diagnostic.severity {
+                            Severity::Error => egui::Color32::RED,
+                            Severity::Warning => egui::Color32::YELLOW,
+                        };
+                        let text = format!("Строка {}: {}: {}", diagnostic.line, diagnostic.severity.label(), diagnostic.message);
+                        if ui.add(egui::Label::new(egui::RichText::new(text).color(color)).sense(egui::Sense::click())).clicked() {
+                            self.goto_line = Some(diagnostic.line);
+                        }
+                    }
+                    for diagnostic in &included {
+                        let color = match diagnostic.severity {
+                            Severity::Error => egui::Color32::RED,
+                            Severity::Warning => egui::Color32::YELLOW,
+                        };
+                        ui.colored_label(
+                            color,
+                            format!("{}:{}: {}: {}", diagnostic.file, diagnostic.line, diagnostic.severity.label(), diagnostic.message),
+                        );
+                    }
+                    for line in &compilation.other {
+                        ui.colored_label(egui::Color32::RED, line);
+                    }
+                });
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Образец").clicked() {
+                if let Some(path) = FileDialog::new().set_title("Файл для проверки правил").pick_file() {
+                    self.sample = path.to_string_lossy().to_string();
+                }
+            }
+            ui.label(if self.sample.is_empty() { "файл не выбран" } else { &self.sample });
+            if ui
+                .add_enabled(!self.sample.is_empty(), egui::Button::new("Проверить на образце"))
+                .clicked()
+            {
+                self.start_test(jobs, defines);
+            }
+        });
+
+        match self.test.lock().unwrap().as_ref() {
+            None => {}
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            Some(Ok(result)) if result.rules.is_empty() => {
+                ui.label("Ни одно правило не сработало");
+            }
+            Some(Ok(result)) => {
+                ui.label(format!("Сработавшие правила: {}", result.rules.join(", ")));
+                egui::ScrollArea::vertical().id_source("rule_editor_matches").max_height(120.0).show(ui, |ui| {
+                    egui::Grid::new("rule_editor_matches_grid").striped(true).show(ui, |ui| {
+                        ui.strong("Правило");
+                        ui.strong("Строка");
+                        ui.strong("Смещение");
+                        ui.strong("Данные");
+                        ui.end_row();
+                        for string in &result.strings {
+                            ui.label(&string.rule);
+                            ui.monospace(&string.identifier);
+                            ui.monospace(format!("0x{:x} ({})", string.offset, string.offset));
+                            ui.monospace(&string.data);
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        }
+
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+    }
+}
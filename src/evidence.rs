@@ -0,0 +1,298 @@
+// Evidence about files matched by YARA: hashes, size, owner, permissions, times and the RPM
+// package the file came from, with its checksum checked against the package database
+use crate::archives::ArchiveMember;
+use crate::findings::Finding;
+use crate::pool::ProgressTracker;
+use crate::rpm;
+use crate::scan::ScanMonitor;
+use md5::Md5;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct FileEvidence {
+    pub sha256: String,
+    pub md5: String,
+    pub size: u64,
+    pub owner: String,
+    pub group: String,
+    // As printed by `ls -l`, e.g. "-rwxr-xr-x"
+    pub permissions: String,
+    pub modified: String,
+    // Last inode change (ctime)
+    pub changed: String,
+    // False when rpm could not be run, the package is then unknown
+    pub rpm_checked: bool,
+    // None for files that do not belong to an installed package
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageCheck>,
+    // Why the file could not be read, None when it was
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<EvidenceError>,
+    // Message of the system for the error, e.g. "Permission denied (os error 13)"
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub error_detail: String,
+}
+
+// Why the evidence of a file is missing. Reports word it in their own language.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceError {
+    Metadata,
+    Unreadable,
+}
+
+impl EvidenceError {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EvidenceError::Metadata => "Не удалось получить сведения о файле",
+            EvidenceError::Unreadable => "Не удалось прочитать файл",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct PackageCheck {
+    pub package: String,
+    // Whether the file hash equals the digest of the package database,
+    // None when the database has no digest in an algorithm computed here
+    pub verified: Option<bool>,
+}
+
+impl FileEvidence {
+    // Files changed after installation from a package deserve the closest look
+    pub fn is_modified_package_file(&self) -> bool {
+        self.package.as_ref().is_some_and(|check| check.verified == Some(false))
+    }
+
+    pub fn package_status(&self) -> String {
+        match &self.package {
+            None if !self.rpm_checked => "принадлежность пакету RPM не проверена".to_string(),
+            None => "не принадлежит пакету RPM".to_string(),
+            Some(check) => match check.verified {
+                Some(true) => format!("пакет {}, совпадает с базой RPM", check.package),
+                Some(false) => format!("пакет {}, ИЗМЕНЕН относительно базы RPM", check.package),
+                None => format!("пакет {}, контрольная сумма не проверена", check.package),
+            },
+        }
+    }
+
+    // Multi-line description for hover texts and console output
+    pub fn describe(&self) -> String {
+        if let Some(error) = self.error {
+            return format!("{}: {}", error.label(), self.error_detail);
+        }
+        format!(
+            "SHA-256: {}\nMD5: {}\nРазмер: {} байт\nВладелец: {}:{}, права {}\nИзменен: {}, изменение inode: {}\n{}",
+            self.sha256,
+            self.md5,
+            self.size,
+            self.owner,
+            self.group,
+            self.permissions,
+            self.modified,
+            self.changed,
+            self.package_status()
+        )
+    }
+}
+
+// Names by id from /etc/passwd or /etc/group
+pub fn account_names(file: &str) -> HashMap<u32, String> {
+    std::fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            Some((fields.get(2)?.parse().ok()?, fields[0].to_string()))
+        })
+        .collect()
+}
+
+fn permissions(mode: u32) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut text = String::from(kind);
+    for (shift, special, set, unset) in [(6, 0o4000, 's', 'S'), (3, 0o2000, 's', 'S'), (0, 0o1000, 't', 'T')] {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+fn format_time(seconds: i64) -> String {
+    chrono::DateTime::from_timestamp(seconds, 0)
+        .map(|date| date.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// SHA-256 and MD5 in one pass over the file
+fn hashes(path: &Path) -> std::io::Result<(String, String)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut sha256 = Sha256::new();
+    let mut md5 = Md5::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+        md5.update(&buffer[..read]);
+    }
+    Ok((hex(&sha256.finalize()), hex(&md5.finalize())))
+}
+
+struct Accounts {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+fn collect(path: &Path, accounts: &Accounts) -> FileEvidence {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return FileEvidence {
+                error: Some(EvidenceError::Metadata),
+                error_detail: e.to_string(),
+                ..Default::default()
+            }
+        }
+    };
+    let name = |names: &HashMap<u32, String>, id: u32| names.get(&id).cloned().unwrap_or_else(|| id.to_string());
+
+    let mut evidence = FileEvidence {
+        size: metadata.len(),
+        owner: name(&accounts.users, metadata.uid()),
+        group: name(&accounts.groups, metadata.gid()),
+        permissions: permissions(metadata.mode()),
+        modified: format_time(metadata.mtime()),
+        changed: format_time(metadata.ctime()),
+        ..Default::default()
+    };
+    match hashes(path) {
+        Ok((sha256, md5)) => {
+            evidence.sha256 = sha256;
+            evidence.md5 = md5;
+        }
+        Err(e) => {
+            evidence.error = Some(EvidenceError::Unreadable);
+            evidence.error_detail = e.to_string();
+        }
+    }
+    evidence
+}
+
+// Owner, permissions and time of an archive member come from its archive entry
+fn describe_entry(evidence: &mut FileEvidence, member: &ArchiveMember) {
+    evidence.owner = member.owner.clone();
+    evidence.group = member.group.clone();
+    evidence.permissions = permissions(member.mode);
+    evidence.modified = if member.mtime > 0 { format_time(member.mtime) } else { String::new() };
+    evidence.changed = String::new();
+}
+
+fn check_package(evidence: &FileEvidence, owner: rpm::PackagedFile) -> PackageCheck {
+    let computed = match owner.algorithm {
+        1 => Some(&evidence.md5),
+        8 => Some(&evidence.sha256),
+        _ => None,
+    };
+    PackageCheck {
+        package: owner.package,
+        verified: computed
+            .filter(|hash| !hash.is_empty() && !owner.digest.is_empty())
+            .map(|hash| *hash == owner.digest),
+    }
+}
+
+// Attach evidence to the YARA file matches, once per file. Memory matches have no file, archive
+// members are read from their unpacked copy and are not looked up in the package database.
+// Returns the notes for the scan.
+pub fn attach(findings: &mut [Finding], monitor: &dyn ScanMonitor) -> Result<Vec<String>, String> {
+    let mut paths: Vec<(String, Option<ArchiveMember>)> = findings
+        .iter()
+        .filter(|f| f.process.is_none())
+        .map(|f| (f.target.clone(), f.archive.clone()))
+        .collect();
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    paths.dedup_by(|a, b| a.0 == b.0);
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let accounts = Accounts {
+        users: account_names("/etc/passwd"),
+        groups: account_names("/etc/group"),
+    };
+    let source = |(target, member): &(String, Option<ArchiveMember>)| -> PathBuf {
+        member.as_ref().map_or_else(|| PathBuf::from(target), |member| member.extracted.clone())
+    };
+    let sizes: Vec<u64> = paths.iter().map(|path| std::fs::metadata(source(path)).map_or(0, |meta| meta.len())).collect();
+    let tracker = ProgressTracker::new(monitor, "Хеширование", paths.len(), sizes.iter().sum());
+    let mut notes = Vec::new();
+    // Without rpm the remaining files are not looked up
+    let mut rpm_available = true;
+    let mut collected: HashMap<&str, FileEvidence> = HashMap::new();
+
+    for (path, size) in paths.iter().zip(&sizes) {
+        let (target, member) = path;
+        tracker.checkpoint()?;
+        tracker.start(target);
+        let mut evidence = collect(&source(path), &accounts);
+        if let Some(member) = member.as_ref().filter(|_| evidence.error.is_none()) {
+            describe_entry(&mut evidence, member);
+        } else if evidence.error.is_none() && rpm_available {
+            match rpm::file_owner(target) {
+                Ok(owner) => {
+                    evidence.rpm_checked = true;
+                    evidence.package = owner.map(|owner| check_package(&evidence, owner));
+                }
+                Err(e) => {
+                    notes.push(format!("Принадлежность файлов пакетам не проверена: {}", e));
+                    rpm_available = false;
+                }
+            }
+        }
+        tracker.finish(1, *size);
+        collected.insert(target, evidence);
+    }
+    tracker.done();
+
+    let modified = collected.values().filter(|evidence| evidence.is_modified_package_file()).count();
+    let failed = collected.values().filter(|evidence| evidence.error.is_some()).count();
+    notes.insert(0, format!("Собраны хеши и сведения о файлах с совпадениями: {}", collected.len() - failed));
+    if modified > 0 {
+        notes.push(format!("Файлов пакетов RPM, измененных после установки: {}", modified));
+    }
+    if failed > 0 {
+        notes.push(format!("Не удалось прочитать файлов с совпадениями: {}", failed));
+    }
+
+    for finding in findings.iter_mut().filter(|f| f.process.is_none()) {
+        finding.file = collected.get(finding.target.as_str()).cloned();
+    }
+    Ok(notes)
+}
@@ -0,0 +1,83 @@
+// Accepted risks: vulnerable OVAL definitions that cannot be patched yet, with an owner,
+// a reason and an expiry date. Findings under an active exception are listed apart from the
+// untriaged ones and reported as exceptions; once it expires the finding counts again.
+use crate::findings::{Engine, Finding};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+
+const EXCEPTIONS_FILE: &str = "risk-exceptions.json";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct RiskException {
+    // OVAL definition or XCCDF rule id
+    pub definition: String,
+    // Title of the definition when the exception was made
+    pub title: String,
+    pub owner: String,
+    pub reason: String,
+    // Last day of the exception, YYYY-MM-DD
+    pub expires: String,
+    pub created_at: String,
+}
+
+pub fn load() -> Result<Vec<RiskException>, String> {
+    storage::load(EXCEPTIONS_FILE)
+}
+
+pub fn save(exceptions: &[RiskException]) -> Result<(), String> {
+    storage::save(EXCEPTIONS_FILE, &exceptions)
+}
+
+impl RiskException {
+    pub fn for_finding(finding: &Finding) -> Self {
+        Self {
+            definition: finding.rule_id.clone(),
+            title: finding.title.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.definition.trim().is_empty() {
+            return Err("Укажите идентификатор определения OVAL".to_string());
+        }
+        if self.owner.trim().is_empty() {
+            return Err("Укажите ответственного за принятый риск".to_string());
+        }
+        if self.reason.trim().is_empty() {
+            return Err("Укажите причину принятия риска".to_string());
+        }
+        chrono::NaiveDate::parse_from_str(self.expires.trim(), DATE_FORMAT)
+            .map(|_| ())
+            .map_err(|_| format!("Неверная дата окончания '{}', нужен формат ГГГГ-ММ-ДД", self.expires.trim()))
+    }
+
+    // Invalid dates count as expired so a broken entry never hides a finding
+    pub fn is_expired(&self) -> bool {
+        chrono::NaiveDate::parse_from_str(self.expires.trim(), DATE_FORMAT)
+            .map_or(true, |date| date < chrono::Local::now().date_naive())
+    }
+}
+
+// Findings to present, OVAL ones carrying the active exception of their definition
+pub fn apply(findings: &[Finding], exceptions: &[RiskException]) -> Vec<Finding> {
+    findings
+        .iter()
+        .map(|f| {
+            let mut f = f.clone();
+            if f.engine == Engine::Oval {
+                f.exception = exceptions
+                    .iter()
+                    .find(|exception| exception.definition == f.rule_id && !exception.is_expired())
+                    .cloned();
+            }
+            f
+        })
+        .collect()
+}
+
+pub fn accepted(findings: &[Finding]) -> Vec<&Finding> {
+    findings.iter().filter(|f| f.exception.is_some()).collect()
+}
@@ -0,0 +1,160 @@
+// Accepted risks window: exceptions for OVAL definitions that cannot be patched yet
+use crate::activity::ActivityLog;
+use crate::exceptions::{self, RiskException};
+use crate::findings::Finding;
+use crate::scan::timestamp;
+use eframe::egui;
+
+pub struct ExceptionsPanel {
+    exceptions: Vec<RiskException>,
+    // Exception being filled in before it is added
+    draft: RiskException,
+    message: String,
+    // Bumped on every change of the list, so cached results can tell it changed
+    revision: u64,
+}
+
+impl Default for ExceptionsPanel {
+    fn default() -> Self {
+        let mut message = String::new();
+        let exceptions = exceptions::load().unwrap_or_else(|e| {
+            message = e;
+            Vec::new()
+        });
+
+        Self {
+            exceptions,
+            draft: RiskException::default(),
+            message,
+            revision: 0,
+        }
+    }
+}
+
+impl ExceptionsPanel {
+    pub fn list(&self) -> &[RiskException] {
+        &self.exceptions
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn save(&mut self) {
+        self.revision += 1;
+        if let Err(e) = exceptions::save(&self.exceptions) {
+            self.message = e;
+        }
+    }
+
+    // Start an exception for a finding of the results view
+    pub fn propose(&mut self, finding: &Finding) {
+        self.draft = RiskException::for_finding(finding);
+        self.message = "Укажите ответственного, причину и срок действия, затем нажмите «Принять риск»".to_string();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, activity: &ActivityLog) {
+        egui::Grid::new("exception_draft").num_columns(2).show(ui, |ui| {
+            ui.label("Определение:");
+            ui.add(egui::TextEdit::singleline(&mut self.draft.definition).hint_text("oval:ru.redsoft:def:1").desired_width(400.0));
+            ui.end_row();
+            ui.label("Ответственный:");
+            ui.add(egui::TextEdit::singleline(&mut self.draft.owner).desired_width(400.0));
+            ui.end_row();
+            ui.label("Действует до:");
+            ui.add(egui::TextEdit::singleline(&mut self.draft.expires).hint_text("ГГГГ-ММ-ДД").desired_width(200.0));
+            ui.end_row();
+            ui.label("Причина:");
+            ui.add(egui::TextEdit::multiline(&mut self.draft.reason).desired_rows(2).desired_width(400.0));
+            ui.end_row();
+        });
+        if !self.draft.title.is_empty() {
+            ui.label(format!("Название: {}", self.draft.title));
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Принять риск").clicked() {
+                let mut exception = self.draft.clone();
+                exception.definition = exception.definition.trim().to_string();
+                exception.expires = exception.expires.trim().to_string();
+                exception.created_at = timestamp();
+                match exception.validate() {
+                    Ok(()) => {
+                        self.message = format!(
+                            "Риск по {} принят до {}, ответственный {}",
+                            exception.definition, exception.expires, exception.owner
+                        );
+                        activity.push(format!("{} ({})", self.message, exception.reason.trim()));
+                        // A definition has one exception, a new one replaces the old
+                        self.exceptions.retain(|e| e.definition != exception.definition);
+                        self.exceptions.push(exception);
+                        self.draft = RiskException::default();
+                        self.save();
+                    }
+                    Err(e) => self.message = e,
+                }
+            }
+            if ui.button("Очистить форму").clicked() {
+                self.draft = RiskException::default();
+            }
+            let expired = self.exceptions.iter().filter(|e| e.is_expired()).count();
+            if ui.add_enabled(expired > 0, egui::Button::new(format!("Удалить истекшие ({})", expired))).clicked() {
+                self.exceptions.retain(|e| !e.is_expired());
+                self.message = format!("Удалено истекших исключений: {}", expired);
+                activity.push(self.message.clone());
+                self.save();
+            }
+        });
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+
+        ui.separator();
+        if self.exceptions.is_empty() {
+            ui.label("Принятых рисков нет");
+            return;
+        }
+        let mut remove = None;
+        let mut edit = None;
+        egui::Grid::new("risk_exceptions").striped(true).num_columns(6).show(ui, |ui| {
+            ui.strong("Определение");
+            ui.strong("Ответственный");
+            ui.strong("Причина");
+            ui.strong("Действует до");
+            ui.strong("Добавлено");
+            ui.end_row();
+            for (index, exception) in self.exceptions.iter().enumerate() {
+                let definition = ui.monospace(&exception.definition);
+                if !exception.title.is_empty() {
+                    definition.on_hover_text(&exception.title);
+                }
+                ui.label(&exception.owner);
+                ui.add(egui::Label::new(&exception.reason).wrap(true));
+                if exception.is_expired() {
+                    ui.colored_label(egui::Color32::RED, format!("{} (истекло)", exception.expires));
+                } else {
+                    ui.label(&exception.expires);
+                }
+                ui.label(&exception.created_at);
+                ui.horizontal(|ui| {
+                    if ui.small_button("Изменить").clicked() {
+                        edit = Some(index);
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(index);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if let Some(index) = edit {
+            self.draft = self.exceptions[index].clone();
+        }
+        if let Some(index) = remove {
+            let removed = self.exceptions.remove(index);
+            self.message = format!("Исключение для {} удалено", removed.definition);
+            activity.push(self.message.clone());
+            self.save();
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// Machine-readable export of parsed findings (JSON, CSV, SARIF)
+use crate::findings::{Engine, Finding};
+use serde_json::json;
+use std::path::Path;
+
+// Bumped whenever a field is renamed or removed from the JSON export
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Sarif,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Csv, ExportFormat::Sarif];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Sarif => "SARIF",
+        }
+    }
+
+    // Format named on the command line by its extension, e.g. "sarif"
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        ExportFormat::ALL.into_iter().find(|format| format.extension() == name.to_lowercase())
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Sarif => "sarif",
+        }
+    }
+}
+
+pub fn render(findings: &[Finding], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => to_json(findings),
+        ExportFormat::Csv => to_csv(findings),
+        ExportFormat::Sarif => to_sarif(findings),
+    }
+}
+
+pub fn write_to_file(findings: &[Finding], format: ExportFormat, path: &Path) -> std::io::Result<()> {
+    std::fs::write(path, render(findings, format))
+}
+
+fn to_json(findings: &[Finding]) -> String {
+    let document = json!({
+        "schema_version": JSON_SCHEMA_VERSION,
+        "generator": concat!("Security Scanner GUI ", env!("CARGO_PKG_VERSION")),
+        "generated_at": chrono::Local::now().to_rfc3339(),
+        "findings": findings,
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+fn to_csv(findings: &[Finding]) -> String {
+    let mut out = String::from("engine,rule_id,title,severity,target,references,tags,cvss,packages,sha256,md5,rpm_package,accepted_until\n");
+    for f in findings {
+        let packages: Vec<String> = f
+            .packages
+            .iter()
+            .map(|p| format!("{} {} -> {}", p.name, p.installed_version, p.fixed_version))
+            .collect();
+        let row = [
+            f.engine.tool_name().to_string(),
+            f.rule_id.clone(),
+            f.title.clone(),
+            f.severity.clone(),
+            f.target.clone(),
+            f.references.join(" "),
+            f.tags.join(" "),
+            f.cvss_score().map(|score| format!("{:.1}", score)).unwrap_or_default(),
+            packages.join("; "),
+            f.file.as_ref().map(|file| file.sha256.clone()).unwrap_or_default(),
+            f.file.as_ref().map(|file| file.md5.clone()).unwrap_or_default(),
+            f.file.as_ref().and_then(|file| file.package.as_ref()).map(|check| check.package.clone()).unwrap_or_default(),
+            f.exception.as_ref().map(|exception| exception.expires.clone()).unwrap_or_default(),
+        ];
+        let escaped: Vec<String> = row.iter().map(|field| csv_escape(field)).collect();
+        out.push_str(&escaped.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+// Spreadsheets take fields starting with these as formulas, and file paths or rule metadata
+// can start with anything
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+fn csv_escape(field: &str) -> String {
+    let field = if field.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+// SARIF 2.1.0 with one run per engine
+fn to_sarif(findings: &[Finding]) -> String {
+    let mut runs = Vec::new();
+
+    for engine in [Engine::Oval, Engine::Yara] {
+        let engine_findings: Vec<&Finding> = findings.iter().filter(|f| f.engine == engine).collect();
+        if engine_findings.is_empty() {
+            continue;
+        }
+
+        let mut rule_ids: Vec<&str> = Vec::new();
+        let mut rules = Vec::new();
+        for f in &engine_findings {
+            if !rule_ids.contains(&f.rule_id.as_str()) {
+                rule_ids.push(&f.rule_id);
+                let mut properties = json!({ "tags": f.tags });
+                // Read by code scanning tools to rank results
+                if let Some(score) = f.cvss_score() {
+                    properties["security-severity"] = json!(format!("{:.1}", score));
+                }
+                rules.push(json!({
+                    "id": f.rule_id,
+                    "shortDescription": { "text": f.title },
+                    "properties": properties,
+                }));
+            }
+        }
+
+        let results: Vec<_> = engine_findings
+            .iter()
+            .map(|f| {
+                let location = match engine {
+                    Engine::Oval => json!({
+                        "logicalLocations": [{ "name": f.target, "kind": "host" }],
+                    }),
+                    Engine::Yara if f.process.is_some() => json!({
+                        "logicalLocations": [{ "name": f.target, "kind": "process" }],
+                    }),
+                    // The path inside an archive is kept in the properties
+                    Engine::Yara => json!({
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": file_uri(f.archive.as_ref().map_or(&f.target, |member| &member.archive)) },
+                        },
+                    }),
+                };
+                let mut properties = json!({ "references": f.references, "cves": f.cves, "packages": f.packages });
+                if let Some(file) = &f.file {
+                    properties["file"] = json!(file);
+                }
+                if let Some(member) = &f.archive {
+                    properties["archiveMember"] = json!(member.member);
+                }
+                let mut result = json!({
+                    "ruleId": f.rule_id,
+                    "level": sarif_level(&f.severity),
+                    "message": { "text": f.title },
+                    "locations": [location],
+                    "properties": properties,
+                });
+                // Accepted risks are reported as suppressed results
+                if let Some(exception) = &f.exception {
+                    result["suppressions"] = json!([{
+                        "kind": "external",
+                        "status": "accepted",
+                        "justification": format!("{} ({}, до {})", exception.reason, exception.owner, exception.expires),
+                    }]);
+                }
+                result
+            })
+            .collect();
+
+        runs.push(json!({
+            "tool": { "driver": { "name": engine.tool_name(), "rules": rules } },
+            "results": results,
+        }));
+    }
+
+    let document = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": runs,
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+fn sarif_level(severity: &str) -> &'static str {
+    match severity.to_lowercase().as_str() {
+        "critical" | "high" | "important" => "error",
+        "medium" | "moderate" => "warning",
+        "low" => "note",
+        // YARA matches carry no severity, but every match needs attention
+        _ => "warning",
+    }
+}
+
+fn file_uri(path: &str) -> String {
+    if path.starts_with('/') {
+        let encoded = path
+            .replace('%', "%25")
+            .replace(' ', "%20")
+            .replace('#', "%23")
+            .replace('?', "%3F");
+        format!("file://{}", encoded)
+    } else {
+        path.to_string()
+    }
+}
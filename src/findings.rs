@@ -0,0 +1,602 @@
+// Parsed scan findings shared by the exporters and the GUI
+use crate::archives::ArchiveMember;
+use crate::evidence::FileEvidence;
+use crate::exceptions::RiskException;
+use crate::processes::ProcessInfo;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// Scanning engine that produced a finding
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Oval,
+    Yara,
+}
+
+impl Engine {
+    pub fn tool_name(&self) -> &'static str {
+        match self {
+            Engine::Oval => "OpenSCAP",
+            Engine::Yara => "YARA",
+        }
+    }
+}
+
+// A single finding: a vulnerable OVAL definition or a YARA rule match
+#[derive(Serialize, Clone, Debug)]
+pub struct Finding {
+    pub engine: Engine,
+    // OVAL definition id or YARA rule name (with namespace, if any)
+    pub rule_id: String,
+    pub title: String,
+    // Empty when the source does not provide a severity
+    pub severity: String,
+    // Scanned host for OVAL, matched file or process for YARA
+    pub target: String,
+    pub references: Vec<String>,
+    // Advisory links from the OVAL metadata
+    pub urls: Vec<String>,
+    pub tags: Vec<String>,
+    // CVEs fixed by an OVAL patch definition
+    pub cves: Vec<CveInfo>,
+    // Packages that have to be upgraded to fix an OVAL finding
+    pub packages: Vec<AffectedPackage>,
+    // Scanned process of a YARA memory match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<ProcessInfo>,
+    // Hashes, metadata and package of the file of a YARA match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<FileEvidence>,
+    // Archive and path inside it of a YARA match in an unpacked file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveMember>,
+    // Accepted risk of an OVAL finding, set when the results are presented
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exception: Option<RiskException>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct CveInfo {
+    pub id: String,
+    pub cvss_score: Option<f32>,
+    pub cvss_vector: String,
+    pub severity: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct AffectedPackage {
+    pub name: String,
+    // Installed EVR as collected by oscap, empty if unknown
+    pub installed_version: String,
+    pub fixed_version: String,
+    // Matching entries of the local RPM database
+    pub installed: Vec<InstalledRpm>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct InstalledRpm {
+    pub nevra: String,
+    pub evr: String,
+    pub vendor: String,
+    pub install_date: String,
+}
+
+impl Finding {
+    // Highest CVSS score of the referenced CVEs
+    pub fn cvss_score(&self) -> Option<f32> {
+        self.cves
+            .iter()
+            .filter_map(|cve| cve.cvss_score)
+            .fold(None, |max, score| Some(max.map_or(score, |m: f32| m.max(score))))
+    }
+
+    // Risk used for sorting: CVSS score, or an estimate from the severity
+    pub fn risk(&self) -> f32 {
+        self.cvss_score().unwrap_or_else(|| match self.severity.to_lowercase().as_str() {
+            "critical" => 9.5,
+            "high" | "important" => 7.5,
+            "medium" | "moderate" => 5.0,
+            "low" => 2.5,
+            _ => 0.0,
+        })
+    }
+
+    // Link to the advisory of a reference, falling back to public databases
+    pub fn reference_url(&self, reference: &str) -> Option<String> {
+        if let Some(url) = self.urls.iter().find(|url| url.contains(reference)) {
+            return Some(url.clone());
+        }
+        if reference.starts_with("CVE-") {
+            Some(format!("https://nvd.nist.gov/vuln/detail/{}", reference))
+        } else {
+            reference
+                .strip_prefix("BDU:")
+                .map(|id| format!("https://bdu.fstec.ru/vul/{}", id))
+        }
+    }
+}
+
+// Context of the last run of an engine, shown in reports
+#[derive(Serialize, Clone, Debug)]
+pub struct ScanRun {
+    pub engine: Engine,
+    pub started_at: String,
+    // Full command line, including the selected options
+    pub command: String,
+    // OVAL content generator/timestamp or the YARA rules file
+    pub content_version: String,
+    // Per-target results of a YARA scan
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetResult>,
+}
+
+// Why a target was not scanned. Reports word it in their own language.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetError {
+    NotFound,
+}
+
+impl TargetError {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TargetError::NotFound => "путь не найден",
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TargetResult {
+    pub path: String,
+    // Files left for scanning after the filters, a file of nested targets counts once
+    pub files: usize,
+    pub matches: usize,
+    // Why the target was not scanned, None when it was
+    pub error: Option<TargetError>,
+}
+
+// Parsed `oscap oval eval` or `oscap xccdf eval` results file
+pub struct OvalResults {
+    pub findings: Vec<Finding>,
+    pub content_version: String,
+}
+
+// Parse an `oscap oval eval --results` file and collect the definitions evaluated to "true"
+pub fn parse_oval_results(xml: &str) -> Result<OvalResults, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Ошибка разбора XML: {}", e))?;
+
+    // Generator of the evaluated content, not the one of oscap's own results
+    let content_version = doc
+        .descendants()
+        .find(|n| n.has_tag_name("oval_definitions"))
+        .and_then(|defs| defs.children().find(|n| n.has_tag_name("generator")))
+        .map(|generator| {
+            generator
+                .children()
+                .filter(|n| n.is_element())
+                .filter_map(|n| n.text().map(|t| format!("{}: {}", n.tag_name().name(), t.trim())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+
+    let host = doc
+        .descendants()
+        .find(|n| n.has_tag_name("primary_host_name"))
+        .and_then(|n| n.text())
+        .unwrap_or("localhost")
+        .to_string();
+
+    // Results reference definitions by id, metadata lives in oval_definitions
+    let definitions: HashMap<&str, roxmltree::Node> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("definition"))
+        .filter_map(|n| n.attribute("id").map(|id| (id, n)))
+        .collect();
+
+    // Package checks: test -> (object, state), object -> package name, state -> fixed EVR
+    let mut tests: HashMap<&str, (&str, Option<&str>)> = HashMap::new();
+    let mut package_names: HashMap<&str, &str> = HashMap::new();
+    let mut fixed_versions: HashMap<&str, &str> = HashMap::new();
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        let id = match node.attribute("id") {
+            Some(id) => id,
+            None => continue,
+        };
+        let child_text = |name: &str| {
+            node.children()
+                .find(|c| c.has_tag_name(name))
+                .and_then(|c| c.text())
+                .map(str::trim)
+        };
+        let tag = node.tag_name().name();
+        if tag.ends_with("_test") {
+            let reference = |name: &str, attribute: &str| {
+                node.children()
+                    .find(|c| c.has_tag_name(name))
+                    .and_then(|c| c.attribute(attribute))
+            };
+            if let Some(object) = reference("object", "object_ref") {
+                tests.insert(id, (object, reference("state", "state_ref")));
+            }
+        } else if tag.ends_with("_object") {
+            if let Some(name) = child_text("name") {
+                package_names.insert(id, name);
+            }
+        } else if tag.ends_with("_state") {
+            let evr = node
+                .children()
+                .find(|c| c.has_tag_name("evr") && c.attribute("operation") == Some("less than"))
+                .and_then(|c| c.text());
+            if let Some(evr) = evr {
+                fixed_versions.insert(id, evr.trim());
+            }
+        }
+    }
+
+    // Installed versions collected by oscap: object -> items -> EVR
+    let item_versions: HashMap<&str, &str> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("system_data"))
+        .flat_map(|data| data.children().filter(|n| n.is_element()))
+        .filter_map(|item| {
+            let evr = item.children().find(|c| c.has_tag_name("evr"))?.text()?;
+            Some((item.attribute("id")?, evr.trim()))
+        })
+        .collect();
+    let mut installed_versions: HashMap<&str, Vec<&str>> = HashMap::new();
+    for object in doc
+        .descendants()
+        .filter(|n| n.has_tag_name("collected_objects"))
+        .flat_map(|objects| objects.children().filter(|n| n.has_tag_name("object")))
+    {
+        if let Some(id) = object.attribute("id") {
+            let versions = object
+                .children()
+                .filter_map(|r| r.attribute("item_ref"))
+                .filter_map(|item| item_versions.get(item).copied())
+                .collect();
+            installed_versions.insert(id, versions);
+        }
+    }
+
+    let mut findings = Vec::new();
+
+    for result in doc
+        .descendants()
+        .filter(|n| n.has_tag_name("definition") && n.attribute("definition_id").is_some())
+    {
+        if result.attribute("result") != Some("true") {
+            continue;
+        }
+        let id = result.attribute("definition_id").unwrap_or_default();
+
+        let definition = definitions.get(id);
+
+        let mut finding = Finding {
+            engine: Engine::Oval,
+            rule_id: id.to_string(),
+            title: String::new(),
+            severity: String::new(),
+            target: host.clone(),
+            references: Vec::new(),
+            urls: Vec::new(),
+            tags: Vec::new(),
+            cves: Vec::new(),
+            packages: Vec::new(),
+            process: None,
+            file: None,
+            archive: None,
+            exception: None,
+        };
+
+        if let Some(definition) = definition {
+            if let Some(class) = definition.attribute("class") {
+                finding.tags.push(class.to_string());
+            }
+            for node in definition.descendants() {
+                match node.tag_name().name() {
+                    "title" => finding.title = node.text().unwrap_or_default().trim().to_string(),
+                    "severity" => finding.severity = node.text().unwrap_or_default().trim().to_string(),
+                    "reference" => {
+                        if let Some(ref_id) = node.attribute("ref_id") {
+                            finding.references.push(ref_id.to_string());
+                        }
+                        if let Some(ref_url) = node.attribute("ref_url") {
+                            finding.urls.push(ref_url.to_string());
+                        }
+                    }
+                    // Advisory entry like <cve cvss3="7.5/CVSS:3.1/AV:N/..." impact="high">
+                    "cve" => {
+                        let (cvss_score, cvss_vector) = node
+                            .attribute("cvss3")
+                            .or_else(|| node.attribute("cvss2"))
+                            .map(parse_cvss)
+                            .unwrap_or_default();
+                        finding.cves.push(CveInfo {
+                            id: node.text().unwrap_or_default().trim().to_string(),
+                            cvss_score,
+                            cvss_vector,
+                            severity: node.attribute("impact").unwrap_or_default().to_string(),
+                        });
+                    }
+                    "criterion" => {
+                        let check = node.attribute("test_ref").and_then(|test| tests.get(test));
+                        if let Some((object, Some(state))) = check {
+                            if let (Some(name), Some(fixed)) = (package_names.get(object), fixed_versions.get(state)) {
+                                if !finding.packages.iter().any(|p| p.name == *name) {
+                                    finding.packages.push(AffectedPackage {
+                                        name: name.to_string(),
+                                        installed_version: installed_versions
+                                            .get(object)
+                                            .map(|versions| versions.join(", "))
+                                            .unwrap_or_default(),
+                                        fixed_version: fixed.to_string(),
+                                        installed: Vec::new(),
+                                    });
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // CVEs only listed as references, without advisory details
+        for reference in &finding.references {
+            if reference.starts_with("CVE-") && !finding.cves.iter().any(|c| &c.id == reference) {
+                finding.cves.push(CveInfo {
+                    id: reference.clone(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        findings.push(finding);
+    }
+
+    Ok(OvalResults {
+        findings,
+        content_version,
+    })
+}
+
+// Parse an `oscap xccdf eval --results` file and collect the failed rules
+pub fn parse_xccdf_results(xml: &str) -> Result<OvalResults, String> {
+    let doc = roxmltree::Document::parse(xml).map_err(|e| format!("Ошибка разбора XML: {}", e))?;
+
+    let benchmark = doc
+        .descendants()
+        .find(|n| n.has_tag_name("Benchmark"))
+        .ok_or("В файле нет результатов XCCDF")?;
+    let content_version = benchmark
+        .children()
+        .find(|n| n.has_tag_name("version"))
+        .and_then(|n| n.text())
+        .map(|version| format!("{} {}", benchmark.attribute("id").unwrap_or_default(), version.trim()))
+        .unwrap_or_default();
+
+    // Rules can be nested in groups
+    let rules: HashMap<&str, roxmltree::Node> = benchmark
+        .descendants()
+        .filter(|n| n.has_tag_name("Rule"))
+        .filter_map(|n| n.attribute("id").map(|id| (id, n)))
+        .collect();
+
+    let test_result = doc
+        .descendants()
+        .find(|n| n.has_tag_name("TestResult"))
+        .ok_or("В файле нет результатов проверки (TestResult)")?;
+    let host = test_result
+        .children()
+        .find(|n| n.has_tag_name("target"))
+        .and_then(|n| n.text())
+        .unwrap_or("localhost")
+        .to_string();
+
+    let mut findings = Vec::new();
+    for result in test_result.children().filter(|n| n.has_tag_name("rule-result")) {
+        let failed = result
+            .children()
+            .find(|n| n.has_tag_name("result"))
+            .and_then(|n| n.text())
+            .is_some_and(|text| text.trim() == "fail");
+        let id = match result.attribute("idref") {
+            Some(id) if failed => id,
+            _ => continue,
+        };
+        let rule = rules.get(id);
+
+        let title = rule
+            .and_then(|rule| rule.children().find(|n| n.has_tag_name("title")))
+            .and_then(|n| n.text())
+            .map(str::trim)
+            .unwrap_or(id)
+            .to_string();
+        let severity = result
+            .attribute("severity")
+            .or_else(|| rule.and_then(|rule| rule.attribute("severity")))
+            .filter(|severity| *severity != "unknown")
+            .unwrap_or_default()
+            .to_string();
+
+        // Identifiers (CVE, CCE) and reference links of the rule
+        let mut references: Vec<String> = Vec::new();
+        let mut urls = Vec::new();
+        for node in result.children().chain(rule.into_iter().flat_map(|rule| rule.children())) {
+            if node.has_tag_name("ident") {
+                if let Some(text) = node.text().map(str::trim) {
+                    if !references.iter().any(|r| r == text) {
+                        references.push(text.to_string());
+                    }
+                }
+            } else if node.has_tag_name("reference") {
+                if let Some(href) = node.attribute("href").filter(|href| href.starts_with("http")) {
+                    if !urls.iter().any(|u| u == href) {
+                        urls.push(href.to_string());
+                    }
+                }
+            }
+        }
+
+        let cves = references
+            .iter()
+            .filter(|r| r.starts_with("CVE-"))
+            .map(|id| CveInfo {
+                id: id.clone(),
+                ..Default::default()
+            })
+            .collect();
+
+        findings.push(Finding {
+            engine: Engine::Oval,
+            rule_id: id.to_string(),
+            title,
+            severity,
+            target: host.clone(),
+            references,
+            urls,
+            tags: Vec::new(),
+            cves,
+            packages: Vec::new(),
+            process: None,
+            file: None,
+            archive: None,
+            exception: None,
+        });
+    }
+
+    Ok(OvalResults {
+        findings,
+        content_version,
+    })
+}
+
+// Split "7.5/CVSS:3.1/AV:N/..." into the base score and the vector
+fn parse_cvss(value: &str) -> (Option<f32>, String) {
+    match value.split_once('/') {
+        Some((score, vector)) => (score.parse().ok(), vector.to_string()),
+        None => (value.parse().ok(), String::new()),
+    }
+}
+
+// Parse the text output of the `yara` command line tool
+pub fn parse_yara_output(stdout: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for line in stdout.lines() {
+        let line = line.trim_end();
+        // Skip empty lines and matched strings printed by `-s`
+        if line.is_empty() || line.starts_with("0x") {
+            continue;
+        }
+
+        let (rule_id, mut rest) = match line.split_once(' ') {
+            Some(parts) => parts,
+            None => continue,
+        };
+
+        let mut tags = Vec::new();
+        let mut meta = Vec::new();
+
+        // Optional "[tags]" and "[meta]" blocks printed by `-g` and `-m`
+        while rest.starts_with('[') {
+            let end = match bracket_end(rest) {
+                Some(end) => end,
+                None => break,
+            };
+            let block = &rest[1..end];
+            if block.contains('=') {
+                meta.push(block.to_string());
+            } else {
+                tags.extend(
+                    block
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|t| !t.is_empty())
+                        .map(str::to_string),
+                );
+            }
+            rest = rest[end + 1..].trim_start();
+        }
+
+        findings.push(Finding {
+            engine: Engine::Yara,
+            rule_id: rule_id.to_string(),
+            title: if meta.is_empty() { rule_id.to_string() } else { meta.join(" ") },
+            severity: String::new(),
+            target: rest.to_string(),
+            references: Vec::new(),
+            urls: Vec::new(),
+            tags,
+            cves: Vec::new(),
+            packages: Vec::new(),
+            process: None,
+            file: None,
+            archive: None,
+            exception: None,
+        });
+    }
+
+    findings
+}
+
+// String of a rule matched at an offset, printed by `yara -s`
+#[derive(Clone)]
+pub struct StringMatch {
+    pub rule: String,
+    pub offset: u64,
+    pub identifier: String,
+    pub data: String,
+}
+
+// String lines look like "0x1a:$a: data", or "0x1a:5:$a: data" with the length printed.
+// They follow the line of the matching rule.
+pub fn parse_yara_strings(stdout: &str) -> Vec<StringMatch> {
+    let mut matches = Vec::new();
+    let mut rule = String::new();
+
+    for line in stdout.lines() {
+        let line = line.trim_end();
+        let Some(rest) = line.strip_prefix("0x") else {
+            if let Some((rule_id, _)) = line.split_once(' ') {
+                rule = rule_id.to_string();
+            }
+            continue;
+        };
+
+        let Some((offset, mut rest)) = rest.split_once(':') else {
+            continue;
+        };
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            rest = rest.split_once(':').map_or("", |(_, rest)| rest);
+        }
+        let (identifier, data) = rest.split_once(": ").unwrap_or((rest.trim_end_matches(':'), ""));
+        matches.push(StringMatch {
+            rule: rule.clone(),
+            offset: u64::from_str_radix(offset, 16).unwrap_or_default(),
+            identifier: identifier.to_string(),
+            data: data.to_string(),
+        });
+    }
+
+    matches
+}
+
+// Find the closing bracket of a block, ignoring brackets inside quoted meta values
+fn bracket_end(s: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ']' if !in_quotes => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
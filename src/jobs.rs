@@ -0,0 +1,277 @@
+// Background jobs: queued and running scans, each with its own output and cancel handle
+use crate::console::{Console, LineKind};
+use crate::pool::ScanProgress;
+use crate::scan::{timestamp, OutputStream, ScanMonitor, CANCELLED};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+// Jobs above this limit wait in the queue
+pub const MAX_RUNNING: usize = 4;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "В очереди",
+            JobStatus::Running => "Выполняется",
+            JobStatus::Finished => "Завершено",
+            JobStatus::Failed => "Ошибка",
+            JobStatus::Cancelled => "Отменено",
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+pub struct Job {
+    pub id: u64,
+    // Tool or subsystem that started the job, e.g. "OpenSCAP"
+    pub group: String,
+    pub title: String,
+    pub status: JobStatus,
+    // Shared with the running task, which appends to it outside the queue lock
+    pub output: Arc<Mutex<Console>>,
+    // None while the tool does not report progress
+    pub progress: Option<f32>,
+    // Files, bytes and speed of file scans, which can also be paused
+    pub scan_progress: Option<ScanProgress>,
+    pub created_at: String,
+    pub finished_at: String,
+    cancel: Arc<AtomicBool>,
+    pause: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn is_paused(&self) -> bool {
+        self.pause.load(Ordering::Relaxed)
+    }
+}
+
+struct Queue {
+    jobs: Vec<Job>,
+    next_id: u64,
+    running: usize,
+}
+
+#[derive(Clone)]
+pub struct JobManager {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self {
+            queue: Arc::new((
+                Mutex::new(Queue {
+                    jobs: Vec::new(),
+                    next_id: 1,
+                    running: 0,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+}
+
+// Given to the task of a job; reports output and progress back to the manager
+pub struct JobHandle {
+    id: u64,
+    manager: JobManager,
+    cancel: Arc<AtomicBool>,
+    pause: Arc<AtomicBool>,
+    output: Arc<Mutex<Console>>,
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Job)) {
+        self.manager.update(self.id, change);
+    }
+}
+
+impl ScanMonitor for JobHandle {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn is_paused(&self) -> bool {
+        self.pause.load(Ordering::Relaxed)
+    }
+
+    fn output(&self, stream: OutputStream, line: &str) {
+        self.output.lock().unwrap().push(stream.into(), line);
+    }
+
+    fn progress(&self, fraction: f32) {
+        self.update(|job| job.progress = Some(fraction.clamp(0.0, 1.0)));
+    }
+
+    fn scan_progress(&self, progress: &ScanProgress) {
+        self.update(|job| job.scan_progress = Some(progress.clone()));
+    }
+}
+
+impl JobManager {
+    // Queue a task. It returns a summary on success and an error message otherwise,
+    // both are appended to the output of the job.
+    pub fn spawn<F>(&self, group: &str, title: &str, task: F) -> u64
+    where
+        F: FnOnce(&JobHandle) -> Result<String, String> + Send + 'static,
+    {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let pause = Arc::new(AtomicBool::new(false));
+        let output = Arc::new(Mutex::new(Console::default()));
+        let id = {
+            let mut queue = self.queue.0.lock().unwrap();
+            let id = queue.next_id;
+            queue.next_id += 1;
+            queue.jobs.push(Job {
+                id,
+                group: group.to_string(),
+                title: title.to_string(),
+                status: JobStatus::Queued,
+                output: Arc::clone(&output),
+                progress: None,
+                scan_progress: None,
+                created_at: timestamp(),
+                finished_at: String::new(),
+                cancel: Arc::clone(&cancel),
+                pause: Arc::clone(&pause),
+            });
+            id
+        };
+
+        let handle = JobHandle {
+            id,
+            manager: self.clone(),
+            cancel,
+            pause,
+            output,
+        };
+        std::thread::spawn(move || {
+            if !handle.manager.wait_for_slot(id) {
+                return;
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| task(&handle)))
+                .unwrap_or_else(|_| Err("Задание аварийно завершилось.".to_string()));
+            handle.manager.finish(id, result);
+        });
+
+        id
+    }
+
+    // Jobs start in the order they were queued. Returns false if cancelled while waiting.
+    fn wait_for_slot(&self, id: u64) -> bool {
+        let (lock, condvar) = &*self.queue;
+        let mut queue = lock.lock().unwrap();
+        loop {
+            let first_queued = queue.jobs.iter().find(|job| job.status == JobStatus::Queued).map(|job| job.id);
+            let running = queue.running;
+            let Some(job) = queue.jobs.iter_mut().find(|job| job.id == id) else {
+                return false;
+            };
+
+            if job.cancel.load(Ordering::Relaxed) {
+                job.status = JobStatus::Cancelled;
+                job.output.lock().unwrap().push(LineKind::App, CANCELLED);
+                job.finished_at = timestamp();
+                condvar.notify_all();
+                return false;
+            }
+            if running < MAX_RUNNING && first_queued == Some(id) {
+                job.status = JobStatus::Running;
+                queue.running += 1;
+                return true;
+            }
+
+            // Wake up periodically to notice cancellation
+            queue = condvar.wait_timeout(queue, Duration::from_millis(200)).unwrap().0;
+        }
+    }
+
+    fn finish(&self, id: u64, result: Result<String, String>) {
+        let (lock, condvar) = &*self.queue;
+        let mut queue = lock.lock().unwrap();
+        queue.running -= 1;
+
+        if let Some(job) = queue.jobs.iter_mut().find(|job| job.id == id) {
+            let cancelled = job.cancel.load(Ordering::Relaxed);
+            job.pause.store(false, Ordering::Relaxed);
+            let message = match result {
+                Ok(summary) => {
+                    job.status = JobStatus::Finished;
+                    job.progress = Some(1.0);
+                    summary
+                }
+                Err(e) => {
+                    job.status = if cancelled { JobStatus::Cancelled } else { JobStatus::Failed };
+                    e
+                }
+            };
+            job.output.lock().unwrap().push(LineKind::App, &message);
+            job.finished_at = timestamp();
+        }
+        condvar.notify_all();
+    }
+
+    fn update(&self, id: u64, change: impl FnOnce(&mut Job)) {
+        let mut queue = self.queue.0.lock().unwrap();
+        if let Some(job) = queue.jobs.iter_mut().find(|job| job.id == id) {
+            change(job);
+        }
+    }
+
+    pub fn cancel(&self, id: u64) {
+        self.update(id, |job| job.cancel.store(true, Ordering::Relaxed));
+        self.queue.1.notify_all();
+    }
+
+    // Only file scans react, see `ScanMonitor::is_paused`
+    pub fn set_paused(&self, id: u64, paused: bool) {
+        self.update(id, |job| job.pause.store(paused, Ordering::Relaxed));
+    }
+
+    // Forget finished, failed and cancelled jobs
+    pub fn clear_finished(&self) {
+        self.queue.0.lock().unwrap().jobs.retain(|job| job.status.is_active());
+    }
+
+    // Access the jobs under the queue lock
+    pub fn with_jobs<R>(&self, read: impl FnOnce(&[Job]) -> R) -> R {
+        read(&self.queue.0.lock().unwrap().jobs)
+    }
+
+    pub fn active_in(&self, group: &str) -> usize {
+        self.with_jobs(|jobs| {
+            jobs.iter()
+                .filter(|job| job.group == group && job.status.is_active())
+                .count()
+        })
+    }
+
+    pub fn is_active(&self, group: &str, title: &str) -> bool {
+        self.with_jobs(|jobs| {
+            jobs.iter()
+                .any(|job| job.group == group && job.title == title && job.status.is_active())
+        })
+    }
+
+    pub fn has_active(&self) -> bool {
+        self.with_jobs(|jobs| jobs.iter().any(|job| job.status.is_active()))
+    }
+}
@@ -0,0 +1,127 @@
+// Jobs panel: queued and running scans with their progress and output
+use crate::console::ConsoleView;
+use crate::jobs::{self, JobManager, JobStatus};
+use eframe::egui;
+use std::sync::Arc;
+
+fn status_color(status: JobStatus) -> egui::Color32 {
+    match status {
+        JobStatus::Queued => egui::Color32::GRAY,
+        JobStatus::Running => egui::Color32::LIGHT_BLUE,
+        JobStatus::Finished => egui::Color32::GREEN,
+        JobStatus::Failed => egui::Color32::RED,
+        JobStatus::Cancelled => egui::Color32::YELLOW,
+    }
+}
+
+pub struct JobsPanel {
+    // Job whose output is shown in the console
+    selected: Option<u64>,
+    console: ConsoleView,
+}
+
+impl Default for JobsPanel {
+    fn default() -> Self {
+        Self {
+            selected: None,
+            console: ConsoleView::new("job_console"),
+        }
+    }
+}
+
+impl JobsPanel {
+    pub fn show(&mut self, ui: &mut egui::Ui, manager: &JobManager) {
+        ui.horizontal(|ui| {
+            ui.strong("Задания");
+            ui.label(format!("(одновременно выполняется не более {})", jobs::MAX_RUNNING));
+            if ui.button("Очистить завершенные").clicked() {
+                manager.clear_finished();
+            }
+        });
+
+        // Cancel and pause after the jobs are released, the manager is locked while drawing
+        let mut cancel = Vec::new();
+        let mut pause = Vec::new();
+        let mut selected = self.selected;
+
+        egui::ScrollArea::vertical()
+            .id_source("jobs_list")
+            .max_height(120.0)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                manager.with_jobs(|jobs| {
+                    if jobs.is_empty() {
+                        ui.label("Нет заданий");
+                    }
+
+                    for job in jobs.iter().rev() {
+                        ui.horizontal(|ui| {
+                            if ui
+                                .selectable_label(selected == Some(job.id), format!("#{} {}: {}", job.id, job.group, job.title))
+                                .clicked()
+                            {
+                                selected = Some(job.id);
+                            }
+                            if job.status == JobStatus::Running && job.is_paused() {
+                                ui.colored_label(egui::Color32::YELLOW, "Приостановлено");
+                            } else {
+                                ui.colored_label(status_color(job.status), job.status.label());
+                            }
+                            match (job.status, job.progress) {
+                                (JobStatus::Running, Some(progress)) => {
+                                    ui.add(egui::ProgressBar::new(progress).desired_width(150.0).show_percentage());
+                                }
+                                (JobStatus::Running, None) => {
+                                    ui.spinner();
+                                }
+                                _ => {}
+                            }
+                            if job.finished_at.is_empty() {
+                                ui.small(format!("создано {}", job.created_at));
+                            } else {
+                                ui.small(format!("{} — {}", job.created_at, job.finished_at));
+                            }
+                            if job.status == JobStatus::Running && job.scan_progress.is_some() {
+                                let paused = job.is_paused();
+                                if ui.button(if paused { "Продолжить" } else { "Пауза" }).clicked() {
+                                    pause.push((job.id, !paused));
+                                }
+                            }
+                            if job.status.is_active() && ui.button("Отменить").clicked() {
+                                cancel.push(job.id);
+                            }
+                        });
+                        if let Some(progress) = &job.scan_progress {
+                            ui.small(progress.summary());
+                            if job.status == JobStatus::Running && !progress.current.is_empty() {
+                                ui.add(egui::Label::new(egui::RichText::new(&progress.current).small().monospace()).wrap(true));
+                            }
+                        }
+                    }
+                });
+            });
+
+        for id in cancel {
+            manager.cancel(id);
+        }
+        for (id, paused) in pause {
+            manager.set_paused(id, paused);
+        }
+
+        if selected != self.selected {
+            self.selected = selected;
+            self.console.reset();
+        }
+
+        let output = self
+            .selected
+            .and_then(|id| manager.with_jobs(|jobs| jobs.iter().find(|job| job.id == id).map(|job| Arc::clone(&job.output))));
+        ui.separator();
+        match output {
+            Some(output) => self.console.show(ui, &output, ui.available_height()),
+            None => {
+                ui.label("Выберите задание, чтобы увидеть его вывод.");
+            }
+        }
+    }
+}
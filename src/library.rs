@@ -0,0 +1,316 @@
+// Rule library: imported rule files in a managed store, their index and named rule sets
+use crate::rules::{self, RuleFile};
+use crate::storage;
+use crate::yara_syntax;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const INDEX_FILE: &str = "library/index.json";
+
+// Numbers the scratch files and directories of concurrent imports and set builds
+static SCRATCH: AtomicU64 = AtomicU64::new(0);
+
+pub const ARCHIVE_EXTENSIONS: [&str; 6] = ["zip", "tar", "gz", "tgz", "bz2", "xz"];
+
+// One rule of the library
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LibraryRule {
+    pub name: String,
+    // Path relative to the store
+    pub file: String,
+    pub line: usize,
+    pub tags: Vec<String>,
+    pub author: String,
+    pub description: String,
+    pub date: String,
+    pub reference: String,
+    pub private: bool,
+}
+
+impl LibraryRule {
+    // The query is expected in lower case
+    pub fn matches(&self, query: &str) -> bool {
+        [&self.name, &self.author, &self.description, &self.reference, &self.file]
+            .iter()
+            .any(|field| field.to_lowercase().contains(query))
+            || self.tags.iter().any(|tag| tag.to_lowercase().contains(query))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RuleRef {
+    pub file: String,
+    pub name: String,
+}
+
+impl From<&LibraryRule> for RuleRef {
+    fn from(rule: &LibraryRule) -> Self {
+        Self {
+            file: rule.file.clone(),
+            name: rule.name.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub name: String,
+    pub rules: Vec<RuleRef>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleLibrary {
+    pub rules: Vec<LibraryRule>,
+    pub sets: Vec<RuleSet>,
+}
+
+pub fn files_dir() -> PathBuf {
+    storage::data_dir().join("library/files")
+}
+
+// Name not taken yet in the directory: "name", "name-2", ...
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.join(name);
+    let mut suffix = 2;
+    while path.exists() {
+        path = match name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => dir.join(format!("{}-{}.{}", stem, suffix, extension)),
+            _ => dir.join(format!("{}-{}", name, suffix)),
+        };
+        suffix += 1;
+    }
+    path
+}
+
+// Archive name without the archive extensions: "rules.tar.gz" -> "rules"
+fn archive_stem(name: &str) -> &str {
+    let lower = name.to_lowercase();
+    for extension in [".tar.gz", ".tar.bz2", ".tar.xz", ".tgz", ".tar", ".zip"] {
+        if lower.ends_with(extension) {
+            return &name[..name.len() - extension.len()];
+        }
+    }
+    name
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ARCHIVE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+// Unpack with the system unzip or tar
+fn extract(archive: &Path, dir: &Path) -> Result<(), String> {
+    let zip = archive.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    let (tool, output) = if zip {
+        ("unzip", Command::new("unzip").arg("-q").arg("-o").arg(archive).arg("-d").arg(dir).output())
+    } else {
+        ("tar", Command::new("tar").arg("-xf").arg(archive).arg("-C").arg(dir).output())
+    };
+    let output = output.map_err(|e| format!("Ошибка при запуске {}: {}", tool, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Не удалось распаковать {}: {}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// Copy the rule files of a directory keeping its structure, so relative includes still work
+fn copy_rules(from: &Path, to: &Path) -> Result<usize, String> {
+    let files = rules::collect_dir(from)?;
+    if files.is_empty() {
+        return Err(format!("В {} нет файлов правил", from.display()));
+    }
+    for file in &files {
+        let file = Path::new(file);
+        let destination = to.join(file.strip_prefix(from).unwrap_or(file));
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Не удалось создать {}: {}", parent.display(), e))?;
+        }
+        std::fs::copy(file, &destination).map_err(|e| format!("Не удалось скопировать {}: {}", file.display(), e))?;
+    }
+    Ok(files.len())
+}
+
+// Copy a rule file, a directory or an archive into the store. Returns the number of imported files.
+pub fn import(path: &Path) -> Result<usize, String> {
+    let store = files_dir();
+    std::fs::create_dir_all(&store).map_err(|e| format!("Не удалось создать {}: {}", store.display(), e))?;
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+    if path.is_dir() {
+        copy_rules(path, &unique_path(&store, &name))
+    } else if rules::is_rule_file(path) {
+        let destination = unique_path(&store, &name);
+        std::fs::copy(path, &destination).map_err(|e| format!("Не удалось скопировать {}: {}", path.display(), e))?;
+        Ok(1)
+    } else if is_archive(path) {
+        // Imports run as jobs, each unpacks into its own directory
+        let unpacked = storage::path(&format!(
+            "library/unpacked-{}-{}",
+            std::process::id(),
+            SCRATCH.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir(&unpacked).map_err(|e| format!("Не удалось создать {}: {}", unpacked.display(), e))?;
+        let result = extract(path, &unpacked).and_then(|_| copy_rules(&unpacked, &unique_path(&store, archive_stem(&name))));
+        let _ = std::fs::remove_dir_all(&unpacked);
+        result
+    } else {
+        Err(format!("Неподдерживаемый формат файла: {}", path.display()))
+    }
+}
+
+impl RuleLibrary {
+    pub fn load() -> Result<Self, String> {
+        storage::load(INDEX_FILE)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        storage::save(INDEX_FILE, self)
+    }
+
+    // All tags of the library, sorted
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .rules
+            .iter()
+            .flat_map(|rule| rule.tags.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        tags.sort();
+        tags
+    }
+}
+
+// Index the rule files in the store. The result replaces `RuleLibrary::rules`, rule sets are kept.
+pub fn index() -> Result<Vec<LibraryRule>, String> {
+    let store = files_dir();
+    let files = if store.exists() { rules::collect_dir(&store)? } else { Vec::new() };
+
+    let mut indexed = Vec::new();
+    for file in files {
+        let bytes = std::fs::read(&file).map_err(|e| format!("Не удалось прочитать {}: {}", file, e))?;
+        let text = String::from_utf8_lossy(&bytes);
+        let relative = Path::new(&file).strip_prefix(&store).map(|p| p.to_string_lossy().to_string()).unwrap_or(file.clone());
+        for rule in yara_syntax::parse(&text).rules {
+            let meta = |name: &str| rule.meta_value(name).unwrap_or_default().to_string();
+            indexed.push(LibraryRule {
+                author: meta("author"),
+                description: meta("description"),
+                date: meta("date"),
+                reference: meta("reference"),
+                name: rule.name,
+                file: relative.clone(),
+                line: rule.line,
+                tags: rule.tags,
+                private: rule.private,
+            });
+        }
+    }
+    Ok(indexed)
+}
+
+// Write one rule file per source file with the rules of the set and return them for scanning.
+// Imports and includes of the source are kept, and rules referenced from the conditions
+// of selected rules in the same file come along. The directory is named after the set and a
+// hash of its name and rules, so a scan still using an earlier build keeps its files and sets
+// whose names differ only in punctuation do not share one.
+pub fn build_set(set: &RuleSet) -> Result<Vec<RuleFile>, String> {
+    if set.rules.is_empty() {
+        return Err(format!("Набор {} пуст", set.name));
+    }
+
+    let mut by_file: BTreeMap<&str, HashSet<String>> = BTreeMap::new();
+    for rule in &set.rules {
+        by_file.entry(&rule.file).or_default().insert(rule.name.clone());
+    }
+
+    let mut sources: Vec<(String, String)> = Vec::new();
+    for (file, mut wanted) in by_file {
+        let path = files_dir().join(file);
+        let bytes = std::fs::read(&path).map_err(|e| format!("Не удалось прочитать {}: {}", path.display(), e))?;
+        let text = String::from_utf8_lossy(&bytes);
+        let parsed = yara_syntax::parse(&text);
+
+        if let Some(missing) = wanted.iter().find(|name| !parsed.rules.iter().any(|rule| &rule.name == *name)) {
+            return Err(format!("Правило {} не найдено в {}, переиндексируйте библиотеку", missing, file));
+        }
+        loop {
+            let referenced: Vec<String> = parsed
+                .rules
+                .iter()
+                .filter(|rule| wanted.contains(&rule.name))
+                .flat_map(|rule| rule.condition_words.iter())
+                .filter(|word| !wanted.contains(*word) && parsed.rules.iter().any(|rule| &rule.name == *word))
+                .cloned()
+                .collect();
+            if referenced.is_empty() {
+                break;
+            }
+            wanted.extend(referenced);
+        }
+
+        let mut source = String::new();
+        for import in &parsed.imports {
+            source.push_str(&format!("import \"{}\"\n", import));
+        }
+        let base = path.parent().unwrap_or(Path::new("/"));
+        for include in &parsed.includes {
+            source.push_str(&format!("include \"{}\"\n", base.join(include).display()));
+        }
+        for rule in parsed.rules.iter().filter(|rule| wanted.contains(&rule.name)) {
+            source.push('\n');
+            source.push_str(&text[rule.start..rule.end]);
+            source.push('\n');
+        }
+
+        let mut namespace = rules::default_namespace(file);
+        let mut suffix = 2;
+        while sources.iter().any(|(taken, _)| *taken == namespace) {
+            namespace = format!("{}_{}", rules::default_namespace(file), suffix);
+            suffix += 1;
+        }
+        sources.push((namespace, source));
+    }
+
+    let mut hasher = Sha256::new();
+    for part in std::iter::once(&set.name).chain(sources.iter().flat_map(|(namespace, source)| [namespace, source])) {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    let hash: String = hasher.finalize().iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    let output_dir = storage::data_dir()
+        .join("library/sets")
+        .join(format!("{}-{}", rules::default_namespace(&set.name), hash));
+    std::fs::create_dir_all(&output_dir).map_err(|e| format!("Не удалось создать {}: {}", output_dir.display(), e))?;
+
+    let mut result = Vec::new();
+    for (namespace, source) in sources {
+        // The same build may be in use, its files are replaced whole
+        let output = output_dir.join(format!("{}.yar", namespace));
+        let partial = output_dir.join(format!(
+            "{}.yar.{}-{}.tmp",
+            namespace,
+            std::process::id(),
+            SCRATCH.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&partial, source)
+            .and_then(|_| std::fs::rename(&partial, &output))
+            .map_err(|e| format!("Не удалось записать {}: {}", output.display(), e))?;
+        result.push(RuleFile {
+            path: output.to_string_lossy().to_string(),
+            namespace,
+            enabled: true,
+        });
+    }
+    Ok(result)
+}
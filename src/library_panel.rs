@@ -0,0 +1,383 @@
+// Rule library window: import, search and rule sets for scanning
+use crate::activity::ActivityLog;
+use crate::jobs::JobManager;
+use crate::library::{self, LibraryRule, RuleLibrary, RuleRef, RuleSet};
+use crate::rules::{self, RuleFile};
+use crate::scan::ScanMonitor;
+use eframe::egui;
+use rfd::FileDialog;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// Rows drawn in the rule table
+const ROWS_SHOWN: usize = 1000;
+const JOB_GROUP: &str = "Библиотека правил";
+
+// New index built by an import or reindex job and the message about it
+struct Indexing {
+    rules: Option<Vec<LibraryRule>>,
+    message: String,
+}
+
+// Search settings the rule list was filtered with
+#[derive(Clone, PartialEq)]
+struct FilterKey {
+    search: String,
+    tag: String,
+    current_set: Option<usize>,
+    only_set: bool,
+    revision: u64,
+}
+
+fn shorten(text: &str, limit: usize) -> String {
+    if text.chars().count() > limit {
+        format!("{}…", text.chars().take(limit).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+pub struct LibraryPanel {
+    library: RuleLibrary,
+    search: String,
+    // Empty for all tags
+    tag: String,
+    // Index of the rule set being edited
+    current_set: Option<usize>,
+    only_set: bool,
+    selected: HashSet<RuleRef>,
+    new_set: String,
+    message: String,
+    // Filled by the import and reindex jobs
+    indexing: Arc<Mutex<Option<Indexing>>>,
+    // Bumped on every change of the library, invalidates the caches below
+    revision: u64,
+    tags: Vec<String>,
+    filter_key: Option<FilterKey>,
+    in_set: HashSet<RuleRef>,
+    found: Vec<usize>,
+}
+
+impl Default for LibraryPanel {
+    fn default() -> Self {
+        let mut message = String::new();
+        let library = RuleLibrary::load().unwrap_or_else(|e| {
+            message = e;
+            RuleLibrary::default()
+        });
+
+        Self {
+            current_set: if library.sets.is_empty() { None } else { Some(0) },
+            tags: library.tags(),
+            library,
+            search: String::new(),
+            tag: String::new(),
+            only_set: false,
+            selected: HashSet::new(),
+            new_set: String::new(),
+            message,
+            indexing: Arc::new(Mutex::new(None)),
+            revision: 0,
+            filter_key: None,
+            in_set: HashSet::new(),
+            found: Vec::new(),
+        }
+    }
+}
+
+impl LibraryPanel {
+    fn save(&mut self) {
+        self.revision += 1;
+        if let Err(e) = self.library.save() {
+            self.message = e;
+        }
+    }
+
+    // Copy the files into the store and reindex it in the background, no paths only reindex
+    fn start_indexing(&mut self, jobs: &JobManager, paths: Vec<PathBuf>, activity: &ActivityLog) {
+        let indexing = Arc::clone(&self.indexing);
+        let activity = activity.clone();
+        let title = if paths.is_empty() { "Переиндексация" } else { "Импорт" };
+        self.message = format!("{}...", title);
+
+        jobs.spawn(JOB_GROUP, title, move |job| {
+            let import = !paths.is_empty();
+            let mut imported = 0;
+            let mut errors = Vec::new();
+            for (done, path) in paths.iter().enumerate() {
+                job.progress(done as f32 / paths.len() as f32);
+                match library::import(path) {
+                    Ok(count) => imported += count,
+                    Err(e) => errors.push(e),
+                }
+            }
+            let rules = library::index().map_err(|e| errors.push(e)).ok();
+
+            let count = rules.as_ref().map(|rules| rules.len());
+            let mut message = match (import, count) {
+                (true, Some(count)) => format!("Импортировано файлов: {}, правил в библиотеке: {}", imported, count),
+                (true, None) => format!("Импортировано файлов: {}", imported),
+                (false, Some(count)) => format!("Правил в библиотеке: {}", count),
+                (false, None) => String::new(),
+            };
+            if !errors.is_empty() {
+                if !message.is_empty() {
+                    message.push_str(". ");
+                }
+                message.push_str(&format!("Ошибки: {}", errors.join("; ")));
+            }
+            activity.push(format!("Библиотека правил: {}", message));
+            *indexing.lock().unwrap() = Some(Indexing {
+                rules,
+                message: message.clone(),
+            });
+            if errors.is_empty() {
+                Ok(message)
+            } else {
+                Err(message)
+            }
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, jobs: &JobManager, yara_rules: &Mutex<Vec<RuleFile>>, activity: &ActivityLog) {
+        let finished = self.indexing.lock().unwrap().take();
+        if let Some(indexing) = finished {
+            if let Some(rules) = indexing.rules {
+                self.library.rules = rules;
+                self.tags = self.library.tags();
+                self.selected.clear();
+                self.save();
+            }
+            self.message = indexing.message;
+        }
+        let indexing = jobs.active_in(JOB_GROUP) > 0;
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!indexing, |ui| {
+                if ui.button("Импорт файлов и архивов").clicked() {
+                    let extensions: Vec<&str> = rules::RULE_EXTENSIONS.iter().chain(library::ARCHIVE_EXTENSIONS.iter()).copied().collect();
+                    if let Some(paths) = FileDialog::new()
+                        .add_filter("Правила YARA и архивы", &extensions)
+                        .set_title("Импорт в библиотеку правил")
+                        .pick_files()
+                    {
+                        self.start_indexing(jobs, paths, activity);
+                    }
+                }
+                if ui.button("Импорт папки").clicked() {
+                    if let Some(dir) = FileDialog::new().set_title("Импорт папки в библиотеку правил").pick_folder() {
+                        self.start_indexing(jobs, vec![dir], activity);
+                    }
+                }
+                if ui.button("Переиндексировать").clicked() {
+                    self.start_indexing(jobs, Vec::new(), activity);
+                }
+            });
+            if indexing {
+                ui.spinner();
+            }
+            if ui.button("Открыть папку библиотеки").clicked() {
+                let dir = library::files_dir();
+                let _ = std::fs::create_dir_all(&dir);
+                if let Err(e) = open::that(&dir) {
+                    self.message = format!("Не удалось открыть {}: {}", dir.display(), e);
+                }
+            }
+        });
+        ui.label(format!(
+            "Правил: {}, наборов: {}. Хранилище: {}",
+            self.library.rules.len(),
+            self.library.sets.len(),
+            library::files_dir().display()
+        ));
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Набор правил:");
+            let current_name = self
+                .current_set
+                .and_then(|index| self.library.sets.get(index))
+                .map(|set| format!("{} ({})", set.name, set.rules.len()))
+                .unwrap_or_else(|| "не выбран".to_string());
+            egui::ComboBox::from_id_source("library_set")
+                .selected_text(current_name)
+                .show_ui(ui, |ui| {
+                    for (index, set) in self.library.sets.iter().enumerate() {
+                        ui.selectable_value(&mut self.current_set, Some(index), format!("{} ({})", set.name, set.rules.len()));
+                    }
+                });
+
+            ui.add(egui::TextEdit::singleline(&mut self.new_set).hint_text("Имя нового набора").desired_width(150.0));
+            if ui.button("Создать набор").clicked() {
+                let name = self.new_set.trim().to_string();
+                if name.is_empty() {
+                    self.message = "Введите имя набора".to_string();
+                } else if self.library.sets.iter().any(|set| set.name == name) {
+                    self.message = format!("Набор {} уже существует", name);
+                } else {
+                    self.library.sets.push(RuleSet {
+                        name,
+                        rules: Vec::new(),
+                    });
+                    self.current_set = Some(self.library.sets.len() - 1);
+                    self.new_set.clear();
+                    self.save();
+                }
+            }
+            if let Some(index) = self.current_set {
+                if ui.button("Удалить набор").clicked() {
+                    self.library.sets.remove(index);
+                    self.current_set = None;
+                    self.only_set = false;
+                    self.save();
+                }
+            }
+        });
+
+        if let Some(index) = self.current_set {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!self.selected.is_empty(), egui::Button::new(format!("Добавить выбранные ({})", self.selected.len())))
+                    .clicked()
+                {
+                    let set = &mut self.library.sets[index];
+                    for rule in &self.selected {
+                        if !set.rules.contains(rule) {
+                            set.rules.push(rule.clone());
+                        }
+                    }
+                    self.save();
+                }
+                if ui
+                    .add_enabled(!self.selected.is_empty(), egui::Button::new("Убрать выбранные из набора"))
+                    .clicked()
+                {
+                    self.library.sets[index].rules.retain(|rule| !self.selected.contains(rule));
+                    self.save();
+                }
+                if ui.button("Использовать для сканирования").clicked() {
+                    let set = &self.library.sets[index];
+                    self.message = match library::build_set(set) {
+                        Ok(files) => {
+                            let message = format!("Набор {} выбран для сканирования: файлов правил {}", set.name, files.len());
+                            *yara_rules.lock().unwrap() = files;
+                            activity.push(message.clone());
+                            message
+                        }
+                        Err(e) => e,
+                    };
+                }
+            });
+        }
+
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+
+        ui.separator();
+        let tags = &self.tags;
+        ui.horizontal(|ui| {
+            ui.label("Поиск:");
+            ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("имя, тег, автор, описание, файл"));
+            egui::ComboBox::from_id_source("library_tag")
+                .selected_text(if self.tag.is_empty() { "Все теги" } else { &self.tag })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.tag, String::new(), "Все теги");
+                    for tag in tags {
+                        ui.selectable_value(&mut self.tag, tag.clone(), tag);
+                    }
+                });
+            ui.add_enabled(self.current_set.is_some(), egui::Checkbox::new(&mut self.only_set, "Только правила набора"));
+        });
+
+        // The list is filtered again only when the search or the library changes
+        let key = FilterKey {
+            search: self.search.clone(),
+            tag: self.tag.clone(),
+            current_set: self.current_set,
+            only_set: self.only_set,
+            revision: self.revision,
+        };
+        if self.filter_key.as_ref() != Some(&key) {
+            self.in_set = self
+                .current_set
+                .and_then(|index| self.library.sets.get(index))
+                .map(|set| set.rules.iter().cloned().collect())
+                .unwrap_or_default();
+            let query = self.search.to_lowercase();
+            self.found = self
+                .library
+                .rules
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| query.is_empty() || rule.matches(&query))
+                .filter(|(_, rule)| self.tag.is_empty() || rule.tags.contains(&self.tag))
+                .filter(|(_, rule)| !self.only_set || self.in_set.contains(&RuleRef::from(*rule)))
+                .map(|(index, _)| index)
+                .collect();
+            self.filter_key = Some(key);
+        }
+        let found = &self.found;
+        let in_set = &self.in_set;
+
+        ui.horizontal(|ui| {
+            ui.label(format!("Найдено: {}, выбрано: {}", found.len(), self.selected.len()));
+            if ui.small_button("Выбрать найденные").clicked() {
+                self.selected.extend(found.iter().map(|index| RuleRef::from(&self.library.rules[*index])));
+            }
+            if ui.small_button("Снять выбор").clicked() {
+                self.selected.clear();
+            }
+        });
+        if found.len() > ROWS_SHOWN {
+            ui.label(format!("Показаны первые {} правил, уточните поиск.", ROWS_SHOWN));
+        }
+
+        egui::ScrollArea::both().id_source("library_rules").show(ui, |ui| {
+            egui::Grid::new("library_rules_grid").striped(true).show(ui, |ui| {
+                ui.label("");
+                ui.strong("Правило");
+                ui.strong("Теги");
+                ui.strong("Автор");
+                ui.strong("Дата");
+                ui.strong("Описание");
+                ui.strong("Ссылка");
+                ui.strong("Файл");
+                ui.end_row();
+
+                for index in found.iter().take(ROWS_SHOWN) {
+                    let rule = &self.library.rules[*index];
+                    let key = RuleRef::from(rule);
+                    let mut checked = self.selected.contains(&key);
+                    if ui.checkbox(&mut checked, "").changed() {
+                        if checked {
+                            self.selected.insert(key.clone());
+                        } else {
+                            self.selected.remove(&key);
+                        }
+                    }
+                    let mut name = egui::RichText::new(&rule.name);
+                    if in_set.contains(&key) {
+                        name = name.strong().color(egui::Color32::LIGHT_BLUE);
+                    }
+                    if rule.private {
+                        name = name.italics();
+                    }
+                    ui.label(name);
+                    ui.label(rule.tags.join(" "));
+                    ui.label(&rule.author);
+                    ui.label(&rule.date);
+                    ui.label(shorten(&rule.description, 80)).on_hover_text(&rule.description);
+                    if rule.reference.starts_with("http://") || rule.reference.starts_with("https://") {
+                        ui.hyperlink_to(shorten(&rule.reference, 40), &rule.reference);
+                    } else {
+                        ui.label(shorten(&rule.reference, 40));
+                    }
+                    ui.label(format!("{}:{}", rule.file, rule.line));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
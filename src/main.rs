@@ -1,73 +1,620 @@
+mod activity;
+mod archives;
+mod console;
+mod cve;
+mod editor;
+mod evidence;
+mod exceptions;
+mod exceptions_panel;
+mod export;
+mod findings;
+mod jobs;
+mod jobs_panel;
+mod library;
+mod library_panel;
+mod pool;
+mod processes;
+mod processes_panel;
+mod quarantine;
+mod quarantine_panel;
+mod report;
+mod rpm;
+mod rules;
+mod scan;
+mod schedule;
+mod scheduler;
+mod scheduler_panel;
+mod storage;
+mod suppressions;
+mod suppressions_panel;
+mod targets;
+mod targets_panel;
+mod viewer;
+mod walker;
+#[cfg(feature = "embedded-yara")]
+mod yara_engine;
+mod yara_syntax;
+
 // Include necessary imports
+use activity::ActivityLog;
+use console::{ConsoleView, JobConsoles, LineKind, Tee};
+use editor::RuleEditor;
 use eframe::{egui, App, Frame};
+use exceptions_panel::ExceptionsPanel;
+use export::ExportFormat;
+use findings::{Engine, Finding, ScanRun};
+use jobs::JobManager;
+use jobs_panel::JobsPanel;
+use library_panel::LibraryPanel;
+use processes_panel::ProcessesPanel;
+use quarantine_panel::QuarantinePanel;
 use open;
+use report::{ReportData, ReportLanguage, ReportSettings};
 use rfd::FileDialog;
-use std::process::Command;
+use rules::{Compilation, RuleFile, Severity};
+use scan::{timestamp, ExternalVariable, OpenScapOptions, OscapModule, Validation, Verbosity, YaraOptions};
+use scheduler_panel::{PanelSettings, SchedulerPanel};
+use suppressions_panel::SuppressionsPanel;
+use targets_panel::TargetsPanel;
+use viewer::ViewerAction;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use ureq::Agent;
+
+// Findings as shown, reported and exported: without the suppressed YARA matches, with the
+// accepted risks marked, along with the number of suppressed matches
+#[derive(Default)]
+struct Presented {
+    // Revisions of the findings, suppressions and exceptions and the day they were applied on,
+    // as the entries expire with the date
+    key: Option<(u64, u64, u64, chrono::NaiveDate)>,
+    findings: Vec<Finding>,
+    suppressed: usize,
+}
 
 // Structure for the application
 struct SecurityScannerApp {
-    output: Arc<Mutex<String>>,
-    is_running: Arc<Mutex<bool>>,
+    // Output of the last scan of each tool
+    oscap_output: Arc<Mutex<JobConsoles>>,
+    yara_output: Arc<Mutex<JobConsoles>>,
+    oscap_console: ConsoleView,
+    yara_console: ConsoleView,
+    // Timestamped messages about user actions
+    activity: ActivityLog,
+    show_activity: bool,
+    // Downloads and scans running in the background
+    jobs: JobManager,
+    jobs_panel: JobsPanel,
     xml_path: Arc<Mutex<Option<String>>>,
     download_url: String,
     download_path: Arc<Mutex<Option<String>>>,
+    // HTML report of the last finished OpenSCAP scan, each job writes its own
     report_path: Arc<Mutex<String>>,
     // New fields for YARA functionality
     selected_tool: Arc<Mutex<String>>,
-    yara_rules_path: Arc<Mutex<Option<String>>>,
-    scan_path: Arc<Mutex<Option<String>>>,
+    yara_rules: Arc<Mutex<Vec<RuleFile>>>,
+    // Last "validate rules" result and the selected message
+    yara_validation: Arc<Mutex<Option<Compilation>>>,
+    yara_diagnostic: Option<usize>,
+    // Files and folders scanned by one YARA job
+    scan_targets: Arc<Mutex<Vec<String>>>,
+    targets_panel: TargetsPanel,
+    // Scan the memory of running processes instead of paths
+    scan_memory: bool,
+    processes_panel: ProcessesPanel,
     // Checkboxes for OpenSCAP additional arguments
     oscap_checkboxes: Arc<Mutex<OpenScapOptions>>,
+    // Check of the OpenSCAP options against the selected content
+    oscap_validation: Validation<(OpenScapOptions, String)>,
     // Checkboxes for YARA additional arguments
     yara_checkboxes: Arc<Mutex<YaraOptions>>,
-}
-
-// Structures for checkboxes options
-#[derive(Default, Clone)]
-struct OpenScapOptions {
-    skip_valid: bool,
-    verbose: bool,
-    oval_results: bool,
-    dont_send_results: bool,
-}
-
-#[derive(Default, Clone)]
-struct YaraOptions {
-    recursive: bool,
-    fast_scan: bool,
-    no_warnings: bool,
-    print_tags: bool,
+    // Check of the YARA options, rules and targets; targets are not checked for memory scans
+    yara_options_check: Validation<(YaraOptions, Vec<RuleFile>, Option<Vec<String>>)>,
+    // Parsed findings of the last OpenSCAP and YARA scans
+    findings: Arc<Mutex<Vec<Finding>>>,
+    // Bumped on every change of the findings, so the presented ones are recomputed only then
+    findings_revision: Arc<AtomicU64>,
+    presented: Presented,
+    // Options and content of the last scan of each engine
+    scan_runs: Arc<Mutex<Vec<ScanRun>>>,
+    // Optional offline CVE data used to enrich OVAL findings
+    cve_data_path: Arc<Mutex<Option<String>>>,
+    // Settings of the consolidated report
+    report_settings: ReportSettings,
+    // Embedded report viewer window
+    show_report_viewer: bool,
+    // Recurring scan jobs window
+    scheduler_panel: SchedulerPanel,
+    show_scheduler: bool,
+    // YARA rule library window
+    library_panel: LibraryPanel,
+    show_library: bool,
+    // YARA rule editor window
+    rule_editor: RuleEditor,
+    show_editor: bool,
+    // Quarantine of detected files
+    quarantine_panel: QuarantinePanel,
+    show_quarantine: bool,
+    // False positive suppressions of YARA matches
+    suppressions_panel: SuppressionsPanel,
+    show_suppressions: bool,
+    // Accepted risks of OVAL findings
+    exceptions_panel: ExceptionsPanel,
+    show_exceptions: bool,
 }
 
 impl Default for SecurityScannerApp {
     fn default() -> Self {
         Self {
-            output: Arc::new(Mutex::new(String::new())),
-            is_running: Arc::new(Mutex::new(false)),
+            oscap_output: Arc::new(Mutex::new(JobConsoles::default())),
+            yara_output: Arc::new(Mutex::new(JobConsoles::default())),
+            oscap_console: ConsoleView::new("oscap_console"),
+            yara_console: ConsoleView::new("yara_console"),
+            activity: ActivityLog::default(),
+            show_activity: false,
+            jobs: JobManager::default(),
+            jobs_panel: JobsPanel::default(),
             xml_path: Arc::new(Mutex::new(None)),
             download_url: "https://redos.red-soft.ru/support/secure/redos.xml".to_string(),
             download_path: Arc::new(Mutex::new(None)),
             report_path: Arc::new(Mutex::new("/tmp/report.html".to_string())),
             selected_tool: Arc::new(Mutex::new("OpenSCAP".to_string())),
-            yara_rules_path: Arc::new(Mutex::new(None)),
-            scan_path: Arc::new(Mutex::new(None)),
+            yara_rules: Arc::new(Mutex::new(Vec::new())),
+            yara_validation: Arc::new(Mutex::new(None)),
+            yara_diagnostic: None,
+            scan_targets: Arc::new(Mutex::new(Vec::new())),
+            targets_panel: TargetsPanel::default(),
+            scan_memory: false,
+            processes_panel: ProcessesPanel::default(),
             oscap_checkboxes: Arc::new(Mutex::new(OpenScapOptions::default())),
+            oscap_validation: Validation::default(),
             yara_checkboxes: Arc::new(Mutex::new(YaraOptions::default())),
+            yara_options_check: Validation::default(),
+            findings: Arc::new(Mutex::new(Vec::new())),
+            findings_revision: Arc::new(AtomicU64::new(0)),
+            presented: Presented::default(),
+            scan_runs: Arc::new(Mutex::new(Vec::new())),
+            cve_data_path: Arc::new(Mutex::new(None)),
+            report_settings: ReportSettings::default(),
+            show_report_viewer: false,
+            scheduler_panel: SchedulerPanel::default(),
+            show_scheduler: false,
+            library_panel: LibraryPanel::default(),
+            show_library: false,
+            rule_editor: RuleEditor::default(),
+            show_editor: false,
+            quarantine_panel: QuarantinePanel::default(),
+            show_quarantine: false,
+            suppressions_panel: SuppressionsPanel::default(),
+            show_suppressions: false,
+            exceptions_panel: ExceptionsPanel::default(),
+            show_exceptions: false,
+        }
+    }
+}
+
+// Job title of the YARA rule validation
+const RULE_VALIDATION: &str = "Проверка правил";
+
+// Replace the findings of one engine, keeping the results of the other
+fn store_findings(findings: &Arc<Mutex<Vec<Finding>>>, revision: &AtomicU64, engine: Engine, new_findings: Vec<Finding>) {
+    let mut all = findings.lock().unwrap();
+    all.retain(|f| f.engine != engine);
+    all.extend(new_findings);
+    revision.fetch_add(1, Ordering::Relaxed);
+}
+
+// Replace the recorded run of the same engine
+fn store_scan_run(scan_runs: &Arc<Mutex<Vec<ScanRun>>>, run: ScanRun) {
+    let mut runs = scan_runs.lock().unwrap();
+    runs.retain(|r| r.engine != run.engine);
+    runs.push(run);
+}
+
+// Text field for an optional path with a file picker next to it
+fn path_field(ui: &mut egui::Ui, label: &str, value: &mut String, save: bool) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.text_edit_singleline(value);
+        if ui.button("Обзор...").clicked() {
+            let dialog = FileDialog::new().set_title(label);
+            let picked = if save { dialog.save_file() } else { dialog.pick_file() };
+            if let Some(path) = picked {
+                *value = path.to_string_lossy().to_string();
+            }
         }
+    });
+}
+
+fn text_field(ui: &mut egui::Ui, label: &str, value: &mut String) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.text_edit_singleline(value);
+    });
+}
+
+// Editor of a list with one value per line
+fn lines_field(ui: &mut egui::Ui, label: &str, hint: &str, values: &mut Vec<String>) {
+    ui.label(label);
+    let mut text = values.join("\n");
+    if ui
+        .add(egui::TextEdit::multiline(&mut text).hint_text(hint).desired_rows(2).desired_width(400.0))
+        .changed()
+    {
+        *values = text.split('\n').map(str::to_string).collect();
+    }
+}
+
+impl SecurityScannerApp {
+    // Apply the suppressions and exceptions again when the findings or the entries changed
+    fn refresh_presented(&mut self) {
+        let key = (
+            self.findings_revision.load(Ordering::Relaxed),
+            self.suppressions_panel.revision(),
+            self.exceptions_panel.revision(),
+            chrono::Local::now().date_naive(),
+        );
+        if self.presented.key == Some(key) {
+            return;
+        }
+        let (visible, suppressed) = suppressions::apply(&self.findings.lock().unwrap(), self.suppressions_panel.list());
+        self.presented = Presented {
+            key: Some(key),
+            findings: exceptions::apply(&visible, self.exceptions_panel.list()),
+            suppressed,
+        };
+    }
+
+    // Buttons exporting the parsed findings of the given engine
+    fn export_buttons(&self, ui: &mut egui::Ui, engine: Engine) {
+        ui.horizontal(|ui| {
+            for format in ExportFormat::ALL {
+                if ui.button(format!("Экспорт в {}", format.label())).clicked() {
+                    let suppressed = self.presented.suppressed;
+                    let findings: Vec<Finding> =
+                        self.presented.findings.iter().filter(|f| f.engine == engine).cloned().collect();
+
+                    let message = if let Some(path) = FileDialog::new()
+                        .add_filter(format.label(), &[format.extension()])
+                        .set_title("Сохранить результаты как")
+                        .save_file()
+                    {
+                        match export::write_to_file(&findings, format, &path) {
+                            Ok(_) if suppressed > 0 && engine == Engine::Yara => format!(
+                                "Экспортировано находок: {} в файл {}, скрыто как ложные: {}",
+                                findings.len(),
+                                path.display(),
+                                suppressed
+                            ),
+                            Ok(_) => format!(
+                                "Экспортировано находок: {} в файл {}",
+                                findings.len(),
+                                path.display()
+                            ),
+                            Err(e) => format!("Ошибка при экспорте результатов: {}", e),
+                        }
+                    } else {
+                        "Экспорт отменен пользователем.".to_string()
+                    };
+                    self.activity.push(message);
+                }
+            }
+        });
+    }
+
+    // Messages of the last rule validation; a selected message shows the source around it
+    fn rule_diagnostics(&mut self, ui: &mut egui::Ui) {
+        let validation = self.yara_validation.lock().unwrap();
+        let Some(compilation) = validation.as_ref() else {
+            return;
+        };
+
+        let warnings = compilation.count(Severity::Warning);
+        if compilation.compiled.is_some() {
+            ui.colored_label(
+                egui::Color32::GREEN,
+                format!("Правила скомпилированы и будут использованы при сканировании. Предупреждений: {}", warnings),
+            );
+        } else {
+            ui.colored_label(
+                egui::Color32::RED,
+                format!("Ошибок: {}, предупреждений: {}", compilation.count(Severity::Error), warnings),
+            );
+        }
+
+        egui::ScrollArea::vertical()
+            .id_source("yara_diagnostics")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for (index, diagnostic) in compilation.diagnostics.iter().enumerate() {
+                    let color = match diagnostic.severity {
+                        Severity::Error => egui::Color32::RED,
+                        Severity::Warning => egui::Color32::YELLOW,
+                    };
+                    let rule = diagnostic.rule.as_ref().map(|rule| format!(" [{}]", rule)).unwrap_or_default();
+                    let text = egui::RichText::new(format!(
+                        "{}: {}:{}{}: {}",
+                        diagnostic.severity.label(),
+                        diagnostic.file,
+                        diagnostic.line,
+                        rule,
+                        diagnostic.message
+                    ))
+                    .color(color);
+                    if ui.selectable_label(self.yara_diagnostic == Some(index), text).clicked() {
+                        self.yara_diagnostic = Some(index);
+                    }
+                }
+                for line in &compilation.other {
+                    ui.label(line);
+                }
+            });
+
+        if let Some(diagnostic) = self.yara_diagnostic.and_then(|index| compilation.diagnostics.get(index)) {
+            ui.horizontal(|ui| {
+                ui.strong(format!("{}:{}", diagnostic.file, diagnostic.line));
+                if ui.button("Открыть в редакторе").clicked() {
+                    self.rule_editor.open(std::path::Path::new(&diagnostic.file), diagnostic.line);
+                    self.show_editor = true;
+                }
+                if ui.button("Открыть файл").clicked() {
+                    if let Err(e) = open::that(&diagnostic.file) {
+                        self.activity.push(format!("Не удалось открыть {}: {}", diagnostic.file, e));
+                    }
+                }
+            });
+            for (number, text) in rules::excerpt(&diagnostic.file, diagnostic.line, 3) {
+                let line = egui::RichText::new(format!("{:>5} | {}", number, text)).monospace();
+                if number == diagnostic.line {
+                    ui.label(line.background_color(egui::Color32::from_rgb(90, 30, 30)));
+                } else {
+                    ui.label(line);
+                }
+            }
+        }
+    }
+
+    // Save the consolidated report of both engines as HTML or PDF
+    fn save_report(&self, pdf: bool) {
+        let (name, extensions): (&str, &[&str]) = if pdf { ("PDF", &["pdf"]) } else { ("HTML", &["html", "htm"]) };
+
+        let path = match FileDialog::new()
+            .add_filter(name, extensions)
+            .set_title("Сохранить сводный отчет как")
+            .save_file()
+        {
+            Some(path) => path,
+            None => {
+                self.activity.push("Сохранение отчета отменено пользователем.");
+                return;
+            }
+        };
+
+        let runs = self.scan_runs.lock().unwrap().clone();
+        let data = ReportData {
+            host: report::HostInfo::collect(),
+            runs: &runs,
+            findings: &self.presented.findings,
+            suppressed: self.presented.suppressed,
+            generated_at: timestamp(),
+        };
+
+        let result = if pdf {
+            report::write_pdf(&data, &self.report_settings, &path)
+        } else {
+            report::write_html(&data, &self.report_settings, &path).map_err(|e| e.to_string())
+        };
+
+        self.activity.push(match result {
+            Ok(_) => format!("Сводный отчет сохранен: {}", path.display()),
+            Err(e) => format!("Ошибка при формировании отчета: {}", e),
+        });
     }
 }
 
 impl App for SecurityScannerApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        self.refresh_presented();
+
         // Apply a visual style for a better look
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal_wrapped(|ui| {
                 ui.heading("🔍 Security Scanner GUI");
+                if ui.button("Планировщик").clicked() {
+                    self.show_scheduler = true;
+                }
+                if ui.button("Журнал действий").clicked() {
+                    self.show_activity = true;
+                }
+                if ui.button("Карантин").clicked() {
+                    self.show_quarantine = true;
+                }
+                if ui.button("Подавления YARA").clicked() {
+                    self.show_suppressions = true;
+                }
+                if ui.button("Принятые риски").clicked() {
+                    self.show_exceptions = true;
+                }
+                if let Some(last) = self.activity.last() {
+                    ui.label(format!("[{}] {}", last.time, last.message));
+                }
             });
         });
 
+        egui::Window::new("Журнал действий")
+            .open(&mut self.show_activity)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| {
+                if ui.button("Очистить").clicked() {
+                    self.activity.clear();
+                }
+                egui::ScrollArea::vertical()
+                    .id_source("activity_log")
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for entry in self.activity.entries() {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.monospace(&entry.time);
+                                ui.label(&entry.message);
+                            });
+                        }
+                    });
+            });
+
+        egui::Window::new("Карантин")
+            .open(&mut self.show_quarantine)
+            .default_size([800.0, 500.0])
+            .vscroll(true)
+            .show(ctx, |ui| {
+                self.quarantine_panel.show(ui, &self.jobs, &self.presented.findings, &self.activity);
+            });
+
+        egui::Window::new("Подавление ложных срабатываний YARA")
+            .open(&mut self.show_suppressions)
+            .default_size([700.0, 500.0])
+            .vscroll(true)
+            .show(ctx, |ui| {
+                self.suppressions_panel.show(ui, &self.activity);
+            });
+
+        egui::Window::new("Принятые риски OVAL")
+            .open(&mut self.show_exceptions)
+            .default_size([700.0, 500.0])
+            .vscroll(true)
+            .show(ctx, |ui| {
+                self.exceptions_panel.show(ui, &self.activity);
+            });
+
+        egui::Window::new("Библиотека правил YARA")
+            .open(&mut self.show_library)
+            .default_size([900.0, 600.0])
+            .show(ctx, |ui| {
+                self.library_panel.show(ui, &self.jobs, &self.yara_rules, &self.activity);
+            });
+
+        egui::Window::new("Редактор правил YARA")
+            .open(&mut self.show_editor)
+            .default_size([800.0, 650.0])
+            .show(ctx, |ui| {
+                let defines = self.yara_checkboxes.lock().unwrap().defines.clone();
+                self.rule_editor.show(ui, &self.jobs, &defines);
+            });
+
+        egui::Window::new("Планировщик проверок")
+            .open(&mut self.show_scheduler)
+            .default_size([700.0, 500.0])
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let oscap_options = self.oscap_checkboxes.lock().unwrap().clone();
+                let yara_options = self.yara_checkboxes.lock().unwrap().clone();
+                let yara_rules = self.yara_rules.lock().unwrap().clone();
+                let scan_targets = self.scan_targets.lock().unwrap().clone();
+                let cve_data_path = self.cve_data_path.lock().unwrap().clone();
+                self.scheduler_panel.show(
+                    ui,
+                    &self.jobs,
+                    PanelSettings {
+                        content_url: &self.download_url,
+                        oscap_options: &oscap_options,
+                        cve_data_path: cve_data_path.as_deref(),
+                        yara_options: &yara_options,
+                        yara_rules: &yara_rules,
+                        scan_targets: &scan_targets,
+                    },
+                );
+            });
+
+        egui::TopBottomPanel::bottom("jobs_panel")
+            .resizable(true)
+            .default_height(300.0)
+            .show(ctx, |ui| {
+                self.jobs_panel.show(ui, &self.jobs);
+            });
+
+        // Keep progress and output of running jobs up to date
+        if self.jobs.has_active() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+
+        // Consolidated report of both engines
+        egui::SidePanel::right("report_panel").show(ctx, |ui| {
+            ui.heading("Сводный отчет");
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Язык шаблона:");
+                egui::ComboBox::from_id_source("report_language")
+                    .selected_text(self.report_settings.language.label())
+                    .show_ui(ui, |ui| {
+                        for language in [ReportLanguage::Russian, ReportLanguage::English] {
+                            ui.selectable_value(&mut self.report_settings.language, language, language.label());
+                        }
+                    });
+            });
+
+            ui.label("Организация:");
+            ui.text_edit_singleline(&mut self.report_settings.organisation);
+
+            ui.horizontal(|ui| {
+                if ui.button("Выбрать логотип").clicked() {
+                    if let Some(path) = FileDialog::new()
+                        .add_filter("Изображения", &["png", "jpg", "jpeg"])
+                        .set_title("Выберите логотип организации")
+                        .pick_file()
+                    {
+                        let path = path.to_string_lossy().to_string();
+                        match report::check_logo(&path) {
+                            Ok(()) => self.report_settings.logo_path = Some(path),
+                            Err(e) => self.activity.push(e),
+                        }
+                    }
+                }
+                if self.report_settings.logo_path.is_some() && ui.button("Убрать").clicked() {
+                    self.report_settings.logo_path = None;
+                }
+            });
+            if let Some(ref logo) = self.report_settings.logo_path {
+                ui.label(format!("Логотип: {}", logo));
+            }
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Сохранить HTML").clicked() {
+                    self.save_report(false);
+                }
+                if ui.button("Сохранить PDF").clicked() {
+                    self.save_report(true);
+                }
+            });
+
+            if ui.button("Просмотреть в приложении").clicked() {
+                self.show_report_viewer = true;
+            }
+        });
+
+        let mut action = None;
+        let presented = &self.presented;
+        egui::Window::new("Просмотр отчета")
+            .open(&mut self.show_report_viewer)
+            .default_size([700.0, 500.0])
+            .vscroll(true)
+            .show(ctx, |ui| {
+                let runs = self.scan_runs.lock().unwrap();
+                action = viewer::show(ui, &presented.findings, &runs, presented.suppressed);
+            });
+        match action {
+            Some(ViewerAction::FalsePositive(finding)) => {
+                self.suppressions_panel.propose(&finding);
+                self.show_suppressions = true;
+            }
+            Some(ViewerAction::AcceptRisk(finding)) => {
+                self.exceptions_panel.propose(&finding);
+                self.show_exceptions = true;
+            }
+            None => {}
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.separator();
 
@@ -101,58 +648,28 @@ impl App for SecurityScannerApp {
                         if ui.button("Загрузить XML").clicked() {
                             let download_url = self.download_url.clone();
                             let download_path_clone = Arc::clone(&self.download_path);
-                            let output_clone = Arc::clone(&self.output);
-                            let running_clone = Arc::clone(&self.is_running);
-
-                            *running_clone.lock().unwrap() = true;
-                            *output_clone.lock().unwrap() = "Начало загрузки XML-файла...".to_string();
-
-                            // Start downloading in a separate thread
-                            std::thread::spawn(move || {
-                                let agent = Agent::new();
-
-                                let response = agent.get(&download_url).call();
-
-                                match response {
-                                    Ok(resp) => {
-                                        let content = resp.into_string();
-                                        match content {
-                                            Ok(text) => {
-                                                // Save the file via save dialog
-                                                if let Some(path) = FileDialog::new()
-                                                    .add_filter("XML", &["xml"])
-                                                    .set_title("Сохранить XML-файл как")
-                                                    .save_file()
-                                                {
-                                                    if let Err(e) = std::fs::write(&path, text) {
-                                                        let mut out = output_clone.lock().unwrap();
-                                                        *out = format!("Не удалось сохранить файл: {}", e);
-                                                    } else {
-                                                        let mut path_lock = download_path_clone.lock().unwrap();
-                                                        *path_lock = Some(path.to_string_lossy().to_string());
-                                                        let mut out = output_clone.lock().unwrap();
-                                                        *out = format!(
-                                                            "XML-файл успешно загружен и сохранён по пути: {}",
-                                                            path.display()
-                                                        );
-                                                    }
-                                                } else {
-                                                    let mut out = output_clone.lock().unwrap();
-                                                    *out = "Загрузка отменена пользователем.".to_string();
-                                                }
-                                            }
-                                            Err(e) => {
-                                                let mut out = output_clone.lock().unwrap();
-                                                *out = format!("Ошибка чтения содержимого ответа: {}", e);
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        let mut out = output_clone.lock().unwrap();
-                                        *out = format!("Ошибка загрузки файла: {}", e);
-                                    }
+                            let activity = self.activity.clone();
+
+                            self.activity.push(format!("Начало загрузки XML-файла: {}", download_url));
+
+                            // Start downloading as a background job
+                            self.jobs.spawn("Загрузка", &download_url.clone(), move |job| {
+                                let result = scan::download_content(&download_url, job).and_then(|text| {
+                                    // Save the file via save dialog
+                                    let path = FileDialog::new()
+                                        .add_filter("XML", &["xml"])
+                                        .set_title("Сохранить XML-файл как")
+                                        .save_file()
+                                        .ok_or_else(|| "Загрузка отменена пользователем.".to_string())?;
+                                    std::fs::write(&path, text).map_err(|e| format!("Не удалось сохранить файл: {}", e))?;
+                                    *download_path_clone.lock().unwrap() = Some(path.to_string_lossy().to_string());
+                                    Ok(format!("XML-файл успешно загружен и сохранён по пути: {}", path.display()))
+                                });
+
+                                match &result {
+                                    Ok(message) | Err(message) => activity.push(message.clone()),
                                 }
-                                *running_clone.lock().unwrap() = false;
+                                result
                             });
                         }
 
@@ -182,8 +699,7 @@ impl App for SecurityScannerApp {
                                 let path_str = path.to_string_lossy().to_string();
                                 let mut xml = self.xml_path.lock().unwrap();
                                 *xml = Some(path_str.clone());
-                                let mut output = self.output.lock().unwrap();
-                                *output = format!("Выбранный XML-файл: {}", path.display());
+                                self.activity.push(format!("Выбранный XML-файл: {}", path.display()));
                             }
                         }
 
@@ -195,6 +711,39 @@ impl App for SecurityScannerApp {
                             ui.label("Файл не выбран");
                         }
                     });
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Выбрать файл данных CVE").clicked() {
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("NVD JSON / CSV", &["json", "csv"])
+                                .set_title("Выберите файл данных CVE")
+                                .pick_file()
+                            {
+                                let path_str = path.to_string_lossy().to_string();
+                                // Enrich the findings already on screen right away
+                                let message = match cve::CveDatabase::load(&path_str) {
+                                    Ok(database) => {
+                                        database.enrich(&mut self.findings.lock().unwrap());
+                                        self.findings_revision.fetch_add(1, Ordering::Relaxed);
+                                        *self.cve_data_path.lock().unwrap() = Some(path_str);
+                                        format!("Загружены сведения о {} CVE из файла {}", database.count(), path.display())
+                                    }
+                                    Err(e) => e,
+                                };
+                                self.activity.push(message);
+                            }
+                        }
+
+                        let cve_data = self.cve_data_path.lock().unwrap().clone();
+                        if let Some(path) = cve_data {
+                            ui.label(format!("Данные CVE: {}", path));
+                            if ui.button("Убрать").clicked() {
+                                *self.cve_data_path.lock().unwrap() = None;
+                            }
+                        } else {
+                            ui.label("Данные CVE не выбраны (необязательно)");
+                        }
+                    });
                 });
 
                 ui.separator();
@@ -204,13 +753,60 @@ impl App for SecurityScannerApp {
                     ui.label("3. Дополнительные опции для сканирования:");
 
                     let mut oscap_options = self.oscap_checkboxes.lock().unwrap();
+                    let options = &mut *oscap_options;
 
                     ui.vertical(|ui| {
-                        ui.checkbox(&mut oscap_options.skip_valid, "Пропустить проверку на валидность (--skip-valid)");
-                        ui.checkbox(&mut oscap_options.verbose, "Подробный вывод (--verbose)");
-                        ui.checkbox(&mut oscap_options.oval_results, "Сохранить результаты OVAL (--oval-results)");
-                        ui.checkbox(&mut oscap_options.dont_send_results, "Не отправлять результаты (--dont-send-results)");
+                        ui.horizontal(|ui| {
+                            ui.label("Модуль:");
+                            ui.radio_value(&mut options.module, OscapModule::Oval, "OVAL (oval eval)");
+                            ui.radio_value(&mut options.module, OscapModule::Xccdf, "XCCDF / DataStream (xccdf eval)");
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("Уровень подробного вывода (--verbose):");
+                            egui::ComboBox::from_id_source("oscap_verbosity")
+                                .selected_text(options.verbosity.label())
+                                .show_ui(ui, |ui| {
+                                    for level in Verbosity::ALL {
+                                        ui.selectable_value(&mut options.verbosity, level, level.label());
+                                    }
+                                });
+                        });
+                        path_field(ui, "Журнал (--verbose-log-file):", &mut options.verbose_log_file, true);
+
+                        ui.checkbox(&mut options.skip_valid, "Пропустить проверку на валидность (--skip-valid)");
+                        ui.checkbox(&mut options.dont_send_results, "Не отправлять результаты (--dont-send-results)");
+                        text_field(ui, "Идентификатор DataStream (--datastream-id):", &mut options.datastream_id);
+
+                        match options.module {
+                            OscapModule::Oval => {
+                                text_field(ui, "Одно определение (--id):", &mut options.definition_id);
+                                path_field(ui, "Переменные (--variables):", &mut options.variables, false);
+                                path_field(ui, "Директивы (--directives):", &mut options.directives, false);
+                            }
+                            OscapModule::Xccdf => {
+                                text_field(ui, "Профиль (--profile):", &mut options.profile);
+                                text_field(ui, "Одно правило (--rule):", &mut options.rule_id);
+                                ui.checkbox(&mut options.oval_results, "Сохранить результаты OVAL (--oval-results)");
+                                ui.checkbox(
+                                    &mut options.fetch_remote_resources,
+                                    "Загружать удаленные ресурсы (--fetch-remote-resources)",
+                                );
+                                ui.checkbox(&mut options.progress, "Показывать ход проверки (--progress)");
+                            }
+                        }
                     });
+
+                    // Options left over from the other module are reported here
+                    let xml_path = self.xml_path.lock().unwrap().clone();
+                    if let Some(xml_path) = xml_path {
+                        let valid = self
+                            .oscap_validation
+                            .check((options.clone(), xml_path), |(options, xml_path)| options.validate(xml_path));
+                        if let Err(e) = valid {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                    }
                 });
 
                 ui.separator();
@@ -219,93 +815,82 @@ impl App for SecurityScannerApp {
                 ui.group(|ui| {
                     ui.label("4. Запустите сканирование:");
 
-                    if *self.is_running.lock().unwrap() {
-                        ui.add(egui::Label::new("Выполнение сканирования...").wrap(false));
-                    } else {
+                    ui.horizontal(|ui| {
                         if ui.button("Запустить сканирование").clicked() {
-                            let output_clone = Arc::clone(&self.output);
-                            let running_clone = Arc::clone(&self.is_running);
-                            let xml_clone = Arc::clone(&self.xml_path);
-                            let oscap_options_clone = Arc::clone(&self.oscap_checkboxes);
-                            let report_path_clone = Arc::clone(&self.report_path);
-
-                            *running_clone.lock().unwrap() = true;
-                            *output_clone.lock().unwrap() = "Начало сканирования...".to_string();
-
-                            // Start scanning in a separate thread
-                            std::thread::spawn(move || {
-                                let xml_path = {
-                                    let xml = xml_clone.lock().unwrap();
-                                    match &*xml {
-                                        Some(path) => path.clone(),
-                                        None => {
-                                            let mut out = output_clone.lock().unwrap();
-                                            *out = "Не выбран XML-файл для сканирования.".to_string();
-                                            *running_clone.lock().unwrap() = false;
-                                            return;
-                                        }
-                                    }
-                                };
-
-                                let oscap_options = {
-                                    let options = oscap_options_clone.lock().unwrap();
-                                    options.clone()
-                                };
-
-                                // Paths for results and report
-                                let results_path = "/tmp/results.xml";
-                                let report_path = {
-                                    let rp = report_path_clone.lock().unwrap();
-                                    rp.clone()
-                                };
-
-                                // Form command with additional options
-                                let mut args = vec![
-                                    "oval",
-                                    "eval",
-                                    "--results",
-                                    results_path,
-                                    "--report",
-                                    &report_path,
-                                ];
-
-                                // Add selected options
-                                if oscap_options.skip_valid {
-                                    args.push("--skip-valid");
-                                }
-                                if oscap_options.verbose {
-                                    args.push("--verbose");
-                                }
-                                if oscap_options.oval_results {
-                                    args.push("--oval-results");
+                            let xml_path = self.xml_path.lock().unwrap().clone();
+                            match xml_path {
+                                None => {
+                                    self.activity.push("Не выбран XML-файл для сканирования.");
                                 }
-                                if oscap_options.dont_send_results {
-                                    args.push("--dont-send-results");
-                                }
-
-                                args.push(&xml_path);
-
-                                let output = Command::new("oscap")
-                                    .args(&args)
-                                    .output();
-
-                                match output {
-                                    Ok(output) => {
-                                        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                                        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                                        let combined = format!("STDOUT:\n{}\nSTDERR:\n{}", stdout, stderr);
-                                        let mut out = output_clone.lock().unwrap();
-                                        *out = combined;
-                                    }
-                                    Err(e) => {
-                                        let mut out = output_clone.lock().unwrap();
-                                        *out = format!("Ошибка при запуске OpenSCAP: {}", e);
+                                Some(xml_path) => {
+                                    let output_clone = Arc::clone(&self.oscap_output);
+                                    let activity = self.activity.clone();
+                                    let oscap_options = self.oscap_checkboxes.lock().unwrap().clone();
+                                    if let Err(e) = oscap_options.validate(&xml_path) {
+                                        self.activity.push(e);
+                                        return;
                                     }
+                                    let report_path_clone = Arc::clone(&self.report_path);
+                                    let findings_clone = Arc::clone(&self.findings);
+                                    let findings_revision = Arc::clone(&self.findings_revision);
+                                    let scan_runs_clone = Arc::clone(&self.scan_runs);
+                                    let cve_data_path = self.cve_data_path.lock().unwrap().clone();
+
+                                    // Start scanning as a background job
+                                    let title = xml_path.clone();
+                                    let id = self.jobs.spawn("OpenSCAP", &title, move |job| {
+                                        let output = output_clone.lock().unwrap().open(job.id());
+                                        output.lock().unwrap().push(LineKind::App, "Начало сканирования...");
+                                        // Every run writes its own results and report so concurrent scans do not collide
+                                        let (results_path, report_path) = scan::oscap_files(&format!("job-{}", job.id()));
+
+                                        let result = scan::run_oscap(
+                                            &oscap_options,
+                                            &xml_path,
+                                            &results_path,
+                                            &report_path,
+                                            cve_data_path.as_deref(),
+                                            &Tee {
+                                                monitor: job,
+                                                console: &output,
+                                            },
+                                        );
+                                        // The report buttons open the report of the last successful scan
+                                        if result.is_ok() {
+                                            *report_path_clone.lock().unwrap() = report_path;
+                                        }
+                                        let mut out = output.lock().unwrap();
+                                        match result {
+                                            Ok(outcome) => {
+                                                activity.push(format!(
+                                                    "Сканирование OpenSCAP завершено (задание #{}), находок: {}",
+                                                    job.id(),
+                                                    outcome.findings.len()
+                                                ));
+                                                store_findings(&findings_clone, &findings_revision, Engine::Oval, outcome.findings);
+                                                store_scan_run(&scan_runs_clone, outcome.run);
+                                                let notes = outcome.notes.join("\n");
+                                                out.push(LineKind::App, &notes);
+                                                Ok(notes)
+                                            }
+                                            Err(e) => {
+                                                activity.push(format!("Сканирование OpenSCAP (задание #{}): {}", job.id(), e));
+                                                out.push(LineKind::App, &e);
+                                                Err(e)
+                                            }
+                                        }
+                                    });
+                                    self.activity.push(format!("Запущено сканирование OpenSCAP: {} (задание #{})", title, id));
                                 }
-                                *running_clone.lock().unwrap() = false;
-                            });
+                            }
                         }
-                    }
+
+                        let active = self.jobs.active_in("OpenSCAP");
+                        if active > 0 {
+                            ui.spinner();
+                            ui.label(format!("Выполняется заданий: {}", active));
+                        }
+                    });
                 });
 
                 ui.separator();
@@ -314,17 +899,7 @@ impl App for SecurityScannerApp {
                 ui.group(|ui| {
                     ui.label("5. Вывод:");
 
-                    egui::ScrollArea::vertical()
-                        .max_height(200.0)
-                        .show(ui, |ui| {
-                            let output = self.output.lock().unwrap();
-                            ui.add(
-                                egui::TextEdit::multiline(&mut output.clone())
-                                    .desired_rows(10)
-                                    .desired_width(f32::INFINITY)
-                                    .font(egui::TextStyle::Monospace),
-                            );
-                        });
+                    self.oscap_console.show_jobs(ui, &self.oscap_output, 250.0);
                 });
 
                 ui.separator();
@@ -338,15 +913,14 @@ impl App for SecurityScannerApp {
                             let report_path = self.report_path.lock().unwrap().clone();
                             if std::path::Path::new(&report_path).exists() {
                                 if let Err(e) = open::that(&report_path) {
-                                    let mut out = self.output.lock().unwrap();
-                                    *out = format!("Не удалось открыть отчет: {}", e);
+                                    // No browser on this host, fall back to the embedded viewer
+                                    self.show_report_viewer = true;
+                                    self.activity.push(format!("Не удалось открыть отчет: {}. Результаты показаны в окне просмотра.", e));
                                 } else {
-                                    let mut out = self.output.lock().unwrap();
-                                    *out = format!("Открытие отчета: {}", report_path);
+                                    self.activity.push(format!("Открытие отчета: {}", report_path));
                                 }
                             } else {
-                                let mut out = self.output.lock().unwrap();
-                                *out = format!("Отчет не найден по пути: {}", report_path);
+                                self.activity.push(format!("Отчет не найден по пути: {}", report_path));
                             }
                         }
 
@@ -360,102 +934,272 @@ impl App for SecurityScannerApp {
                                 {
                                     match std::fs::copy(&report_path, &save_path) {
                                         Ok(_) => {
-                                            let mut out = self.output.lock().unwrap();
-                                            *out = format!("Отчет успешно скопирован в: {}", save_path.display());
+                                            self.activity.push(format!("Отчет успешно скопирован в: {}", save_path.display()));
                                         }
                                         Err(e) => {
-                                            let mut out = self.output.lock().unwrap();
-                                            *out = format!("Ошибка при копировании отчета: {}", e);
+                                            self.activity.push(format!("Ошибка при копировании отчета: {}", e));
                                         }
                                     }
                                 } else {
-                                    let mut out = self.output.lock().unwrap();
-                                    *out = "Скачивание отчета отменено пользователем.".to_string();
+                                    self.activity.push("Скачивание отчета отменено пользователем.");
                                 }
                             } else {
-                                let mut out = self.output.lock().unwrap();
-                                *out = format!("Отчет не найден по пути: {}", report_path);
+                                self.activity.push(format!("Отчет не найден по пути: {}", report_path));
                             }
                         }
+
+                        if ui.button("Просмотреть в приложении").clicked() {
+                            self.show_report_viewer = true;
+                        }
                     });
-                });
 
-            } else if selected_tool == "YARA" {
-                // YARA Functionality
-                ui.heading("YARA Сканирование");
+                    self.export_buttons(ui, Engine::Oval);
+                });
 
                 ui.separator();
 
-                // Section for selecting YARA rules file
+                // Section for the packages to upgrade
                 ui.group(|ui| {
-                    ui.label("1. Выберите файл правил YARA:");
+                    ui.label("7. Пакеты для обновления:");
 
-                    ui.horizontal(|ui| {
-                        if ui.button("Выбрать файл правил").clicked() {
-                            if let Some(path) = FileDialog::new()
-                                .add_filter("YARA Rules", &["yar", "yara"])
-                                .set_title("Выберите файл правил YARA")
-                                .pick_file()
+                    if ui.button("Обновить сведения из базы RPM").clicked() {
+                        let message = match rpm::correlate(&mut self.findings.lock().unwrap()) {
+                            Ok(count) => {
+                                self.findings_revision.fetch_add(1, Ordering::Relaxed);
+                                format!("Найдено установленных пакетов: {}", count)
+                            }
+                            Err(e) => e,
+                        };
+                        self.activity.push(message);
+                    }
+
+                    let findings = self.findings.lock().unwrap();
+                    let mut shown: Vec<&str> = Vec::new();
+
+                    egui::ScrollArea::vertical()
+                        .id_source("packages_to_upgrade")
+                        .max_height(150.0)
+                        .show(ui, |ui| {
+                            for package in findings
+                                .iter()
+                                .filter(|f| f.engine == Engine::Oval)
+                                .flat_map(|f| f.packages.iter())
                             {
-                                let path_str = path.to_string_lossy().to_string();
-                                let mut rules_path = self.yara_rules_path.lock().unwrap();
-                                *rules_path = Some(path_str.clone());
-                                let mut output = self.output.lock().unwrap();
-                                *output = format!("Выбран файл правил YARA: {}", path.display());
+                                if shown.contains(&package.name.as_str()) {
+                                    continue;
+                                }
+                                shown.push(&package.name);
+
+                                if package.installed.is_empty() {
+                                    ui.monospace(format!("{}: не установлен → {}", package.name, package.fixed_version));
+                                }
+                                for installed in &package.installed {
+                                    ui.monospace(format!(
+                                        "{} → {} ({}, установлен {})",
+                                        installed.nevra, package.fixed_version, installed.vendor, installed.install_date
+                                    ));
+                                }
                             }
-                        }
+                        });
 
-                        // Display selected rules file
-                        let rules_path = self.yara_rules_path.lock().unwrap();
-                        if let Some(ref path) = *rules_path {
-                            ui.label(format!("Выбранный файл: {}", path));
-                        } else {
-                            ui.label("Файл правил не выбран");
+                    let commands = rpm::upgrade_commands(&findings);
+                    if commands.is_empty() {
+                        ui.label("Нет пакетов для обновления");
+                    } else {
+                        for command in &commands {
+                            ui.monospace(command);
                         }
-                    });
+                        if ui.button("Копировать команды").clicked() {
+                            ui.output_mut(|o| o.copied_text = commands.join("\n"));
+                        }
+                    }
                 });
 
+            } else if selected_tool == "YARA" {
+                // YARA Functionality
+                ui.heading("YARA Сканирование");
+
                 ui.separator();
 
-                // Section for selecting scan path
+                // Section for selecting YARA rule files and directories
                 ui.group(|ui| {
-                    ui.label("2. Выберите файл или папку для сканирования:");
+                    ui.label("1. Выберите файлы правил YARA:");
 
                     ui.horizontal(|ui| {
-                        if ui.button("Выбрать файл для сканирования").clicked() {
-                            if let Some(path) = FileDialog::new()
-                                .set_title("Выберите файл для сканирования")
-                                .pick_file()
+                        if ui.button("Добавить файлы").clicked() {
+                            if let Some(paths) = FileDialog::new()
+                                .add_filter("YARA Rules", &rules::RULE_EXTENSIONS)
+                                .set_title("Выберите файлы правил YARA")
+                                .pick_files()
                             {
-                                let path_str = path.to_string_lossy().to_string();
-                                let mut scan_path = self.scan_path.lock().unwrap();
-                                *scan_path = Some(path_str.clone());
-                                let mut output = self.output.lock().unwrap();
-                                *output = format!("Выбран файл для сканирования: {}", path.display());
+                                let mut yara_rules = self.yara_rules.lock().unwrap();
+                                let added =
+                                    rules::add_files(&mut yara_rules, paths.iter().map(|path| path.to_string_lossy().to_string()));
+                                self.activity.push(format!("Добавлено файлов правил YARA: {}", added));
                             }
                         }
+                        if ui.button("Добавить папку").clicked() {
+                            if let Some(dir) = FileDialog::new().set_title("Выберите папку с правилами YARA").pick_folder() {
+                                match rules::collect_dir(&dir) {
+                                    Ok(paths) => {
+                                        let found = paths.len();
+                                        let added = rules::add_files(&mut self.yara_rules.lock().unwrap(), paths);
+                                        self.activity.push(format!(
+                                            "Папка правил {}: найдено файлов {}, добавлено {}",
+                                            dir.display(),
+                                            found,
+                                            added
+                                        ));
+                                    }
+                                    Err(e) => self.activity.push(e),
+                                }
+                            }
+                        }
+                        if ui.button("Очистить список").clicked() {
+                            self.yara_rules.lock().unwrap().clear();
+                        }
+                        if ui.button("Библиотека правил").clicked() {
+                            self.show_library = true;
+                        }
+                        if ui.button("Редактор правил").clicked() {
+                            self.show_editor = true;
+                        }
+                    });
 
-                        if ui.button("Выбрать папку для сканирования").clicked() {
-                            if let Some(path) = FileDialog::new()
-                                .set_title("Выберите папку для сканирования")
-                                .pick_folder()
-                            {
-                                let path_str = path.to_string_lossy().to_string();
-                                let mut scan_path = self.scan_path.lock().unwrap();
-                                *scan_path = Some(path_str.clone());
-                                let mut output = self.output.lock().unwrap();
-                                *output = format!("Выбрана папка для сканирования: {}", path.display());
+                    let mut yara_rules = self.yara_rules.lock().unwrap();
+                    if yara_rules.is_empty() {
+                        ui.label("Файлы правил не выбраны");
+                    } else {
+                        ui.horizontal(|ui| {
+                            let enabled = yara_rules.iter().filter(|rule| rule.enabled).count();
+                            ui.label(format!("Включено {} из {}", enabled, yara_rules.len()));
+                            if ui.small_button("Включить все").clicked() {
+                                yara_rules.iter_mut().for_each(|rule| rule.enabled = true);
+                            }
+                            if ui.small_button("Выключить все").clicked() {
+                                yara_rules.iter_mut().for_each(|rule| rule.enabled = false);
                             }
+                        });
+
+                        let mut remove = None;
+                        egui::ScrollArea::vertical()
+                            .id_source("yara_rules_list")
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                egui::Grid::new("yara_rules_grid").striped(true).show(ui, |ui| {
+                                    ui.label("");
+                                    ui.label("Пространство имен");
+                                    ui.label("Файл");
+                                    ui.label("");
+                                    ui.end_row();
+                                    for (index, rule) in yara_rules.iter_mut().enumerate() {
+                                        ui.checkbox(&mut rule.enabled, "");
+                                        ui.add(egui::TextEdit::singleline(&mut rule.namespace).desired_width(150.0));
+                                        ui.label(&rule.path);
+                                        if ui.small_button("Удалить").clicked() {
+                                            remove = Some(index);
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                        if let Some(index) = remove {
+                            yara_rules.remove(index);
                         }
+                    }
+                    let rule_set = yara_rules.clone();
+                    drop(yara_rules);
 
-                        // Display selected scan path
-                        let scan_path = self.scan_path.lock().unwrap();
-                        if let Some(ref path) = *scan_path {
-                            ui.label(format!("Сканируемый путь: {}", path));
-                        } else {
-                            ui.label("Путь для сканирования не выбран");
+                    ui.horizontal(|ui| {
+                        let validating = self.jobs.is_active("YARA", RULE_VALIDATION);
+                        if ui
+                            .add_enabled(!validating && !rule_set.is_empty(), egui::Button::new("Проверить правила"))
+                            .clicked()
+                        {
+                            let defines = self.yara_checkboxes.lock().unwrap().defines.clone();
+                            let validation = Arc::clone(&self.yara_validation);
+                            let activity = self.activity.clone();
+                            self.yara_diagnostic = None;
+                            self.jobs.spawn("YARA", RULE_VALIDATION, move |job| {
+                                let compilation = match rules::compile(&rule_set, &defines, job) {
+                                    Ok(compilation) => compilation,
+                                    Err(e) => {
+                                        activity.push(format!("Проверка правил YARA: {}", e));
+                                        return Err(e);
+                                    }
+                                };
+                                let warnings = compilation.count(Severity::Warning);
+                                let result = if compilation.compiled.is_some() {
+                                    Ok(format!("Правила YARA скомпилированы, предупреждений: {}", warnings))
+                                } else {
+                                    Err(format!(
+                                        "Правила YARA содержат ошибок: {}, предупреждений: {}",
+                                        compilation.count(Severity::Error),
+                                        warnings
+                                    ))
+                                };
+                                activity.push(match &result {
+                                    Ok(message) | Err(message) => message.clone(),
+                                });
+                                *validation.lock().unwrap() = Some(compilation);
+                                result
+                            });
+                        }
+                        if validating {
+                            ui.spinner();
                         }
+                        if ui.button("Очистить кэш правил").clicked() {
+                            match rules::clear_cache() {
+                                Ok(removed) => {
+                                    self.activity.push(format!("Удалено скомпилированных наборов правил: {}", removed))
+                                }
+                                Err(e) => self.activity.push(e),
+                            }
+                            *self.yara_validation.lock().unwrap() = None;
+                        }
+                    });
+                    self.rule_diagnostics(ui);
+                });
+
+                ui.separator();
+
+                // Section for selecting scan path
+                ui.group(|ui| {
+                    ui.label("2. Выберите, что сканировать:");
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut self.scan_memory, false, "Файлы и папки");
+                        ui.radio_value(&mut self.scan_memory, true, "Память процессов");
                     });
+
+                    if self.scan_memory {
+                        ui.small("Память сканируется через yara из PATH (yara ПРАВИЛА PID), нужен доступ ptrace.");
+                        self.processes_panel.show(ui);
+                        return;
+                    }
+                    {
+                        let mut scan_targets = self.scan_targets.lock().unwrap();
+                        self.targets_panel.show(ui, &mut scan_targets, &self.activity);
+                    }
+
+                    // Filters of the app's own walker, used when a folder is scanned
+                    egui::CollapsingHeader::new("Фильтры обхода папки")
+                        .id_source("yara_filters")
+                        .show(ui, |ui| {
+                            let mut options = self.yara_checkboxes.lock().unwrap();
+                            let filters = &mut options.filters;
+                            lines_field(
+                                ui,
+                                "Включать только (шаблоны, по одному в строке):",
+                                "*.php\n/var/www/**/*.js",
+                                &mut filters.include,
+                            );
+                            lines_field(ui, "Исключать:", "/proc\n*.iso", &mut filters.exclude);
+                            lines_field(ui, "Пропускать MIME-типы:", "video/*\napplication/x-iso9660-image", &mut filters.skip_mime);
+                            ui.small("Шаблоны с \"/\" сравниваются с полным путем, остальные с именем файла; \"**\" охватывает вложенные папки.");
+                            ui.checkbox(&mut filters.one_filesystem, "Не выходить за пределы файловой системы");
+                            ui.checkbox(&mut filters.skip_hidden, "Пропускать скрытые файлы и папки");
+                            ui.label("Рекурсия, символические ссылки и максимальный размер файла задаются в опциях ниже.");
+                        });
                 });
 
                 ui.separator();
@@ -466,12 +1210,80 @@ impl App for SecurityScannerApp {
 
                     let mut yara_options = self.yara_checkboxes.lock().unwrap();
 
-                    ui.vertical(|ui| {
-                        ui.checkbox(&mut yara_options.recursive, "Рекурсивное сканирование (-r)");
-                        ui.checkbox(&mut yara_options.fast_scan, "Быстрое сканирование (-f)");
-                        ui.checkbox(&mut yara_options.no_warnings, "Не показывать предупреждения (-w)");
-                        ui.checkbox(&mut yara_options.print_tags, "Показать теги (-t)");
+                    let options = &mut *yara_options;
+
+                    ui.columns(2, |columns| {
+                        let ui = &mut columns[0];
+                        ui.checkbox(&mut options.recursive, "Рекурсивное сканирование (-r)");
+                        ui.checkbox(&mut options.fast_scan, "Быстрое сканирование (-f)");
+                        ui.checkbox(&mut options.no_warnings, "Не показывать предупреждения (-w)");
+                        ui.checkbox(&mut options.no_follow_symlinks, "Не переходить по символическим ссылкам (-N)");
+                        ui.checkbox(&mut options.compiled_rules, "Скомпилированные правила (-C)");
+
+                        let ui = &mut columns[1];
+                        ui.checkbox(&mut options.print_tags, "Показать теги (-g)");
+                        ui.checkbox(&mut options.print_meta, "Показать метаданные (-m)");
+                        ui.checkbox(&mut options.print_strings, "Показать совпавшие строки (-s)");
+                        ui.checkbox(&mut options.print_namespace, "Показать пространство имен (-e)");
+                    });
+
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Параллельных обработчиков:");
+                        ui.add(egui::DragValue::new(&mut options.workers).clamp_range(1..=scan::MAX_WORKERS));
+                        ui.label("Потоки (-p):");
+                        ui.add(egui::DragValue::new(&mut options.threads).clamp_range(0..=scan::YARA_MAX_THREADS));
+                        ui.label("Тайм-аут, с (-a):");
+                        ui.add(egui::DragValue::new(&mut options.timeout));
+                        ui.label("Макс. правил на файл (--max-rules):");
+                        ui.add(egui::DragValue::new(&mut options.max_rules));
+                        ui.label("Макс. размер файла, байт (-z):");
+                        ui.add(egui::DragValue::new(&mut options.max_file_size).speed(1024.0));
                     });
+                    ui.small("0 — значение yara по умолчанию (без ограничения)");
+                    ui.horizontal_wrapped(|ui| {
+                        ui.checkbox(&mut options.scan_archives, "Проверять содержимое архивов и образов контейнеров");
+                        ui.label("Предел распаковки, МБ:");
+                        ui.add_enabled(
+                            options.scan_archives,
+                            egui::DragValue::new(&mut options.archive_limit_mb).clamp_range(1..=1024 * 1024),
+                        );
+                    });
+                    ui.small("zip, tar, tar.gz, пакеты RPM (через rpm2cpio) и слои образов OCI распаковываются во временный каталог, совпадения показываются как архив!путь");
+                    ui.small("Файлы делятся на пакеты между параллельными процессами yara, потоки -p распределяются между ними. Пауза действует после текущего пакета.");
+
+                    text_field(ui, "Только правило (-i):", &mut options.identifier);
+
+                    ui.label("Внешние переменные (-d):");
+                    let mut remove = None;
+                    for (index, define) in options.defines.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut define.name).hint_text("имя").desired_width(120.0));
+                            ui.label("=");
+                            ui.add(egui::TextEdit::singleline(&mut define.value).hint_text("значение").desired_width(200.0));
+                            if ui.small_button("✖").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        options.defines.remove(index);
+                    }
+                    if ui.small_button("Добавить переменную").clicked() {
+                        options.defines.push(ExternalVariable::default());
+                    }
+
+                    let yara_rules = self.yara_rules.lock().unwrap().clone();
+                    let scan_targets = self.scan_targets.lock().unwrap().clone();
+                    if !yara_rules.is_empty() && (self.scan_memory || !scan_targets.is_empty()) {
+                        let key = (options.clone(), yara_rules, (!self.scan_memory).then_some(scan_targets));
+                        let valid = self.yara_options_check.check(key, |(options, rules, targets)| match targets {
+                            Some(targets) => options.validate(rules, targets),
+                            None => options.validate_rules(rules),
+                        });
+                        if let Err(e) = valid {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                    }
                 });
 
                 ui.separator();
@@ -479,93 +1291,93 @@ impl App for SecurityScannerApp {
                 // Section for starting the scan
                 ui.group(|ui| {
                     ui.label("4. Запустите сканирование:");
+                    ui.small(format!("Движок: {}", scan::YARA_ENGINE));
 
-                    if *self.is_running.lock().unwrap() {
-                        ui.add(egui::Label::new("Выполнение сканирования...").wrap(false));
-                    } else {
+                    ui.horizontal(|ui| {
                         if ui.button("Запустить сканирование").clicked() {
-                            let output_clone = Arc::clone(&self.output);
-                            let running_clone = Arc::clone(&self.is_running);
-                            let rules_path_clone = Arc::clone(&self.yara_rules_path);
-                            let scan_path_clone = Arc::clone(&self.scan_path);
-                            let yara_options_clone = Arc::clone(&self.yara_checkboxes);
-
-                            *running_clone.lock().unwrap() = true;
-                            *output_clone.lock().unwrap() = "Начало сканирования...".to_string();
-
-                            // Start scanning in a separate thread
-                            std::thread::spawn(move || {
-                                let rules_path = {
-                                    let rules = rules_path_clone.lock().unwrap();
-                                    match &*rules {
-                                        Some(path) => path.clone(),
-                                        None => {
-                                            let mut out = output_clone.lock().unwrap();
-                                            *out = "Не выбран файл правил YARA.".to_string();
-                                            *running_clone.lock().unwrap() = false;
-                                            return;
-                                        }
-                                    }
-                                };
-
-                                let scan_path = {
-                                    let scan = scan_path_clone.lock().unwrap();
-                                    match &*scan {
-                                        Some(path) => path.clone(),
-                                        None => {
-                                            let mut out = output_clone.lock().unwrap();
-                                            *out = "Не выбран путь для сканирования.".to_string();
-                                            *running_clone.lock().unwrap() = false;
-                                            return;
-                                        }
-                                    }
-                                };
-
-                                let yara_options = {
-                                    let options = yara_options_clone.lock().unwrap();
-                                    options.clone()
-                                };
-
-                                // Form command with additional options
-                                let mut args = Vec::new();
-
-                                if yara_options.recursive {
-                                    args.push("-r");
-                                }
-                                if yara_options.fast_scan {
-                                    args.push("-f");
+                            let yara_rules = self.yara_rules.lock().unwrap().clone();
+                            let scan_targets = self.scan_targets.lock().unwrap().clone();
+                            // Processes replace the paths in the memory scan mode
+                            let scan_processes = self.scan_memory.then(|| self.processes_panel.selection());
+                            let no_targets = match &scan_processes {
+                                Some(processes) => processes.is_empty(),
+                                None => scan_targets.is_empty(),
+                            };
+                            match (yara_rules.is_empty(), no_targets) {
+                                (true, _) => {
+                                    self.activity.push("Не выбраны файлы правил YARA.");
                                 }
-                                if yara_options.no_warnings {
-                                    args.push("-w");
+                                (_, true) if self.scan_memory => {
+                                    self.activity.push("Не выбраны процессы для сканирования.");
                                 }
-                                if yara_options.print_tags {
-                                    args.push("-t");
+                                (_, true) => {
+                                    self.activity.push("Не выбраны цели сканирования.");
                                 }
-
-                                args.push(&rules_path);
-                                args.push(&scan_path);
-
-                                let output = Command::new("yara")
-                                    .args(&args)
-                                    .output();
-
-                                match output {
-                                    Ok(output) => {
-                                        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                                        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                                        let combined = format!("STDOUT:\n{}\nSTDERR:\n{}", stdout, stderr);
-                                        let mut out = output_clone.lock().unwrap();
-                                        *out = combined;
-                                    }
-                                    Err(e) => {
-                                        let mut out = output_clone.lock().unwrap();
-                                        *out = format!("Ошибка при запуске YARA: {}", e);
+                                (false, false) => {
+                                    let output_clone = Arc::clone(&self.yara_output);
+                                    let activity = self.activity.clone();
+                                    let yara_options = self.yara_checkboxes.lock().unwrap().clone();
+                                    let valid = match &scan_processes {
+                                        Some(_) => yara_options.validate_rules(&yara_rules),
+                                        None => yara_options.validate(&yara_rules, &scan_targets),
+                                    };
+                                    if let Err(e) = valid {
+                                        self.activity.push(e);
+                                        return;
                                     }
+                                    let findings_clone = Arc::clone(&self.findings);
+                                    let findings_revision = Arc::clone(&self.findings_revision);
+                                    let scan_runs_clone = Arc::clone(&self.scan_runs);
+
+                                    // Start scanning as a background job
+                                    let title = match (&scan_processes, scan_targets.as_slice()) {
+                                        (Some(processes), _) => format!("Память процессов: {}", processes.len()),
+                                        (None, [target]) => target.clone(),
+                                        (None, targets) => format!("{} и еще {}", targets[0], targets.len() - 1),
+                                    };
+                                    let id = self.jobs.spawn("YARA", &title, move |job| {
+                                        let output = output_clone.lock().unwrap().open(job.id());
+                                        output.lock().unwrap().push(LineKind::App, "Начало сканирования...");
+                                        let monitor = Tee {
+                                            monitor: job,
+                                            console: &output,
+                                        };
+                                        let result = match &scan_processes {
+                                            Some(processes) => processes::scan(&yara_options, &yara_rules, processes, &monitor),
+                                            None => scan::run_yara(&yara_options, &yara_rules, &scan_targets, &monitor),
+                                        };
+                                        let mut out = output.lock().unwrap();
+                                        match result {
+                                            Ok(outcome) => {
+                                                activity.push(format!(
+                                                    "Сканирование YARA завершено (задание #{}), находок: {}",
+                                                    job.id(),
+                                                    outcome.findings.len()
+                                                ));
+                                                store_findings(&findings_clone, &findings_revision, Engine::Yara, outcome.findings);
+                                                store_scan_run(&scan_runs_clone, outcome.run);
+                                                let notes = outcome.notes.join("\n");
+                                                out.push(LineKind::App, &notes);
+                                                Ok(notes)
+                                            }
+                                            Err(e) => {
+                                                activity.push(format!("Сканирование YARA (задание #{}): {}", job.id(), e));
+                                                out.push(LineKind::App, &e);
+                                                Err(e)
+                                            }
+                                        }
+                                    });
+                                    self.activity.push(format!("Запущено сканирование YARA: {} (задание #{})", title, id));
                                 }
-                                *running_clone.lock().unwrap() = false;
-                            });
+                            }
                         }
-                    }
+
+                        let active = self.jobs.active_in("YARA");
+                        if active > 0 {
+                            ui.spinner();
+                            ui.label(format!("Выполняется заданий: {}", active));
+                        }
+                    });
                 });
 
                 ui.separator();
@@ -574,24 +1386,90 @@ impl App for SecurityScannerApp {
                 ui.group(|ui| {
                     ui.label("5. Вывод:");
 
-                    egui::ScrollArea::vertical()
-                        .max_height(200.0)
-                        .show(ui, |ui| {
-                            let output = self.output.lock().unwrap();
-                            ui.add(
-                                egui::TextEdit::multiline(&mut output.clone())
-                                    .desired_rows(10)
-                                    .desired_width(f32::INFINITY)
-                                    .font(egui::TextStyle::Monospace),
-                            );
-                        });
+                    self.yara_console.show_jobs(ui, &self.yara_output, 250.0);
+                });
+
+                ui.separator();
+
+                // Section for exporting the results
+                ui.group(|ui| {
+                    ui.label("6. Экспорт результатов:");
+
+                    if ui.button("Просмотреть в приложении").clicked() {
+                        self.show_report_viewer = true;
+                    }
+
+                    self.export_buttons(ui, Engine::Yara);
                 });
             }
         });
     }
 }
 
+// Options of the headless modes: `--format json|csv|sarif` for the results and `--output PATH`
+fn headless_options(args: &[String]) -> Result<(ExportFormat, Option<PathBuf>), String> {
+    let mut format = ExportFormat::Json;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().ok_or("Не указан формат после --format")?;
+                format = ExportFormat::from_name(name)
+                    .ok_or_else(|| format!("Неизвестный формат результатов: {} (json, csv или sarif)", name))?;
+            }
+            "--output" => output = Some(PathBuf::from(args.next().ok_or("Не указан путь после --output")?)),
+            other => return Err(format!("Неизвестный параметр: {}", other)),
+        }
+    }
+    Ok((format, output))
+}
+
 fn main() {
+    // Headless modes used by the background service and systemd timers
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("--scheduler") => {
+            // Every job writes its own results file, one output path cannot serve them all
+            match headless_options(&args[2..]) {
+                Ok((format, None)) => scheduler::run_service(format),
+                Ok((_, Some(_))) => {
+                    eprintln!("Параметр --output используется только с --run-job");
+                    std::process::exit(2);
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            }
+            return;
+        }
+        Some("--run-job") => {
+            let name = args.get(2).cloned().unwrap_or_default();
+            let job = headless_options(args.get(3..).unwrap_or_default()).and_then(|options| {
+                scheduler::load_jobs()
+                    .and_then(|jobs| {
+                        jobs.into_iter()
+                            .find(|job| job.name == name)
+                            .ok_or_else(|| format!("Задание не найдено: {}", name))
+                    })
+                    .map(|job| (job, options))
+            });
+            match job {
+                Ok((job, (format, output))) => {
+                    let entry = scheduler::run_job(&job, format, output.as_deref(), &scan::NoMonitor);
+                    println!("{}", entry.message);
+                    std::process::exit(if entry.success { 0 } else { 1 });
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        _ => {}
+    }
+
     let app = SecurityScannerApp::default();
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
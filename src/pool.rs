@@ -0,0 +1,232 @@
+// Worker pool and progress accounting of file scans: files and bytes done, current file,
+// throughput and time left, with pauses left out of the scanning time
+use crate::scan::{ScanMonitor, CANCELLED};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// How often a paused worker checks whether it may continue
+const PAUSE_POLL: Duration = Duration::from_millis(200);
+// Progress is sent to the monitor at most this often
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Default)]
+pub struct ScanProgress {
+    // What is counted, e.g. "Файлов"
+    pub label: &'static str,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    // File or batch being scanned
+    pub current: String,
+    // Scanning time without pauses
+    pub elapsed: Duration,
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["Б", "КБ", "МБ", "ГБ", "ТБ"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+impl ScanProgress {
+    // Bytes per second of scanning time
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.bytes_done as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    // None until something has been scanned
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.throughput();
+        if rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(self.bytes_total.saturating_sub(self.bytes_done) as f64 / rate))
+    }
+
+    // Share of the bytes done, of the files for empty files
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total > 0 {
+            self.bytes_done as f32 / self.bytes_total as f32
+        } else if self.files_total > 0 {
+            self.files_done as f32 / self.files_total as f32
+        } else {
+            0.0
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        let mut text = format!(
+            "{}: {} из {}, {} из {}, {}/с",
+            self.label,
+            self.files_done,
+            self.files_total,
+            format_bytes(self.bytes_done),
+            format_bytes(self.bytes_total),
+            format_bytes(self.throughput() as u64)
+        );
+        if let Some(eta) = self.eta().filter(|_| self.files_done < self.files_total) {
+            text.push_str(&format!(", осталось ~{}", format_duration(eta)));
+        }
+        text
+    }
+}
+
+struct TrackerState {
+    progress: ScanProgress,
+    paused_total: Duration,
+    // Set while the scan is paused
+    paused_since: Option<Instant>,
+    last_report: Option<Instant>,
+}
+
+// Shared by the workers of one scan
+pub struct ProgressTracker<'a> {
+    monitor: &'a dyn ScanMonitor,
+    started: Instant,
+    state: Mutex<TrackerState>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub fn new(monitor: &'a dyn ScanMonitor, label: &'static str, files_total: usize, bytes_total: u64) -> Self {
+        let tracker = Self {
+            monitor,
+            started: Instant::now(),
+            state: Mutex::new(TrackerState {
+                progress: ScanProgress {
+                    label,
+                    files_total,
+                    bytes_total,
+                    ..Default::default()
+                },
+                paused_total: Duration::ZERO,
+                paused_since: None,
+                last_report: None,
+            }),
+        };
+        tracker.report(true, |_| {});
+        tracker
+    }
+
+    // Apply a change and send the progress when it is due
+    fn report(&self, force: bool, change: impl FnOnce(&mut ScanProgress)) {
+        let mut state = self.state.lock().unwrap();
+        change(&mut state.progress);
+        let pause = state.paused_total + state.paused_since.map_or(Duration::ZERO, |since| since.elapsed());
+        state.progress.elapsed = self.started.elapsed().saturating_sub(pause);
+
+        if force || state.last_report.is_none_or(|last| last.elapsed() >= REPORT_INTERVAL) {
+            state.last_report = Some(Instant::now());
+            self.monitor.scan_progress(&state.progress);
+            self.monitor.progress(state.progress.fraction());
+        }
+    }
+
+    fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        match (paused, state.paused_since) {
+            (true, None) => state.paused_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                state.paused_total += since.elapsed();
+                state.paused_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    // Called by a worker before it takes the next file; blocks while the scan is paused
+    pub fn checkpoint(&self) -> Result<(), String> {
+        while self.monitor.is_paused() && !self.monitor.is_cancelled() {
+            self.set_paused(true);
+            std::thread::sleep(PAUSE_POLL);
+        }
+        self.set_paused(false);
+        if self.monitor.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn start(&self, current: &str) {
+        self.report(false, |progress| progress.current = current.to_string());
+    }
+
+    pub fn finish(&self, files: usize, bytes: u64) {
+        self.report(false, |progress| {
+            progress.files_done += files;
+            progress.bytes_done += bytes;
+        });
+    }
+
+    // Final numbers, always sent
+    pub fn done(&self) -> ScanProgress {
+        self.report(true, |progress| progress.current.clear());
+        self.state.lock().unwrap().progress.clone()
+    }
+}
+
+// Run `task` for every item on `workers` threads, each thread with its own state from `init`.
+// Results keep the order of the items; the first error stops the remaining work.
+pub fn run_pool<T, S, R>(
+    items: &[T],
+    workers: usize,
+    init: impl Fn() -> S + Sync,
+    task: impl Fn(&mut S, &T) -> Result<R, String> + Sync,
+) -> Result<Vec<R>, String>
+where
+    T: Sync,
+    R: Send,
+{
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    let error = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                let mut state = init();
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    match task(&mut state, item) {
+                        Ok(result) => results.lock().unwrap().push((index, result)),
+                        Err(e) => {
+                            failed.store(true, Ordering::Relaxed);
+                            error.lock().unwrap().get_or_insert(e);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(e);
+    }
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
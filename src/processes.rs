@@ -0,0 +1,245 @@
+// Running processes read from /proc and YARA scans of their memory. Memory is always scanned
+// by the yara binary (`yara RULES PID`), which needs ptrace access, usually root.
+use crate::evidence;
+use crate::findings::{self, Engine, Finding, ScanRun};
+use crate::pool::{self, ProgressTracker};
+use crate::rules::RuleFile;
+use crate::scan::{self, launch_error, run_command, timestamp, ScanMonitor, ScanOutcome, YaraOptions};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::Command;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub user: String,
+    // Short name from /proc/PID/status
+    pub name: String,
+    pub command_line: String,
+    // Empty when the link cannot be read, e.g. for processes of other users
+    pub exe: String,
+    // Resident memory, bytes
+    #[serde(skip)]
+    pub memory: u64,
+    // Clock ticks after boot, tells the process from a later one with the same PID
+    #[serde(skip)]
+    pub start_time: u64,
+}
+
+impl ProcessInfo {
+    pub fn label(&self) -> String {
+        if self.exe.is_empty() {
+            format!("PID {} {}", self.pid, self.name)
+        } else {
+            format!("PID {} {} ({})", self.pid, self.name, self.exe)
+        }
+    }
+
+    pub fn matches(&self, query: &str) -> bool {
+        self.pid.to_string().contains(query)
+            || [&self.user, &self.name, &self.command_line, &self.exe]
+                .iter()
+                .any(|field| field.to_lowercase().contains(query))
+    }
+}
+
+// Value of a "Name:\tvalue" line of /proc/PID/status
+fn status_field<'a>(status: &'a str, name: &str) -> Option<&'a str> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+// Field 22 of /proc/PID/stat. The name in field 2 may contain spaces and parentheses,
+// so the fields are counted from its closing parenthesis.
+fn start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+fn read(pid: u32, users: &HashMap<u32, String>) -> Option<ProcessInfo> {
+    let dir = format!("/proc/{}", pid);
+    let status = std::fs::read_to_string(format!("{}/status", dir)).ok()?;
+    let cmdline = std::fs::read(format!("{}/cmdline", dir)).ok()?;
+    // Kernel threads have no command line and no user memory to scan
+    if cmdline.is_empty() {
+        return None;
+    }
+    let start_time = start_time(pid)?;
+
+    let uid: Option<u32> = status_field(&status, "Uid")
+        .and_then(|uids| uids.split_whitespace().next())
+        .and_then(|uid| uid.parse().ok());
+    let memory = status_field(&status, "VmRSS")
+        .and_then(|rss| rss.trim_end_matches("kB").trim().parse::<u64>().ok())
+        .unwrap_or(0)
+        * 1024;
+    let command_line = String::from_utf8_lossy(&cmdline)
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(ProcessInfo {
+        pid,
+        user: uid
+            .map(|uid| users.get(&uid).cloned().unwrap_or_else(|| uid.to_string()))
+            .unwrap_or_default(),
+        name: status_field(&status, "Name").unwrap_or_default().to_string(),
+        command_line,
+        exe: std::fs::read_link(format!("{}/exe", dir))
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        memory,
+        start_time,
+    })
+}
+
+// Memory of other users' processes can only be read by root
+pub fn is_root() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            // Real, effective, saved and file system uid
+            let effective = status_field(&status, "Uid")?.split_whitespace().nth(1)?.to_string();
+            Some(effective == "0")
+        })
+        .unwrap_or(false)
+}
+
+// User-space processes sorted by PID, without this application itself
+pub fn list() -> Vec<ProcessInfo> {
+    let users = evidence::account_names("/etc/passwd");
+    let own = std::process::id();
+    let mut processes: Vec<ProcessInfo> = std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+                .filter(|pid| *pid != own)
+                .filter_map(|pid| read(pid, &users))
+                .collect()
+        })
+        .unwrap_or_default();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+// Outcome of scanning one process
+struct ProcessResult {
+    findings: Vec<Finding>,
+    // yara could not read the memory, e.g. without root or after the process exited
+    error: bool,
+    // The PID belongs to another process than the one selected
+    replaced: bool,
+}
+
+pub fn scan(
+    options: &YaraOptions,
+    rules: &[RuleFile],
+    processes: &[ProcessInfo],
+    monitor: &dyn ScanMonitor,
+) -> Result<ScanOutcome, String> {
+    options.validate_rules(rules)?;
+    if processes.is_empty() {
+        return Err("Не выбраны процессы для сканирования".to_string());
+    }
+
+    let workers = options.workers.max(1) as usize;
+    let mut notes = Vec::new();
+    let (mut process_options, process_rules) = scan::parallel_rules(options, rules, workers, processes.len() > 1, &mut notes, monitor);
+    process_options.recursive = false;
+    let started_at = timestamp();
+    let tracker = ProgressTracker::new(monitor, "Процессов", processes.len(), processes.iter().map(|p| p.memory).sum());
+
+    let results = pool::run_pool(
+        processes,
+        workers,
+        || (),
+        |_, process| {
+            tracker.checkpoint()?;
+            tracker.start(&process.label());
+            // The selected process may have exited and its PID been reused since the list was read,
+            // matches of the new process must not be reported for the old one
+            let replaced = || start_time(process.pid) != Some(process.start_time);
+            if replaced() {
+                tracker.finish(1, process.memory);
+                return Ok(ProcessResult {
+                    findings: Vec::new(),
+                    error: false,
+                    replaced: true,
+                });
+            }
+            let args = scan::yara_args(&process_options, &process_rules, &process.pid.to_string(), false);
+            let (stdout, stderr, status) =
+                run_command(Command::new("yara").args(&args), monitor).map_err(|e| launch_error("YARA", e))?;
+            tracker.finish(1, process.memory);
+            if replaced() {
+                return Ok(ProcessResult {
+                    findings: Vec::new(),
+                    error: false,
+                    replaced: true,
+                });
+            }
+
+            let mut findings = findings::parse_yara_output(&stdout);
+            for finding in &mut findings {
+                finding.target = process.label();
+                finding.process = Some(process.clone());
+            }
+            Ok(ProcessResult {
+                findings,
+                error: !status.success() || stderr.contains("error"),
+                replaced: false,
+            })
+        },
+    )?;
+    let progress = tracker.done();
+
+    let failed = results.iter().filter(|result| result.error).count();
+    let replaced = results.iter().filter(|result| result.replaced).count();
+    let findings: Vec<Finding> = results.into_iter().flat_map(|result| result.findings).collect();
+    notes.splice(
+        0..0,
+        [
+            format!("Совпадений: {}", findings.len()),
+            format!("Параллельных процессов yara: {}. {}", workers.min(processes.len()), progress.summary()),
+        ],
+    );
+    if failed > 0 {
+        notes.push(format!(
+            "Не удалось прочитать память процессов: {} (нужны права root или процесс завершился)",
+            failed
+        ));
+    }
+    if replaced > 0 {
+        notes.push(format!(
+            "Пропущено процессов, завершившихся до или во время сканирования (PID занят другим процессом): {}",
+            replaced
+        ));
+    }
+    let rule_paths: Vec<&str> = rules.iter().filter(|rule| rule.enabled).map(|rule| rule.path.as_str()).collect();
+
+    Ok(ScanOutcome {
+        notes,
+        findings,
+        complete: failed == 0,
+        run: ScanRun {
+            engine: Engine::Yara,
+            started_at,
+            command: format!(
+                "yara {} <PID> (процессов: {})",
+                scan::yara_args(&process_options, &process_rules, "", false)
+                    .into_iter()
+                    .filter(|arg| !arg.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                processes.len()
+            ),
+            content_version: rule_paths.join(", "),
+            targets: Vec::new(),
+        },
+    })
+}
@@ -0,0 +1,125 @@
+// Process list of the YARA memory scan mode
+use crate::pool::format_bytes;
+use crate::processes::{self, ProcessInfo};
+use eframe::egui;
+use std::collections::HashSet;
+
+fn shorten(text: &str, limit: usize) -> String {
+    if text.chars().count() > limit {
+        format!("{}…", text.chars().take(limit).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+pub struct ProcessesPanel {
+    processes: Vec<ProcessInfo>,
+    selected: HashSet<u32>,
+    // Scan every process running when the scan starts
+    all: bool,
+    filter: String,
+    // The privileges do not change while the application runs
+    root: bool,
+}
+
+impl Default for ProcessesPanel {
+    fn default() -> Self {
+        Self {
+            processes: processes::list(),
+            selected: HashSet::new(),
+            all: false,
+            filter: String::new(),
+            root: processes::is_root(),
+        }
+    }
+}
+
+impl ProcessesPanel {
+    // Processes to scan; with "all" the list is read again
+    pub fn selection(&self) -> Vec<ProcessInfo> {
+        if self.all {
+            processes::list()
+        } else {
+            self.processes
+                .iter()
+                .filter(|process| self.selected.contains(&process.pid))
+                .cloned()
+                .collect()
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if !self.root {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                "Приложение запущено не от root: память процессов других пользователей прочитать не удастся.",
+            );
+        }
+
+        let query = self.filter.to_lowercase();
+        let found: Vec<usize> = self
+            .processes
+            .iter()
+            .enumerate()
+            .filter(|(_, process)| query.is_empty() || process.matches(&query))
+            .map(|(index, _)| index)
+            .collect();
+
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Обновить список").clicked() {
+                self.processes = processes::list();
+                let running: HashSet<u32> = self.processes.iter().map(|process| process.pid).collect();
+                self.selected.retain(|pid| running.contains(pid));
+            }
+            ui.checkbox(&mut self.all, "Все процессы");
+            ui.label("Фильтр:");
+            ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("PID, пользователь, имя, путь").desired_width(200.0));
+            if ui.add_enabled(!self.all, egui::Button::new("Выбрать найденные")).clicked() {
+                self.selected.extend(found.iter().map(|index| self.processes[*index].pid));
+            }
+            if ui.add_enabled(!self.all, egui::Button::new("Снять выбор")).clicked() {
+                self.selected.clear();
+            }
+        });
+        if self.all {
+            ui.label(format!("Будут просканированы все процессы ({} сейчас)", self.processes.len()));
+        } else {
+            ui.label(format!("Процессов: {}, найдено: {}, выбрано: {}", self.processes.len(), found.len(), self.selected.len()));
+        }
+
+        egui::ScrollArea::both()
+            .id_source("yara_processes")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                egui::Grid::new("yara_processes_grid").striped(true).show(ui, |ui| {
+                    ui.label("");
+                    ui.strong("PID");
+                    ui.strong("Пользователь");
+                    ui.strong("Имя");
+                    ui.strong("Память");
+                    ui.strong("Исполняемый файл");
+                    ui.strong("Командная строка");
+                    ui.end_row();
+
+                    for index in &found {
+                        let process = &self.processes[*index];
+                        let mut checked = self.all || self.selected.contains(&process.pid);
+                        if ui.add_enabled(!self.all, egui::Checkbox::new(&mut checked, "")).changed() {
+                            if checked {
+                                self.selected.insert(process.pid);
+                            } else {
+                                self.selected.remove(&process.pid);
+                            }
+                        }
+                        ui.label(process.pid.to_string());
+                        ui.label(&process.user);
+                        ui.label(&process.name);
+                        ui.label(format_bytes(process.memory));
+                        ui.monospace(&process.exe);
+                        ui.label(shorten(&process.command_line, 80)).on_hover_text(&process.command_line);
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+}
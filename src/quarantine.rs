@@ -0,0 +1,317 @@
+// Quarantine of files detected by YARA. A quarantined file is moved into a store only its
+// owner can open, with the contents XORed so it can neither run nor match the rules again.
+// The original path, permissions, owner, times and hash are kept to restore it unchanged.
+// Every action, failed ones included, is appended to the audit log.
+use crate::evidence;
+use crate::scan::timestamp;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions, Permissions};
+use std::io::{Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+const DIR: &str = "quarantine";
+const INDEX_FILE: &str = "quarantine/index.json";
+const AUDIT_FILE: &str = "quarantine/audit.log";
+// Not a secret: only keeps the stored bytes from being the original ones
+const KEY: &[u8] = b"security-scanner-quarantine";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QuarantineItem {
+    pub id: String,
+    pub original_path: String,
+    pub sha256: String,
+    pub size: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    // Modification time, seconds since the epoch
+    pub mtime: i64,
+    pub quarantined_at: String,
+    // Rules that matched the file
+    pub reason: String,
+}
+
+impl QuarantineItem {
+    fn stored_path(&self) -> PathBuf {
+        store_dir().join(format!("{}.bin", self.id))
+    }
+}
+
+#[derive(Clone)]
+pub struct AuditEntry {
+    pub time: String,
+    pub user: String,
+    pub action: String,
+    pub path: String,
+    pub sha256: String,
+    pub result: String,
+}
+
+fn store_dir() -> PathBuf {
+    storage::data_dir().join(DIR)
+}
+
+// Created on first use with access for the owner only
+fn prepare_store() -> Result<PathBuf, String> {
+    let dir = store_dir();
+    std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::set_permissions(&dir, Permissions::from_mode(0o700)))
+        .map_err(|e| format!("Не удалось подготовить хранилище карантина {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+pub fn load() -> Result<Vec<QuarantineItem>, String> {
+    storage::load(INDEX_FILE)
+}
+
+fn save(items: &[QuarantineItem]) -> Result<(), String> {
+    storage::save(INDEX_FILE, &items)
+}
+
+// XOR with the key is its own inverse, the same copy encodes and decodes. Returns the SHA-256
+// of the plain content: of the input when encoding, of the output when decoding.
+fn copy_xored(from: &mut File, to: &mut File, encode: bool) -> std::io::Result<String> {
+    let mut buffer = vec![0; 64 * 1024];
+    let mut hasher = Sha256::new();
+    let mut position = 0;
+    loop {
+        let read = from.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        if encode {
+            hasher.update(&buffer[..read]);
+        }
+        for byte in &mut buffer[..read] {
+            *byte ^= KEY[position % KEY.len()];
+            position += 1;
+        }
+        if !encode {
+            hasher.update(&buffer[..read]);
+        }
+        to.write_all(&buffer[..read])?;
+    }
+    to.sync_all()?;
+    Ok(evidence::hex(&hasher.finalize()))
+}
+
+// Only the owner may write new files into the store or restored files before their mode is set
+fn create_private(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)
+}
+
+fn current_user() -> String {
+    // /proc/self belongs to the effective user
+    let uid = std::fs::metadata("/proc/self").map_or(u32::MAX, |meta| meta.uid());
+    evidence::account_names("/etc/passwd")
+        .remove(&uid)
+        .unwrap_or_else(|| uid.to_string())
+}
+
+// Opened before every action: nothing is done that could not be recorded
+fn open_audit() -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(storage::path(AUDIT_FILE))
+        .map_err(|e| format!("Журнал аудита карантина недоступен, действие отменено: {}", e))
+}
+
+// Tab separated: time, user, action, path, SHA-256, result. An action done but not recorded
+// is reported as an error.
+fn audit<T>(log: &mut File, action: &str, path: &str, sha256: &str, result: Result<T, String>) -> Result<T, String> {
+    let outcome = match &result {
+        Ok(_) => "успешно".to_string(),
+        Err(e) => format!("ошибка: {}", e),
+    };
+    let line = [timestamp(), current_user(), action.to_string(), path.to_string(), sha256.to_string(), outcome]
+        .map(|field| field.replace(['\t', '\n'], " "))
+        .join("\t");
+    match writeln!(log, "{}", line) {
+        Ok(()) => result,
+        Err(e) if result.is_ok() => Err(format!("{}: выполнено, но не записано в журнал аудита: {}", path, e)),
+        Err(_) => result,
+    }
+}
+
+pub fn audit_log() -> Vec<AuditEntry> {
+    std::fs::read_to_string(storage::data_dir().join(AUDIT_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 6 {
+                return None;
+            }
+            Some(AuditEntry {
+                time: fields[0].to_string(),
+                user: fields[1].to_string(),
+                action: fields[2].to_string(),
+                path: fields[3].to_string(),
+                sha256: fields[4].to_string(),
+                result: fields[5].to_string(),
+            })
+        })
+        .collect()
+}
+
+fn store(path: &str, expected_sha256: Option<&str>, reason: &str) -> Result<QuarantineItem, String> {
+    // The checks and the copy use one handle, so the path cannot be swapped for a link in between
+    let mut file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::ELOOP) => format!("{} является символической ссылкой", path),
+            _ => format!("{}: {}", path, e),
+        })?;
+    let metadata = file.metadata().map_err(|e| format!("{}: {}", path, e))?;
+    if !metadata.is_file() {
+        return Err(format!("{} не является обычным файлом", path));
+    }
+    let dir = prepare_store()?;
+    let id = format!("{:x}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default());
+    let mut item = QuarantineItem {
+        id,
+        original_path: path.to_string(),
+        sha256: String::new(),
+        size: metadata.len(),
+        mode: metadata.mode() & 0o7777,
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        mtime: metadata.mtime(),
+        quarantined_at: timestamp(),
+        reason: reason.to_string(),
+    };
+
+    let stored = dir.join(format!("{}.bin", item.id));
+    let copied = create_private(&stored)
+        .and_then(|mut to| copy_xored(&mut file, &mut to, true))
+        .map_err(|e| format!("Не удалось переместить {} в карантин: {}", path, e));
+    item.sha256 = match copied {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = std::fs::remove_file(&stored);
+            return Err(e);
+        }
+    };
+    // A file changed since the scan is not the one that was detected
+    if expected_sha256.is_some_and(|expected| expected != item.sha256) {
+        let _ = std::fs::remove_file(&stored);
+        return Err(format!("Файл {} изменился после сканирования (хеш не совпадает), просканируйте его снова", path));
+    }
+
+    // The original is removed only once the copy is complete
+    let moved = std::fs::set_permissions(&stored, Permissions::from_mode(0o400)).and_then(|_| std::fs::remove_file(path));
+    if let Err(e) = moved {
+        let _ = std::fs::remove_file(&stored);
+        return Err(format!("Не удалось переместить {} в карантин: {}", path, e));
+    }
+    Ok(item)
+}
+
+// Move a file into quarantine, `reason` says what detected it. With `expected_sha256`, the hash
+// recorded by the scan, a file whose content has changed since is refused.
+pub fn quarantine(path: &str, expected_sha256: Option<&str>, reason: &str) -> Result<QuarantineItem, String> {
+    let mut log = open_audit()?;
+    let result = load().and_then(|mut items| {
+        let item = store(path, expected_sha256, reason)?;
+        items.push(item.clone());
+        // A file missing from the index could never be restored, so it goes back
+        if let Err(e) = save(&items) {
+            if write_back(&item).is_ok() {
+                let _ = std::fs::remove_file(item.stored_path());
+            }
+            return Err(e);
+        }
+        Ok(item)
+    });
+    let sha256 = result.as_ref().map(|item| item.sha256.clone()).unwrap_or_default();
+    audit(&mut log, "Помещен в карантин", path, &sha256, result)
+}
+
+// Write the file back to its original path. The stored copy is kept, it is removed by the caller
+// once the index no longer lists the item.
+fn write_back(item: &QuarantineItem) -> Result<Vec<String>, String> {
+    let target = Path::new(&item.original_path);
+    if target.exists() {
+        return Err(format!("Файл {} уже существует", item.original_path));
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Не удалось создать {}: {}", parent.display(), e))?;
+    }
+
+    let restored = File::open(item.stored_path()).and_then(|mut from| copy_xored(&mut from, &mut create_private(target)?, false));
+    match restored {
+        Ok(hash) if hash == item.sha256 => {}
+        Ok(_) => {
+            let _ = std::fs::remove_file(target);
+            return Err("Содержимое в карантине повреждено: хеш не совпадает".to_string());
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(target);
+            return Err(format!("Не удалось восстановить {}: {}", item.original_path, e));
+        }
+    }
+
+    // Times, owner and mode are restored where permitted, the content is back either way.
+    // The mode goes last: it may forbid writing and chown clears the setuid bits.
+    let mut warnings = Vec::new();
+    let mtime = UNIX_EPOCH + Duration::from_secs(item.mtime.max(0) as u64);
+    if let Err(e) = File::options().write(true).open(target).and_then(|file| file.set_modified(mtime)) {
+        warnings.push(format!("время изменения не восстановлено: {}", e));
+    }
+    if let Err(e) = std::os::unix::fs::chown(target, Some(item.uid), Some(item.gid)) {
+        warnings.push(format!("владелец не восстановлен: {}", e));
+    }
+    if let Err(e) = std::fs::set_permissions(target, Permissions::from_mode(item.mode)) {
+        warnings.push(format!("права не восстановлены: {}", e));
+    }
+    Ok(warnings)
+}
+
+fn take(items: &mut Vec<QuarantineItem>, id: &str) -> Result<QuarantineItem, String> {
+    let index = items
+        .iter()
+        .position(|item| item.id == id)
+        .ok_or_else(|| format!("Объект карантина {} не найден", id))?;
+    Ok(items.remove(index))
+}
+
+// Put the file back at its original path, returns warnings about attributes not restored
+pub fn restore(id: &str) -> Result<Vec<String>, String> {
+    let mut log = open_audit()?;
+    let mut items = load()?;
+    let item = take(&mut items, id)?;
+    // The stored copy goes only after the index is saved, until then the item stays restorable
+    let result = write_back(&item).and_then(|warnings| match save(&items) {
+        Ok(()) => {
+            let _ = std::fs::remove_file(item.stored_path());
+            Ok(warnings)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&item.original_path);
+            Err(e)
+        }
+    });
+    audit(&mut log, "Восстановлен", &item.original_path, &item.sha256, result)
+}
+
+pub fn delete(id: &str) -> Result<(), String> {
+    let mut log = open_audit()?;
+    let mut items = load()?;
+    let item = take(&mut items, id)?;
+    let stored = item.stored_path();
+    // Saved first: an index entry without its stored copy could not be restored
+    let result = save(&items).and_then(|_| {
+        std::fs::remove_file(&stored)
+            .or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+            .map_err(|e| format!("Не удалось удалить {}: {}", stored.display(), e))
+    });
+    audit(&mut log, "Удален безвозвратно", &item.original_path, &item.sha256, result)
+}
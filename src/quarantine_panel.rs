@@ -0,0 +1,294 @@
+// Quarantine window: files detected by YARA, the quarantined items and the audit log
+use crate::activity::ActivityLog;
+use crate::findings::{Engine, Finding};
+use crate::jobs::JobManager;
+use crate::pool::format_bytes;
+use crate::quarantine::{self, AuditEntry, QuarantineItem};
+use crate::scan::ScanMonitor;
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Newest audit entries shown in the window
+const AUDIT_SHOWN: usize = 200;
+const JOB_GROUP: &str = "Карантин";
+
+// File with YARA matches that can be put in quarantine
+#[derive(Clone)]
+struct Detected {
+    path: String,
+    rules: Vec<String>,
+    // Hash recorded by the scan, None for archives: the evidence describes the matched member
+    sha256: Option<String>,
+}
+
+pub struct QuarantinePanel {
+    items: Vec<QuarantineItem>,
+    audit: Vec<AuditEntry>,
+    // Item waiting for the confirmation of its permanent deletion
+    confirm_delete: Option<String>,
+    // "Поместить все в карантин" waits for a confirmation
+    confirm_all: bool,
+    // Whether a detected path is still a regular file, checked once until the next reload
+    present: HashMap<String, bool>,
+    // Summary of the last finished quarantine or restore job, the lists are reloaded then
+    finished: Arc<Mutex<Option<String>>>,
+    message: String,
+}
+
+impl Default for QuarantinePanel {
+    fn default() -> Self {
+        let mut panel = Self {
+            items: Vec::new(),
+            audit: Vec::new(),
+            confirm_delete: None,
+            confirm_all: false,
+            present: HashMap::new(),
+            finished: Arc::new(Mutex::new(None)),
+            message: String::new(),
+        };
+        panel.reload();
+        panel
+    }
+}
+
+// Detected files still in place with the rules that matched each of them. A match inside an
+// archive puts the whole archive in quarantine.
+fn detected_files(findings: &[Finding], present: &mut HashMap<String, bool>) -> Vec<Detected> {
+    let mut files: Vec<Detected> = Vec::new();
+    for f in findings.iter().filter(|f| f.engine == Engine::Yara && f.process.is_none()) {
+        let file = f.archive.as_ref().map_or(&f.target, |member| &member.archive);
+        match files.iter_mut().find(|detected| &detected.path == file) {
+            Some(detected) => {
+                if !detected.rules.contains(&f.rule_id) {
+                    detected.rules.push(f.rule_id.clone());
+                }
+            }
+            None => files.push(Detected {
+                path: file.clone(),
+                rules: vec![f.rule_id.clone()],
+                sha256: f
+                    .file
+                    .as_ref()
+                    .filter(|evidence| f.archive.is_none() && evidence.error.is_none())
+                    .map(|evidence| evidence.sha256.clone()),
+            }),
+        }
+    }
+    files.retain(|detected| *present.entry(detected.path.clone()).or_insert_with(|| Path::new(&detected.path).is_file()));
+    files
+}
+
+impl QuarantinePanel {
+    fn reload(&mut self) {
+        match quarantine::load() {
+            Ok(items) => self.items = items,
+            Err(e) => self.message = e,
+        }
+        self.audit = quarantine::audit_log();
+        self.present.clear();
+    }
+
+    // Files are copied and hashed in a background job
+    fn start_quarantine(&mut self, jobs: &JobManager, files: Vec<Detected>, activity: &ActivityLog) {
+        let finished = Arc::clone(&self.finished);
+        let activity = activity.clone();
+        let title = match files.as_slice() {
+            [file] => file.path.clone(),
+            files => format!("файлов: {}", files.len()),
+        };
+        jobs.spawn(JOB_GROUP, &format!("В карантин: {}", title), move |job| {
+            let mut moved = 0;
+            let mut errors = Vec::new();
+            for (done, file) in files.iter().enumerate() {
+                job.progress(done as f32 / files.len() as f32);
+                match quarantine::quarantine(&file.path, file.sha256.as_deref(), &format!("YARA: {}", file.rules.join(", "))) {
+                    Ok(_) => {
+                        moved += 1;
+                        activity.push(format!("Файл {} помещен в карантин", file.path));
+                    }
+                    Err(e) => {
+                        activity.push(e.clone());
+                        errors.push(e);
+                    }
+                }
+            }
+            let message = match (files.len(), errors.first()) {
+                (1, Some(e)) => e.clone(),
+                (1, None) => format!("Файл {} помещен в карантин", files[0].path),
+                (count, _) => format!("В карантин помещено файлов: {} из {}", moved, count),
+            };
+            *finished.lock().unwrap() = Some(message.clone());
+            if errors.is_empty() {
+                Ok(message)
+            } else {
+                Err(format!("{}\n{}", message, errors.join("\n")))
+            }
+        });
+    }
+
+    fn start_restore(&mut self, jobs: &JobManager, id: String, path: String, activity: &ActivityLog) {
+        let finished = Arc::clone(&self.finished);
+        let activity = activity.clone();
+        jobs.spawn(JOB_GROUP, &format!("Восстановление: {}", path), move |_| {
+            let result = match quarantine::restore(&id) {
+                Ok(warnings) if warnings.is_empty() => Ok(format!("Файл {} восстановлен", path)),
+                Ok(warnings) => Ok(format!("Файл {} восстановлен, {}", path, warnings.join(", "))),
+                Err(e) => Err(e),
+            };
+            let message = result.clone().unwrap_or_else(|e| e);
+            activity.push(message.clone());
+            *finished.lock().unwrap() = Some(message);
+            result
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, jobs: &JobManager, findings: &[Finding], activity: &ActivityLog) {
+        let finished = self.finished.lock().unwrap().take();
+        if let Some(message) = finished {
+            self.message = message;
+            self.reload();
+        }
+        let busy = jobs.active_in(JOB_GROUP) > 0;
+
+        ui.horizontal(|ui| {
+            if ui.button("Обновить").clicked() {
+                self.reload();
+            }
+            if busy {
+                ui.spinner();
+            }
+            if !self.message.is_empty() {
+                ui.label(&self.message);
+            }
+        });
+
+        let detected = detected_files(findings, &mut self.present);
+        egui::CollapsingHeader::new(format!("Обнаруженные файлы ({})", detected.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                if detected.is_empty() {
+                    ui.label("Нет файлов с совпадениями YARA");
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    if self.confirm_all {
+                        ui.label(format!("Поместить в карантин все файлы ({})?", detected.len()));
+                        if ui.add_enabled(!busy, egui::Button::new("Да, поместить все")).clicked() {
+                            self.confirm_all = false;
+                            self.start_quarantine(jobs, detected.clone(), activity);
+                        }
+                        if ui.button("Отмена").clicked() {
+                            self.confirm_all = false;
+                        }
+                    } else if ui.add_enabled(!busy, egui::Button::new("Поместить все в карантин")).clicked() {
+                        self.confirm_all = true;
+                    }
+                });
+                egui::Grid::new("quarantine_detected").striped(true).num_columns(3).show(ui, |ui| {
+                    ui.strong("Файл");
+                    ui.strong("Правила");
+                    ui.label("");
+                    ui.end_row();
+                    for file in &detected {
+                        ui.monospace(&file.path);
+                        ui.label(file.rules.join(", "));
+                        if ui.add_enabled(!busy, egui::Button::new("В карантин")).clicked() {
+                            self.start_quarantine(jobs, vec![file.clone()], activity);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+        egui::CollapsingHeader::new(format!("В карантине ({})", self.items.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                if self.items.is_empty() {
+                    ui.label("Карантин пуст");
+                    return;
+                }
+                let mut action = None;
+                egui::Grid::new("quarantine_items").striped(true).num_columns(5).show(ui, |ui| {
+                    ui.strong("Исходный путь");
+                    ui.strong("Причина");
+                    ui.strong("Помещен");
+                    ui.strong("Размер");
+                    ui.label("");
+                    ui.end_row();
+                    for item in &self.items {
+                        ui.monospace(&item.original_path).on_hover_text(format!(
+                            "SHA-256: {}\nПрава: {:o}, владелец: {}:{}",
+                            item.sha256, item.mode, item.uid, item.gid
+                        ));
+                        ui.label(&item.reason);
+                        ui.label(&item.quarantined_at);
+                        ui.label(format_bytes(item.size));
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(!busy, egui::Button::new("Восстановить")).clicked() {
+                                action = Some((item.id.clone(), false));
+                            }
+                            if self.confirm_delete.as_ref() == Some(&item.id) {
+                                if ui.button("Точно удалить").clicked() {
+                                    action = Some((item.id.clone(), true));
+                                }
+                                if ui.button("Отмена").clicked() {
+                                    self.confirm_delete = None;
+                                }
+                            } else if ui.button("Удалить навсегда").clicked() {
+                                self.confirm_delete = Some(item.id.clone());
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+
+                if let Some((id, delete)) = action {
+                    let path = self
+                        .items
+                        .iter()
+                        .find(|item| item.id == id)
+                        .map(|item| item.original_path.clone())
+                        .unwrap_or_default();
+                    self.confirm_delete = None;
+                    if delete {
+                        self.message = match quarantine::delete(&id) {
+                            Ok(()) => format!("Файл {} удален из карантина безвозвратно", path),
+                            Err(e) => e,
+                        };
+                        activity.push(self.message.clone());
+                        self.reload();
+                    } else {
+                        self.start_restore(jobs, id, path, activity);
+                    }
+                }
+            });
+
+        egui::CollapsingHeader::new(format!("Журнал аудита ({})", self.audit.len())).show(ui, |ui| {
+            egui::Grid::new("quarantine_audit").striped(true).num_columns(5).show(ui, |ui| {
+                ui.strong("Время");
+                ui.strong("Пользователь");
+                ui.strong("Действие");
+                ui.strong("Файл");
+                ui.strong("Результат");
+                ui.end_row();
+                for entry in self.audit.iter().rev().take(AUDIT_SHOWN) {
+                    ui.monospace(&entry.time);
+                    ui.label(&entry.user);
+                    ui.label(&entry.action);
+                    let path = ui.monospace(&entry.path);
+                    if !entry.sha256.is_empty() {
+                        path.on_hover_text(format!("SHA-256: {}", entry.sha256));
+                    }
+                    if entry.result.starts_with("ошибка") {
+                        ui.colored_label(egui::Color32::RED, &entry.result);
+                    } else {
+                        ui.label(&entry.result);
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,978 @@
+// Consolidated HTML/PDF report covering both OpenSCAP and YARA results
+use crate::evidence::{EvidenceError, FileEvidence};
+use crate::exceptions;
+use crate::findings::{Engine, Finding, ScanRun, TargetError, TargetResult};
+use crate::processes::ProcessInfo;
+use crate::rpm;
+use eframe::egui;
+use printpdf::{
+    image_crate, path::PaintMode, Color, Image, ImageTransform, IndirectFontRef, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Rect, Rgb,
+};
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReportLanguage {
+    Russian,
+    English,
+}
+
+impl ReportLanguage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReportLanguage::Russian => "Русский",
+            ReportLanguage::English => "English",
+        }
+    }
+
+    fn labels(&self) -> &'static Labels {
+        match self {
+            ReportLanguage::Russian => &RU,
+            ReportLanguage::English => &EN,
+        }
+    }
+}
+
+// Report appearance chosen in the GUI
+#[derive(Clone)]
+pub struct ReportSettings {
+    pub language: ReportLanguage,
+    pub organisation: String,
+    pub logo_path: Option<String>,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self {
+            language: ReportLanguage::Russian,
+            organisation: String::new(),
+            logo_path: None,
+        }
+    }
+}
+
+// Everything rendered into a report
+pub struct ReportData<'a> {
+    pub host: HostInfo,
+    pub runs: &'a [ScanRun],
+    pub findings: &'a [Finding],
+    // YARA matches left out as false positives
+    pub suppressed: usize,
+    pub generated_at: String,
+}
+
+pub struct HostInfo {
+    pub hostname: String,
+    pub os: String,
+    pub kernel: String,
+}
+
+impl HostInfo {
+    pub fn collect() -> Self {
+        let read = |path: &str| {
+            std::fs::read_to_string(path)
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let os = read("/etc/os-release")
+            .lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|name| name.trim_matches('"').to_string())
+            .unwrap_or_default();
+
+        Self {
+            hostname: read("/proc/sys/kernel/hostname"),
+            os,
+            kernel: read("/proc/sys/kernel/osrelease"),
+        }
+    }
+}
+
+struct Labels {
+    title: &'static str,
+    generated: &'static str,
+    host_section: &'static str,
+    hostname: &'static str,
+    os: &'static str,
+    kernel: &'static str,
+    scans_section: &'static str,
+    engine: &'static str,
+    started: &'static str,
+    command: &'static str,
+    content: &'static str,
+    no_scans: &'static str,
+    targets_section: &'static str,
+    target: &'static str,
+    files: &'static str,
+    matches: &'static str,
+    status: &'static str,
+    scanned: &'static str,
+    target_not_found: &'static str,
+    summary_section: &'static str,
+    oval_by_severity: &'static str,
+    yara_by_rule: &'static str,
+    suppressed: &'static str,
+    oval_section: &'static str,
+    exceptions_section: &'static str,
+    risk_owner: &'static str,
+    reason: &'static str,
+    expires: &'static str,
+    yara_section: &'static str,
+    definition: &'static str,
+    name: &'static str,
+    severity: &'static str,
+    cvss: &'static str,
+    packages: &'static str,
+    upgrade_commands: &'static str,
+    references: &'static str,
+    remediation: &'static str,
+    rule: &'static str,
+    file: &'static str,
+    tags: &'static str,
+    user: &'static str,
+    command_line: &'static str,
+    size: &'static str,
+    bytes: &'static str,
+    owner: &'static str,
+    permissions: &'static str,
+    modified: &'static str,
+    changed: &'static str,
+    package: &'static str,
+    package_verified: &'static str,
+    package_modified: &'static str,
+    package_unverified: &'static str,
+    not_packaged: &'static str,
+    package_unknown: &'static str,
+    file_metadata_error: &'static str,
+    file_unreadable: &'static str,
+    no_findings: &'static str,
+    unknown_severity: &'static str,
+    oval_hint: &'static str,
+    yara_hint: &'static str,
+}
+
+static RU: Labels = Labels {
+    title: "Отчет о проверке безопасности",
+    generated: "Сформирован",
+    host_section: "Сведения об узле",
+    hostname: "Имя узла",
+    os: "Операционная система",
+    kernel: "Ядро",
+    scans_section: "Выполненные проверки",
+    engine: "Инструмент",
+    started: "Запуск",
+    command: "Команда и опции",
+    content: "Версия содержимого",
+    no_scans: "Проверки не выполнялись",
+    targets_section: "Цели сканирования YARA",
+    target: "Цель",
+    files: "Файлов",
+    matches: "Совпадений",
+    status: "Состояние",
+    scanned: "просканирована",
+    target_not_found: "путь не найден",
+    summary_section: "Сводка",
+    oval_by_severity: "Уязвимости OVAL по критичности",
+    yara_by_rule: "Срабатывания YARA по правилам",
+    suppressed: "Срабатываний YARA скрыто как ложные",
+    oval_section: "Уязвимости OVAL",
+    exceptions_section: "Принятые риски (исключения)",
+    risk_owner: "Ответственный",
+    reason: "Причина",
+    expires: "Действует до",
+    yara_section: "Срабатывания YARA",
+    definition: "Определение",
+    name: "Название",
+    severity: "Критичность",
+    cvss: "CVSS",
+    packages: "Пакеты (установлено → исправлено)",
+    upgrade_commands: "Команды обновления",
+    references: "Ссылки",
+    remediation: "Рекомендация",
+    rule: "Правило",
+    file: "Файл",
+    tags: "Теги",
+    user: "Пользователь",
+    command_line: "Командная строка",
+    size: "Размер",
+    bytes: "байт",
+    owner: "Владелец",
+    permissions: "Права",
+    modified: "Изменен",
+    changed: "Изменение inode",
+    package: "Пакет",
+    package_verified: "совпадает с базой RPM",
+    package_modified: "ИЗМЕНЕН относительно базы RPM",
+    package_unverified: "контрольная сумма не проверена",
+    not_packaged: "Не принадлежит пакету RPM",
+    package_unknown: "Принадлежность пакету RPM не проверена",
+    file_metadata_error: "Не удалось получить сведения о файле",
+    file_unreadable: "Не удалось прочитать файл",
+    no_findings: "Нет находок",
+    unknown_severity: "Не указана",
+    oval_hint: "Установите обновления безопасности для затронутых пакетов (dnf upgrade)",
+    yara_hint: "Проверьте файл; при подтверждении угрозы изолируйте или удалите его",
+};
+
+static EN: Labels = Labels {
+    title: "Security scan report",
+    generated: "Generated",
+    host_section: "Host information",
+    hostname: "Hostname",
+    os: "Operating system",
+    kernel: "Kernel",
+    scans_section: "Scans performed",
+    engine: "Engine",
+    started: "Started",
+    command: "Command and options",
+    content: "Content version",
+    no_scans: "No scans were run",
+    targets_section: "YARA scan targets",
+    target: "Target",
+    files: "Files",
+    matches: "Matches",
+    status: "Status",
+    scanned: "scanned",
+    target_not_found: "path not found",
+    summary_section: "Summary",
+    oval_by_severity: "OVAL vulnerabilities by severity",
+    yara_by_rule: "YARA matches by rule",
+    suppressed: "YARA matches suppressed as false positives",
+    oval_section: "OVAL vulnerabilities",
+    exceptions_section: "Accepted risks (exceptions)",
+    risk_owner: "Risk owner",
+    reason: "Reason",
+    expires: "Valid until",
+    yara_section: "YARA matches",
+    definition: "Definition",
+    name: "Title",
+    severity: "Severity",
+    cvss: "CVSS",
+    packages: "Packages (installed → fixed)",
+    upgrade_commands: "Upgrade commands",
+    references: "References",
+    remediation: "Remediation",
+    rule: "Rule",
+    file: "File",
+    tags: "Tags",
+    user: "User",
+    command_line: "Command line",
+    size: "Size",
+    bytes: "bytes",
+    owner: "Owner",
+    permissions: "Permissions",
+    modified: "Modified",
+    changed: "Inode changed",
+    package: "Package",
+    package_verified: "matches the RPM database",
+    package_modified: "MODIFIED since installation",
+    package_unverified: "checksum not verified",
+    not_packaged: "Not owned by an RPM package",
+    package_unknown: "RPM package ownership not checked",
+    file_metadata_error: "Could not get the file metadata",
+    file_unreadable: "Could not read the file",
+    no_findings: "No findings",
+    unknown_severity: "Unspecified",
+    oval_hint: "Install the security updates for the affected packages (dnf upgrade)",
+    yara_hint: "Review the file; if the threat is confirmed, isolate or remove it",
+};
+
+// Number of YARA rules shown in the summary chart
+const TOP_RULES: usize = 10;
+
+// Chart bars: label and number of findings
+type Counts<'a> = Vec<(&'a str, usize)>;
+
+// Counts of untriaged OVAL findings per severity and of YARA matches per rule
+fn summary<'a>(findings: &'a [Finding], labels: &'a Labels) -> (Counts<'a>, Counts<'a>) {
+    let mut by_severity: Counts = Vec::new();
+    let mut by_rule: Counts = Vec::new();
+
+    for f in findings.iter().filter(|f| f.exception.is_none()) {
+        let (key, counts) = match f.engine {
+            Engine::Oval if f.severity.is_empty() => (labels.unknown_severity, &mut by_severity),
+            Engine::Oval => (f.severity.as_str(), &mut by_severity),
+            Engine::Yara => (f.rule_id.as_str(), &mut by_rule),
+        };
+        match counts.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((key, 1)),
+        }
+    }
+
+    by_severity.sort_by_key(|(severity, _)| severity_rank(severity));
+    by_rule.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    by_rule.truncate(TOP_RULES);
+
+    (by_severity, by_rule)
+}
+
+pub fn severity_rank(severity: &str) -> u8 {
+    match severity.to_lowercase().as_str() {
+        "critical" => 0,
+        "high" | "important" => 1,
+        "medium" | "moderate" => 2,
+        "low" => 3,
+        _ => 4,
+    }
+}
+
+pub fn severity_color(severity: &str) -> (u8, u8, u8) {
+    match severity_rank(severity) {
+        0 => (142, 36, 170),
+        1 => (211, 47, 47),
+        2 => (245, 124, 0),
+        3 => (251, 192, 45),
+        _ => (120, 144, 156),
+    }
+}
+
+// Untriaged OVAL findings, the most risky first. Accepted risks are listed apart.
+pub fn oval_by_risk(findings: &[Finding]) -> Vec<&Finding> {
+    let mut oval: Vec<&Finding> = findings
+        .iter()
+        .filter(|f| f.engine == Engine::Oval && f.exception.is_none())
+        .collect();
+    oval.sort_by(|a, b| b.risk().total_cmp(&a.risk()));
+    oval
+}
+
+pub fn package_versions(finding: &Finding) -> String {
+    finding
+        .packages
+        .iter()
+        .map(|p| format!("{} {} → {}", p.name, p.installed_version, p.fixed_version))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn remediation_hint(finding: &Finding, labels: &Labels) -> String {
+    match finding.engine {
+        Engine::Oval => {
+            // The commands of the upgrade section, whatever the risk decision on this finding
+            let mut open = finding.clone();
+            open.exception = None;
+            let commands = rpm::upgrade_commands(std::slice::from_ref(&open));
+            // Packages neither rpm nor OVAL found on the host are installed in the fixed version
+            let missing: Vec<&str> = finding
+                .packages
+                .iter()
+                .filter(|p| p.installed.is_empty() && p.installed_version.is_empty())
+                .map(|p| p.name.as_str())
+                .collect();
+            if !commands.is_empty() {
+                commands.join("; ")
+            } else if !missing.is_empty() {
+                format!("dnf install {}", missing.join(" "))
+            } else {
+                labels.oval_hint.to_string()
+            }
+        }
+        Engine::Yara => labels.yara_hint.to_string(),
+    }
+}
+
+fn process_context(process: &ProcessInfo, labels: &Labels) -> String {
+    format!("{}: {}, {}: {}", labels.user, process.user, labels.command_line, process.command_line)
+}
+
+// Hashes, metadata and package check of a matched file, one entry per line
+fn file_context(file: &FileEvidence, labels: &Labels) -> Vec<String> {
+    if let Some(error) = file.error {
+        let label = match error {
+            EvidenceError::Metadata => labels.file_metadata_error,
+            EvidenceError::Unreadable => labels.file_unreadable,
+        };
+        return vec![format!("{}: {}", label, file.error_detail)];
+    }
+    let package = match &file.package {
+        None if !file.rpm_checked => labels.package_unknown.to_string(),
+        None => labels.not_packaged.to_string(),
+        Some(check) => {
+            let status = match check.verified {
+                Some(true) => labels.package_verified,
+                Some(false) => labels.package_modified,
+                None => labels.package_unverified,
+            };
+            format!("{}: {}, {}", labels.package, check.package, status)
+        }
+    };
+    vec![
+        format!("SHA-256: {}", file.sha256),
+        format!("MD5: {}", file.md5),
+        format!(
+            "{}: {} {}, {}: {}:{}, {}: {}",
+            labels.size, file.size, labels.bytes, labels.owner, file.owner, file.group, labels.permissions, file.permissions
+        ),
+        format!("{}: {}, {}: {}", labels.modified, file.modified, labels.changed, file.changed),
+        package,
+    ]
+}
+
+fn target_status(target: &TargetResult, labels: &Labels) -> &'static str {
+    match target.error {
+        None => labels.scanned,
+        Some(TargetError::NotFound) => labels.target_not_found,
+    }
+}
+
+fn cvss_text(finding: &Finding) -> String {
+    finding
+        .cvss_score()
+        .map(|score| format!("{:.1}", score))
+        .unwrap_or_default()
+}
+
+fn reference_links(finding: &Finding) -> String {
+    finding
+        .references
+        .iter()
+        .map(|reference| match finding.reference_url(reference) {
+            Some(url) => format!("<a href=\"{}\">{}</a>", html_escape(&url), html_escape(reference)),
+            None => html_escape(reference),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Logo formats both reports can show, PNG and JPEG, by the file extension
+fn logo_mime(path: &str) -> Option<&'static str> {
+    match Path::new(path).extension()?.to_str()?.to_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        _ => None,
+    }
+}
+
+// Inline the logo as a data URI so the report stays a single file
+fn logo_data_uri(path: &str) -> Option<String> {
+    use base64::Engine as _;
+
+    let mime = logo_mime(path)?;
+    let bytes = std::fs::read(path).ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+// Horizontal bar chart as inline SVG
+fn svg_bar_chart(bars: &[(&str, usize)], color_of: impl Fn(&str) -> (u8, u8, u8)) -> String {
+    let max = bars.iter().map(|(_, count)| *count).max().unwrap_or(1).max(1);
+    let row_height = 24;
+    let mut svg = format!(
+        "<svg width=\"520\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\" font-size=\"12\">",
+        bars.len() * row_height
+    );
+    for (i, (label, count)) in bars.iter().enumerate() {
+        let y = i * row_height;
+        let width = 300 * count / max;
+        let (r, g, b) = color_of(label);
+        svg.push_str(&format!(
+            "<text x=\"0\" y=\"{}\">{}</text><rect x=\"170\" y=\"{}\" width=\"{}\" height=\"16\" fill=\"rgb({},{},{})\"/><text x=\"{}\" y=\"{}\">{}</text>",
+            y + 14,
+            html_escape(&truncate(label, 26)),
+            y + 2,
+            width.max(1),
+            r,
+            g,
+            b,
+            175 + width,
+            y + 14,
+            count
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        let mut cut: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn render_html(data: &ReportData, settings: &ReportSettings) -> String {
+    let l = settings.language.labels();
+    let mut html = String::new();
+
+    html.push_str(&format!(
+        "<!DOCTYPE html>\n<html lang=\"{}\"><head><meta charset=\"utf-8\"><title>{}</title><style>\
+         body{{font-family:sans-serif;margin:2em;color:#222}}\
+         header{{display:flex;align-items:center;gap:1.5em;border-bottom:2px solid #b71c1c;padding-bottom:1em}}\
+         header img{{max-height:64px}}\
+         table{{border-collapse:collapse;width:100%;margin:0.5em 0 1.5em}}\
+         th,td{{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}}\
+         th{{background:#f3f3f3}}\
+         code{{white-space:pre-wrap;word-break:break-all}}\
+         .sev{{color:#fff;padding:1px 6px;border-radius:3px}}\
+         </style></head><body>\n",
+        match settings.language {
+            ReportLanguage::Russian => "ru",
+            ReportLanguage::English => "en",
+        },
+        l.title
+    ));
+
+    // Header with the organisation logo
+    html.push_str("<header>");
+    if let Some(uri) = settings.logo_path.as_deref().and_then(logo_data_uri) {
+        html.push_str(&format!("<img src=\"{}\" alt=\"logo\">", uri));
+    }
+    html.push_str(&format!(
+        "<div><h1>{}</h1><div>{}</div><div>{}: {}</div></div></header>\n",
+        l.title,
+        html_escape(&settings.organisation),
+        l.generated,
+        html_escape(&data.generated_at)
+    ));
+
+    html.push_str(&format!(
+        "<h2>{}</h2><table><tr><th>{}</th><td>{}</td></tr><tr><th>{}</th><td>{}</td></tr><tr><th>{}</th><td>{}</td></tr></table>\n",
+        l.host_section,
+        l.hostname,
+        html_escape(&data.host.hostname),
+        l.os,
+        html_escape(&data.host.os),
+        l.kernel,
+        html_escape(&data.host.kernel)
+    ));
+
+    html.push_str(&format!("<h2>{}</h2>", l.scans_section));
+    if data.runs.is_empty() {
+        html.push_str(&format!("<p>{}</p>", l.no_scans));
+    } else {
+        html.push_str(&format!(
+            "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            l.engine, l.started, l.command, l.content
+        ));
+        for run in data.runs {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td><code>{}</code></td><td>{}</td></tr>",
+                run.engine.tool_name(),
+                html_escape(&run.started_at),
+                html_escape(&run.command),
+                html_escape(&run.content_version)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    let targets: Vec<&TargetResult> = data.runs.iter().flat_map(|run| &run.targets).collect();
+    if !targets.is_empty() {
+        html.push_str(&format!(
+            "<h3>{}</h3><table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            l.targets_section, l.target, l.files, l.matches, l.status
+        ));
+        for target in targets {
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                html_escape(&target.path),
+                target.files,
+                target.matches,
+                html_escape(target_status(target, l))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    let (by_severity, by_rule) = summary(data.findings, l);
+    html.push_str(&format!("<h2>{}</h2>", l.summary_section));
+    html.push_str(&format!("<h3>{}</h3>", l.oval_by_severity));
+    if by_severity.is_empty() {
+        html.push_str(&format!("<p>{}</p>", l.no_findings));
+    } else {
+        html.push_str(&svg_bar_chart(&by_severity, severity_color));
+    }
+    html.push_str(&format!("<h3>{}</h3>", l.yara_by_rule));
+    if by_rule.is_empty() {
+        html.push_str(&format!("<p>{}</p>", l.no_findings));
+    } else {
+        html.push_str(&svg_bar_chart(&by_rule, |_| (183, 28, 28)));
+    }
+    if data.suppressed > 0 {
+        html.push_str(&format!("<p>{}: {}</p>", l.suppressed, data.suppressed));
+    }
+
+    let oval = oval_by_risk(data.findings);
+    html.push_str(&format!("<h2>{}</h2>", l.oval_section));
+    if oval.is_empty() {
+        html.push_str(&format!("<p>{}</p>", l.no_findings));
+    } else {
+        html.push_str(&format!(
+            "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            l.definition, l.name, l.severity, l.cvss, l.packages, l.references, l.remediation
+        ));
+        for f in oval {
+            let (r, g, b) = severity_color(&f.severity);
+            let severity = if f.severity.is_empty() { l.unknown_severity } else { f.severity.as_str() };
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td><span class=\"sev\" style=\"background:rgb({},{},{})\">{}</span></td><td>{}</td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>",
+                html_escape(&f.rule_id),
+                html_escape(&f.title),
+                r,
+                g,
+                b,
+                html_escape(severity),
+                cvss_text(f),
+                html_escape(&package_versions(f)),
+                reference_links(f),
+                html_escape(&remediation_hint(f, l))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    let commands = rpm::upgrade_commands(data.findings);
+    if !commands.is_empty() {
+        html.push_str(&format!(
+            "<h3>{}</h3><pre>{}</pre>\n",
+            l.upgrade_commands,
+            html_escape(&commands.join("\n"))
+        ));
+    }
+
+    let accepted = exceptions::accepted(data.findings);
+    if !accepted.is_empty() {
+        html.push_str(&format!(
+            "<h2>{}</h2><table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            l.exceptions_section, l.definition, l.name, l.severity, l.risk_owner, l.reason, l.expires
+        ));
+        for (f, exception) in accepted.iter().filter_map(|f| Some((f, f.exception.as_ref()?))) {
+            let severity = if f.severity.is_empty() { l.unknown_severity } else { f.severity.as_str() };
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                html_escape(&f.rule_id),
+                html_escape(&f.title),
+                html_escape(severity),
+                html_escape(&exception.owner),
+                html_escape(&exception.reason),
+                html_escape(&exception.expires)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    let yara: Vec<&Finding> = data.findings.iter().filter(|f| f.engine == Engine::Yara).collect();
+    html.push_str(&format!("<h2>{}</h2>", l.yara_section));
+    if yara.is_empty() {
+        html.push_str(&format!("<p>{}</p>", l.no_findings));
+    } else {
+        html.push_str(&format!(
+            "<table><tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>",
+            l.rule, l.file, l.tags, l.remediation
+        ));
+        for f in yara {
+            let context = f
+                .process
+                .as_ref()
+                .map(|process| format!("<br><small>{}</small>", html_escape(&process_context(process, l))))
+                .or_else(|| {
+                    let lines: Vec<String> = file_context(f.file.as_ref()?, l).iter().map(|line| html_escape(line)).collect();
+                    Some(format!("<br><small>{}</small>", lines.join("<br>")))
+                })
+                .unwrap_or_default();
+            html.push_str(&format!(
+                "<tr><td><code>{}</code></td><td><code>{}</code>{}</td><td>{}</td><td>{}</td></tr>",
+                html_escape(&f.rule_id),
+                html_escape(&f.target),
+                context,
+                html_escape(&f.tags.join(", ")),
+                html_escape(&remediation_hint(f, l))
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+pub fn write_html(data: &ReportData, settings: &ReportSettings, path: &Path) -> std::io::Result<()> {
+    std::fs::write(path, render_html(data, settings))
+}
+
+// A4 page geometry in millimetres
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 15.0;
+
+// Writes text top-down and starts new pages as needed
+struct PdfWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: IndirectFontRef,
+    y: f32,
+}
+
+impl PdfWriter {
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+    }
+
+    // Approximate the average glyph width to wrap long lines
+    fn wrap(text: &str, size: f32, width: f32) -> Vec<String> {
+        let chars_per_line = ((width / (size * 0.5 * 0.3528)) as usize).max(10);
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let chars: Vec<char> = paragraph.chars().collect();
+            if chars.is_empty() {
+                lines.push(String::new());
+            }
+            for chunk in chars.chunks(chars_per_line) {
+                lines.push(chunk.iter().collect());
+            }
+        }
+        lines
+    }
+
+    fn text(&mut self, text: &str, size: f32, indent: f32) {
+        let line_height = size * 0.3528 * 1.4;
+        for line in Self::wrap(text, size, PAGE_WIDTH - 2.0 * MARGIN - indent) {
+            self.ensure_space(line_height);
+            self.y -= line_height;
+            self.layer.set_fill_color(Color::Rgb(Rgb::new(0.13, 0.13, 0.13, None)));
+            self.layer.use_text(line, size, Mm(MARGIN + indent), Mm(self.y), &self.font);
+        }
+    }
+
+    fn heading(&mut self, text: &str, size: f32) {
+        self.ensure_space(size * 0.3528 * 3.0);
+        self.y -= size * 0.3528 * 0.8;
+        self.text(text, size, 0.0);
+    }
+
+    fn bar_chart(&mut self, bars: &[(&str, usize)], color_of: impl Fn(&str) -> (u8, u8, u8)) {
+        let max = bars.iter().map(|(_, count)| *count).max().unwrap_or(1).max(1);
+        for (label, count) in bars {
+            self.ensure_space(7.0);
+            self.y -= 7.0;
+            self.layer.set_fill_color(Color::Rgb(Rgb::new(0.13, 0.13, 0.13, None)));
+            self.layer
+                .use_text(truncate(label, 26), 9.0, Mm(MARGIN), Mm(self.y + 1.0), &self.font);
+            let width = 100.0 * *count as f32 / max as f32;
+            let (r, g, b) = color_of(label);
+            self.layer.set_fill_color(Color::Rgb(Rgb::new(
+                r as f32 / 255.0,
+                g as f32 / 255.0,
+                b as f32 / 255.0,
+                None,
+            )));
+            self.layer.add_rect(
+                Rect::new(Mm(MARGIN + 55.0), Mm(self.y), Mm(MARGIN + 55.0 + width.max(0.5)), Mm(self.y + 4.5))
+                    .with_mode(PaintMode::Fill),
+            );
+            self.layer.set_fill_color(Color::Rgb(Rgb::new(0.13, 0.13, 0.13, None)));
+            self.layer.use_text(
+                count.to_string(),
+                9.0,
+                Mm(MARGIN + 57.0 + width),
+                Mm(self.y + 1.0),
+                &self.font,
+            );
+        }
+    }
+
+    fn logo(&mut self, path: &str) -> Result<(), String> {
+        let image = open_logo(path)?;
+        // Scale the logo to a fixed height of 20 mm
+        let dpi = image.height() as f32 * 25.4 / 20.0;
+        let rgb = image_crate::DynamicImage::ImageRgb8(image.to_rgb8());
+        self.y -= 20.0;
+        Image::from_dynamic_image(&rgb).add_to_layer(
+            self.layer.clone(),
+            ImageTransform {
+                translate_x: Some(Mm(MARGIN)),
+                translate_y: Some(Mm(self.y)),
+                dpi: Some(dpi),
+                ..Default::default()
+            },
+        );
+        Ok(())
+    }
+}
+
+fn open_logo(path: &str) -> Result<image_crate::DynamicImage, String> {
+    logo_mime(path).ok_or_else(|| format!("Логотип должен быть в формате PNG или JPEG: {}", path))?;
+    image_crate::open(path).map_err(|e| format!("Не удалось прочитать логотип {}: {}", path, e))
+}
+
+// The logo has to be a PNG or JPEG image the PDF writer can decode
+pub fn check_logo(path: &str) -> Result<(), String> {
+    open_logo(path).map(|_| ())
+}
+
+// The GUI font covers Cyrillic, reuse it so the PDF needs no system fonts
+fn report_font() -> Vec<u8> {
+    egui::FontDefinitions::default()
+        .font_data
+        .get("Ubuntu-Light")
+        .map(|data| data.font.to_vec())
+        .unwrap_or_default()
+}
+
+pub fn write_pdf(data: &ReportData, settings: &ReportSettings, path: &Path) -> Result<(), String> {
+    let l = settings.language.labels();
+
+    let (doc, page, layer) = PdfDocument::new(l.title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let font = doc
+        .add_external_font(Cursor::new(report_font()))
+        .map_err(|e| e.to_string())?;
+    let layer = doc.get_page(page).get_layer(layer);
+
+    let mut pdf = PdfWriter {
+        doc,
+        layer,
+        font,
+        y: PAGE_HEIGHT - MARGIN,
+    };
+
+    if let Some(logo) = &settings.logo_path {
+        pdf.logo(logo)?;
+    }
+    pdf.heading(l.title, 18.0);
+    if !settings.organisation.is_empty() {
+        pdf.text(&settings.organisation, 11.0, 0.0);
+    }
+    pdf.text(&format!("{}: {}", l.generated, data.generated_at), 9.0, 0.0);
+
+    pdf.heading(l.host_section, 14.0);
+    pdf.text(&format!("{}: {}", l.hostname, data.host.hostname), 10.0, 0.0);
+    pdf.text(&format!("{}: {}", l.os, data.host.os), 10.0, 0.0);
+    pdf.text(&format!("{}: {}", l.kernel, data.host.kernel), 10.0, 0.0);
+
+    pdf.heading(l.scans_section, 14.0);
+    if data.runs.is_empty() {
+        pdf.text(l.no_scans, 10.0, 0.0);
+    }
+    for run in data.runs {
+        pdf.text(&format!("{} — {}: {}", run.engine.tool_name(), l.started, run.started_at), 10.0, 0.0);
+        pdf.text(&format!("{}: {}", l.command, run.command), 9.0, 5.0);
+        pdf.text(&format!("{}: {}", l.content, run.content_version), 9.0, 5.0);
+    }
+
+    let targets: Vec<&TargetResult> = data.runs.iter().flat_map(|run| &run.targets).collect();
+    if !targets.is_empty() {
+        pdf.text(l.targets_section, 11.0, 0.0);
+        for target in targets {
+            pdf.text(
+                &format!(
+                    "{} — {}: {}, {}: {}, {}: {}",
+                    target.path,
+                    l.files,
+                    target.files,
+                    l.matches,
+                    target.matches,
+                    l.status,
+                    target_status(target, l)
+                ),
+                9.0,
+                5.0,
+            );
+        }
+    }
+
+    let (by_severity, by_rule) = summary(data.findings, l);
+    pdf.heading(l.summary_section, 14.0);
+    pdf.text(l.oval_by_severity, 11.0, 0.0);
+    if by_severity.is_empty() {
+        pdf.text(l.no_findings, 9.0, 5.0);
+    }
+    pdf.bar_chart(&by_severity, severity_color);
+    pdf.text(l.yara_by_rule, 11.0, 0.0);
+    if by_rule.is_empty() {
+        pdf.text(l.no_findings, 9.0, 5.0);
+    }
+    pdf.bar_chart(&by_rule, |_| (183, 28, 28));
+    if data.suppressed > 0 {
+        pdf.text(&format!("{}: {}", l.suppressed, data.suppressed), 9.0, 5.0);
+    }
+
+    let oval = oval_by_risk(data.findings);
+    pdf.heading(l.oval_section, 14.0);
+    if oval.is_empty() {
+        pdf.text(l.no_findings, 10.0, 0.0);
+    }
+    for f in oval {
+        let severity = if f.severity.is_empty() { l.unknown_severity } else { f.severity.as_str() };
+        match f.cvss_score() {
+            Some(score) => pdf.text(&format!("{} [{}, CVSS {:.1}]", f.rule_id, severity, score), 10.0, 0.0),
+            None => pdf.text(&format!("{} [{}]", f.rule_id, severity), 10.0, 0.0),
+        }
+        pdf.text(&f.title, 9.0, 5.0);
+        if !f.packages.is_empty() {
+            pdf.text(&format!("{}: {}", l.packages, package_versions(f)), 9.0, 5.0);
+        }
+        if !f.references.is_empty() {
+            pdf.text(&format!("{}: {}", l.references, f.references.join(", ")), 9.0, 5.0);
+        }
+        pdf.text(&format!("{}: {}", l.remediation, remediation_hint(f, l)), 9.0, 5.0);
+    }
+
+    let commands = rpm::upgrade_commands(data.findings);
+    if !commands.is_empty() {
+        pdf.text(l.upgrade_commands, 11.0, 0.0);
+        for command in &commands {
+            pdf.text(command, 9.0, 5.0);
+        }
+    }
+
+    let accepted = exceptions::accepted(data.findings);
+    if !accepted.is_empty() {
+        pdf.heading(l.exceptions_section, 14.0);
+    }
+    for (f, exception) in accepted.iter().filter_map(|f| Some((f, f.exception.as_ref()?))) {
+        let severity = if f.severity.is_empty() { l.unknown_severity } else { f.severity.as_str() };
+        pdf.text(&format!("{} [{}]", f.rule_id, severity), 10.0, 0.0);
+        pdf.text(&f.title, 9.0, 5.0);
+        pdf.text(&format!("{}: {}, {}: {}", l.risk_owner, exception.owner, l.expires, exception.expires), 9.0, 5.0);
+        pdf.text(&format!("{}: {}", l.reason, exception.reason), 9.0, 5.0);
+    }
+
+    let yara: Vec<&Finding> = data.findings.iter().filter(|f| f.engine == Engine::Yara).collect();
+    pdf.heading(l.yara_section, 14.0);
+    if yara.is_empty() {
+        pdf.text(l.no_findings, 10.0, 0.0);
+    }
+    for f in yara {
+        pdf.text(&format!("{}: {}", l.rule, f.rule_id), 10.0, 0.0);
+        pdf.text(&format!("{}: {}", l.file, f.target), 9.0, 5.0);
+        if let Some(process) = &f.process {
+            pdf.text(&process_context(process, l), 9.0, 5.0);
+        }
+        for line in f.file.iter().flat_map(|file| file_context(file, l)) {
+            pdf.text(&line, 9.0, 5.0);
+        }
+        if !f.tags.is_empty() {
+            pdf.text(&format!("{}: {}", l.tags, f.tags.join(", ")), 9.0, 5.0);
+        }
+        pdf.text(&format!("{}: {}", l.remediation, remediation_hint(f, l)), 9.0, 5.0);
+    }
+
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    pdf.doc
+        .save(&mut BufWriter::new(file))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,138 @@
+// Correlation of OVAL findings with the local RPM database
+use crate::findings::{Engine, Finding, InstalledRpm};
+use std::collections::HashMap;
+use std::process::Command;
+
+// Tab separated fields, one line per installed package
+const QUERY_FORMAT: &str = "%{NAME}\\t%{EPOCHNUM}\\t%{VERSION}\\t%{RELEASE}\\t%{ARCH}\\t%{VENDOR}\\t%{INSTALLTIME}\\n";
+
+// Query the RPM database for the given package names
+pub fn query_installed(names: &[String]) -> Result<HashMap<String, Vec<InstalledRpm>>, String> {
+    let mut installed: HashMap<String, Vec<InstalledRpm>> = HashMap::new();
+    if names.is_empty() {
+        return Ok(installed);
+    }
+
+    // Exits with an error if any package is missing, so only the launch failure counts
+    let output = Command::new("rpm")
+        .arg("-q")
+        .arg("--qf")
+        .arg(QUERY_FORMAT)
+        .args(names)
+        .output()
+        .map_err(|e| format!("Ошибка при запуске rpm: {}", e))?;
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        // "package X is not installed" lines have no tabs
+        if fields.len() != 7 {
+            continue;
+        }
+        let install_date = fields[6]
+            .parse()
+            .ok()
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map(|date| date.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+
+        installed.entry(fields[0].to_string()).or_default().push(InstalledRpm {
+            nevra: format!("{}-{}:{}-{}.{}", fields[0], fields[1], fields[2], fields[3], fields[4]),
+            evr: format!("{}:{}-{}", fields[1], fields[2], fields[3]),
+            vendor: fields[5].to_string(),
+            install_date,
+        });
+    }
+
+    Ok(installed)
+}
+
+// Attach the installed packages to every affected package of the OVAL findings
+pub fn correlate(findings: &mut [Finding]) -> Result<usize, String> {
+    let mut names: Vec<String> = findings
+        .iter()
+        .filter(|f| f.engine == Engine::Oval)
+        .flat_map(|f| f.packages.iter().map(|p| p.name.clone()))
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let installed = query_installed(&names)?;
+
+    for package in findings
+        .iter_mut()
+        .filter(|f| f.engine == Engine::Oval)
+        .flat_map(|f| f.packages.iter_mut())
+    {
+        package.installed = installed.get(&package.name).cloned().unwrap_or_default();
+        if package.installed_version.is_empty() {
+            let versions: Vec<&str> = package.installed.iter().map(|rpm| rpm.evr.as_str()).collect();
+            package.installed_version = versions.join(", ");
+        }
+    }
+
+    Ok(installed.len())
+}
+
+// Installed packages that have to be upgraded, sorted and without duplicates
+pub fn packages_to_upgrade(findings: &[Finding]) -> Vec<String> {
+    let mut names: Vec<String> = findings
+        .iter()
+        // Packages of accepted risks are left as they are
+        .filter(|f| f.engine == Engine::Oval && f.exception.is_none())
+        .flat_map(|f| f.packages.iter())
+        .filter(|p| !p.installed.is_empty())
+        .map(|p| p.name.clone())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// `dnf upgrade` commands, split to keep each command line readable
+pub fn upgrade_commands(findings: &[Finding]) -> Vec<String> {
+    packages_to_upgrade(findings)
+        .chunks(20)
+        .map(|chunk| format!("dnf upgrade {}", chunk.join(" ")))
+        .collect()
+}
+
+// Package that installed a file and the file digest recorded in the RPM database
+pub struct PackagedFile {
+    // name-version-release.arch
+    pub package: String,
+    pub digest: String,
+    // RPM digest algorithm: 1 is MD5, 8 is SHA-256
+    pub algorithm: u32,
+}
+
+// Every file of the owning package: path, digest, digest algorithm and package
+const FILE_QUERY_FORMAT: &str = "[%{FILENAMES}\\t%{FILEDIGESTS}\\t%{=FILEDIGESTALGO}\\t%{=NAME}-%{=VERSION}-%{=RELEASE}.%{=ARCH}\\n]";
+
+// None when the file does not belong to any installed package
+pub fn file_owner(path: &str) -> Result<Option<PackagedFile>, String> {
+    // Exits with an error for files not owned by a package, so only the launch failure counts
+    let output = Command::new("rpm")
+        .arg("-qf")
+        .arg("--qf")
+        .arg(FILE_QUERY_FORMAT)
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Ошибка при запуске rpm: {}", e))?;
+
+    // The database lists the real path, the match may have been found through a symlink
+    let real = std::fs::canonicalize(path)
+        .map(|real| real.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    Ok(String::from_utf8_lossy(&output.stdout).lines().find_map(|line| {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 4 || (fields[0] != path && fields[0] != real) {
+            return None;
+        }
+        Some(PackagedFile {
+            package: fields[3].to_string(),
+            digest: fields[1].to_lowercase(),
+            // Old packages without the tag print "(none)" and use MD5
+            algorithm: fields[2].parse().unwrap_or(1),
+        })
+    }))
+}
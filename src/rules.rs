@@ -0,0 +1,340 @@
+// YARA rule sources selected for a scan: files with their namespaces
+use crate::scan::{self, ExternalVariable, ScanMonitor};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use crate::yara_syntax;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Numbers the files yarac writes before they are moved into place
+static OUTPUTS: AtomicU64 = AtomicU64::new(0);
+
+pub const RULE_EXTENSIONS: [&str; 2] = ["yar", "yara"];
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleFile {
+    pub path: String,
+    pub namespace: String,
+    pub enabled: bool,
+}
+
+impl RuleFile {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            namespace: default_namespace(path),
+            enabled: true,
+        }
+    }
+
+    // Command line form "namespace:path"; yara's own namespace is "default"
+    pub fn argument(&self) -> String {
+        let namespace = if self.namespace.is_empty() { "default" } else { &self.namespace };
+        format!("{}:{}", namespace, self.path)
+    }
+}
+
+// Namespace from the file name: "apt/cobalt-strike.yar" -> "cobalt_strike"
+pub fn default_namespace(path: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let namespace: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if namespace.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", namespace)
+    } else {
+        namespace
+    }
+}
+
+pub fn is_rule_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| RULE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+// Rule files found under a directory and its subdirectories, sorted by path
+pub fn collect_dir(dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir).map_err(|e| format!("Не удалось прочитать папку {}: {}", dir.display(), e))?;
+        for entry in entries.flatten() {
+            // Symlinked directories are not followed to avoid loops
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if is_rule_file(&path) {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// Append files missing from the list, keeping namespaces unique. Returns the number added.
+pub fn add_files(rules: &mut Vec<RuleFile>, paths: impl IntoIterator<Item = String>) -> usize {
+    let mut added = 0;
+    for path in paths {
+        if rules.iter().any(|rule| rule.path == path) {
+            continue;
+        }
+        let mut rule = RuleFile::new(&path);
+        let base = rule.namespace.clone();
+        let mut suffix = 2;
+        while rules.iter().any(|other| other.namespace == rule.namespace) {
+            rule.namespace = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        rules.push(rule);
+        added += 1;
+    }
+    added
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Error => "Ошибка",
+            Severity::Warning => "Предупреждение",
+        }
+    }
+}
+
+// One compiler message with its location
+#[derive(Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    pub line: usize,
+    pub rule: Option<String>,
+    pub message: String,
+}
+
+// "FILE(LINE" -> (FILE, LINE)
+fn split_location(location: &str) -> Option<(String, usize)> {
+    let (file, line) = location.rsplit_once('(')?;
+    Some((file.to_string(), line.parse().ok()?))
+}
+
+// yarac reports `FILE(LINE): error: MESSAGE` or `error: rule "NAME" in FILE(LINE): MESSAGE`,
+// warnings use the same forms
+pub fn parse_diagnostic(line: &str) -> Option<Diagnostic> {
+    for (prefix, severity) in [("error: rule \"", Severity::Error), ("warning: rule \"", Severity::Warning)] {
+        if let Some(rest) = line.strip_prefix(prefix) {
+            let (rule, rest) = rest.split_once("\" in ")?;
+            let (location, message) = rest.split_once("): ")?;
+            let (file, line) = split_location(location)?;
+            return Some(Diagnostic {
+                severity,
+                file,
+                line,
+                rule: Some(rule.to_string()),
+                message: message.to_string(),
+            });
+        }
+    }
+
+    let (location, rest) = line.split_once("): ")?;
+    let (file, line) = split_location(location)?;
+    let (severity, message) = if let Some(message) = rest.strip_prefix("error: ") {
+        (Severity::Error, message)
+    } else if let Some(message) = rest.strip_prefix("warning: ") {
+        (Severity::Warning, message)
+    } else {
+        return None;
+    };
+    Some(Diagnostic {
+        severity,
+        file,
+        line,
+        rule: None,
+        message: message.to_string(),
+    })
+}
+
+// Result of compiling the enabled rule files
+#[derive(Clone)]
+pub struct Compilation {
+    pub diagnostics: Vec<Diagnostic>,
+    // yarac messages without a location
+    pub other: Vec<String>,
+    // Compiled rules in the cache, None when compilation failed or the rules were only checked
+    pub compiled: Option<PathBuf>,
+}
+
+impl Compilation {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == severity).count()
+    }
+}
+
+// Every part of the key is prefixed with its length so different parts never hash alike
+fn hash_part(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+// Paths and contents of the files a rule file includes, and of the files they include in turn.
+// Relative paths are resolved against the including file, as yarac does. A missing file is
+// hashed as missing, yarac reports it when the rules are compiled.
+fn hash_includes(hasher: &mut Sha256, file: &Path, text: &[u8], seen: &mut HashSet<PathBuf>) {
+    for include in yara_syntax::parse(&String::from_utf8_lossy(text)).includes {
+        let path = file.parent().unwrap_or(Path::new("")).join(include);
+        hash_part(hasher, path.to_string_lossy().as_bytes());
+        // Include cycles are an error of yarac, the files are hashed once
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        match std::fs::read(&path) {
+            Ok(included) => {
+                hash_part(hasher, &included);
+                hash_includes(hasher, &path, &included, seen);
+            }
+            Err(_) => hash_part(hasher, b"missing"),
+        }
+    }
+}
+
+// The cache is keyed by namespaces, paths and contents of the rule files and the files they
+// include, and by the external variables
+fn cache_key(rules: &[RuleFile], defines: &[ExternalVariable]) -> Result<String, String> {
+    let mut hasher = Sha256::new();
+    let mut seen = HashSet::new();
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let text = std::fs::read(&rule.path).map_err(|e| format!("Не удалось прочитать файл правил {}: {}", rule.path, e))?;
+        hash_part(&mut hasher, rule.argument().as_bytes());
+        hash_part(&mut hasher, &text);
+        hash_includes(&mut hasher, Path::new(&rule.path), &text, &mut seen);
+    }
+    for define in defines {
+        hash_part(&mut hasher, define.name.as_bytes());
+        hash_part(&mut hasher, define.value.as_bytes());
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn cache_path(key: &str) -> PathBuf {
+    storage::path(&format!("rules-cache/{}.yarc", key))
+}
+
+// Compiled rules for this rule set if it was validated since the files last changed
+pub fn cached(rules: &[RuleFile], defines: &[ExternalVariable]) -> Option<PathBuf> {
+    let path = cache_path(&cache_key(rules, defines).ok()?);
+    path.exists().then_some(path)
+}
+
+// Remove all compiled rules, returns the number of removed files
+pub fn clear_cache() -> Result<usize, String> {
+    let entries = match std::fs::read_dir(storage::data_dir().join("rules-cache")) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Не удалось прочитать кэш правил: {}", e)),
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        if std::fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+// Compile the enabled files with yarac into the cache
+pub fn compile(rules: &[RuleFile], defines: &[ExternalVariable], monitor: &dyn ScanMonitor) -> Result<Compilation, String> {
+    let enabled: Vec<&RuleFile> = rules.iter().filter(|rule| rule.enabled).collect();
+    if enabled.is_empty() {
+        return Err("Не выбрано ни одного файла правил".to_string());
+    }
+
+    let output = cache_path(&cache_key(rules, defines)?);
+    let sources: Vec<String> = enabled.iter().map(|rule| rule.argument()).collect();
+    run_yarac(&sources, defines, output, monitor)
+}
+
+// Syntax check of a single file, e.g. from the rule editor. The compiled rules are not kept.
+pub fn check(path: &Path, defines: &[ExternalVariable], monitor: &dyn ScanMonitor) -> Result<Compilation, String> {
+    let output = storage::path(&format!(
+        "editor/check-{}-{}.yarc",
+        std::process::id(),
+        OUTPUTS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut compilation = run_yarac(&[path.to_string_lossy().to_string()], defines, output, monitor)?;
+    if let Some(compiled) = compilation.compiled.take() {
+        let _ = std::fs::remove_file(compiled);
+    }
+    Ok(compilation)
+}
+
+fn run_yarac(
+    sources: &[String],
+    defines: &[ExternalVariable],
+    output: PathBuf,
+    monitor: &dyn ScanMonitor,
+) -> Result<Compilation, String> {
+    let mut command = Command::new("yarac");
+    for define in defines {
+        command.arg(format!("--define={}={}", define.name, define.value));
+    }
+    // yarac writes next to the output, which only appears once complete, so a scan running
+    // at the same time never reads half-written rules from the cache
+    let partial = output.with_extension(format!("{}-{}.tmp", std::process::id(), OUTPUTS.fetch_add(1, Ordering::Relaxed)));
+    command.args(sources).arg(&partial);
+    let result = scan::run_command(&mut command, monitor).map_err(|e| scan::launch_error("yarac", e));
+    let compiled = match &result {
+        Ok((_, _, status)) if status.success() && partial.exists() => std::fs::rename(&partial, &output).is_ok(),
+        _ => false,
+    };
+    let _ = std::fs::remove_file(&partial);
+    let (_, stderr, _) = result?;
+
+    let mut diagnostics = Vec::new();
+    let mut other = Vec::new();
+    for line in stderr.lines().filter(|line| !line.trim().is_empty()) {
+        match parse_diagnostic(line) {
+            Some(diagnostic) => diagnostics.push(diagnostic),
+            None => other.push(line.to_string()),
+        }
+    }
+
+    let compiled = compiled.then_some(output);
+    Ok(Compilation {
+        diagnostics,
+        other,
+        compiled,
+    })
+}
+
+// Numbered source lines around a line of a rule file
+pub fn excerpt(file: &str, line: usize, context: usize) -> Vec<(usize, String)> {
+    let Ok(text) = std::fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    let first = line.saturating_sub(context).max(1);
+    text.lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.to_string()))
+        .skip(first - 1)
+        .take(line + context + 1 - first)
+        .collect()
+}
@@ -0,0 +1,909 @@
+// OpenSCAP and YARA runs shared by the GUI and the scheduler
+use crate::cve;
+use crate::evidence;
+use crate::findings::{self, Engine, Finding, ScanRun, TargetResult};
+use crate::pool::{self, ProgressTracker, ScanProgress};
+use crate::rpm;
+use crate::rules::{self, Compilation, RuleFile};
+use crate::storage;
+use crate::targets;
+use crate::walker::{self, ScanFilters};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::ops::Range;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use ureq::Agent;
+
+// oscap module evaluating the content
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OscapModule {
+    #[default]
+    Oval,
+    Xccdf,
+}
+
+impl OscapModule {
+    pub fn command(&self) -> &'static str {
+        match self {
+            OscapModule::Oval => "oval",
+            OscapModule::Xccdf => "xccdf",
+        }
+    }
+}
+
+// Level of `oscap --verbose`
+#[derive(Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    #[default]
+    Off,
+    Error,
+    Warning,
+    Info,
+    Devel,
+}
+
+impl Verbosity {
+    pub const ALL: [Verbosity; 5] = [
+        Verbosity::Off,
+        Verbosity::Error,
+        Verbosity::Warning,
+        Verbosity::Info,
+        Verbosity::Devel,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verbosity::Off => "Выключен",
+            Verbosity::Error => "ERROR",
+            Verbosity::Warning => "WARNING",
+            Verbosity::Info => "INFO",
+            Verbosity::Devel => "DEVEL",
+        }
+    }
+}
+
+// Options of `oscap oval eval` and `oscap xccdf eval`. Empty strings mean "not set".
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenScapOptions {
+    pub module: OscapModule,
+    pub verbosity: Verbosity,
+    pub verbose_log_file: String,
+    pub skip_valid: bool,
+    pub dont_send_results: bool,
+    pub datastream_id: String,
+    // OVAL only
+    pub definition_id: String,
+    pub variables: String,
+    pub directives: String,
+    // XCCDF only
+    pub profile: String,
+    pub rule_id: String,
+    pub oval_results: bool,
+    pub fetch_remote_resources: bool,
+    pub progress: bool,
+}
+
+impl OpenScapOptions {
+    // Check the options before oscap is started
+    pub fn validate(&self, xml_path: &str) -> Result<(), String> {
+        let file_exists = |path: &str, what: &str| {
+            if std::path::Path::new(path).is_file() {
+                Ok(())
+            } else {
+                Err(format!("{} не найден: {}", what, path))
+            }
+        };
+
+        file_exists(xml_path, "Файл содержимого SCAP")?;
+
+        if !self.verbose_log_file.is_empty() {
+            if self.verbosity == Verbosity::Off {
+                return Err("Журнал --verbose-log-file требует выбрать уровень --verbose".to_string());
+            }
+            let parent = std::path::Path::new(&self.verbose_log_file)
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty());
+            if parent.is_some_and(|parent| !parent.is_dir()) {
+                return Err(format!("Папка для журнала не существует: {}", self.verbose_log_file));
+            }
+        }
+
+        match self.module {
+            OscapModule::Oval => {
+                let valid_id = self.definition_id.is_empty() || {
+                    let parts: Vec<&str> = self.definition_id.split(':').collect();
+                    parts.len() == 4
+                        && parts[0] == "oval"
+                        && parts[2] == "def"
+                        && parts[3].parse::<u64>().is_ok()
+                };
+                if !valid_id {
+                    return Err(format!(
+                        "Неверный идентификатор определения: {} (ожидается oval:<пространство>:def:<номер>)",
+                        self.definition_id
+                    ));
+                }
+                if !self.variables.is_empty() {
+                    file_exists(&self.variables, "Файл переменных")?;
+                }
+                if !self.directives.is_empty() {
+                    file_exists(&self.directives, "Файл директив")?;
+                }
+                if !self.profile.is_empty() || !self.rule_id.is_empty() {
+                    return Err("Профиль и правило задаются только для xccdf eval".to_string());
+                }
+                if self.oval_results || self.fetch_remote_resources || self.progress {
+                    return Err(
+                        "--oval-results, --fetch-remote-resources и --progress поддерживаются только xccdf eval"
+                            .to_string(),
+                    );
+                }
+            }
+            OscapModule::Xccdf => {
+                if !self.definition_id.is_empty() || !self.variables.is_empty() || !self.directives.is_empty() {
+                    return Err("--id, --variables и --directives поддерживаются только oval eval".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Files named in the options may appear or vanish while they stay the same
+const RECHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+// Result of an options check shown in the interface, redone when the checked values change
+pub struct Validation<K> {
+    key: Option<K>,
+    checked: Instant,
+    result: Result<(), String>,
+}
+
+impl<K> Default for Validation<K> {
+    fn default() -> Self {
+        Self {
+            key: None,
+            checked: Instant::now(),
+            result: Ok(()),
+        }
+    }
+}
+
+impl<K: PartialEq> Validation<K> {
+    pub fn check(&mut self, key: K, validate: impl FnOnce(&K) -> Result<(), String>) -> &Result<(), String> {
+        if self.key.as_ref() != Some(&key) || self.checked.elapsed() > RECHECK_INTERVAL {
+            self.result = validate(&key);
+            self.key = Some(key);
+            self.checked = Instant::now();
+        }
+        &self.result
+    }
+}
+
+// Engine of YARA scans, shown in the interface
+#[cfg(feature = "embedded-yara")]
+pub const YARA_ENGINE: &str = "встроенный YARA-X (скомпилированные правила -C проверяются через yara)";
+#[cfg(not(feature = "embedded-yara"))]
+pub const YARA_ENGINE: &str = "yara из PATH";
+
+// Numbers the file lists of concurrent scans
+static SCAN_LISTS: AtomicU64 = AtomicU64::new(0);
+// Numbers the OpenSCAP results of concurrent scans
+static OSCAP_RUNS: AtomicU64 = AtomicU64::new(0);
+
+// yara accepts at most 32 scanning threads
+pub const YARA_MAX_THREADS: u32 = 32;
+// Upper limit of the scan worker pool
+pub const MAX_WORKERS: u32 = 64;
+
+// External variable passed with `-d name=value`
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalVariable {
+    pub name: String,
+    pub value: String,
+}
+
+// Options of the yara command line. Zero numbers leave yara's own defaults.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YaraOptions {
+    pub recursive: bool,
+    pub fast_scan: bool,
+    pub no_warnings: bool,
+    pub print_tags: bool,
+    pub print_strings: bool,
+    pub print_meta: bool,
+    pub print_namespace: bool,
+    pub no_follow_symlinks: bool,
+    // The rules file was produced by yarac
+    pub compiled_rules: bool,
+    pub threads: u32,
+    // Files are scanned by this many parallel yara processes or engine threads
+    pub workers: u32,
+    // Seconds per scanned file
+    pub timeout: u32,
+    // Stop scanning a file after this many matching rules
+    pub max_rules: u32,
+    // Skip files larger than this many bytes
+    pub max_file_size: u64,
+    pub defines: Vec<ExternalVariable>,
+    // Print only the rule with this name
+    pub identifier: String,
+    // Applied when a directory is scanned
+    pub filters: ScanFilters,
+    // Scan the files inside zip, tar, tar.gz, RPM and OCI image archives
+    pub scan_archives: bool,
+    // Unpacked data per scan, MiB
+    pub archive_limit_mb: u64,
+}
+
+impl Default for YaraOptions {
+    fn default() -> Self {
+        // The flags and threads keep yara's own defaults, as before the options were extended
+        Self {
+            recursive: false,
+            fast_scan: false,
+            no_warnings: false,
+            print_tags: false,
+            print_strings: false,
+            print_meta: false,
+            print_namespace: false,
+            no_follow_symlinks: false,
+            compiled_rules: false,
+            threads: 0,
+            workers: std::thread::available_parallelism()
+                .map(|n| n.get() as u32)
+                .unwrap_or(1)
+                .min(MAX_WORKERS),
+            timeout: 0,
+            max_rules: 0,
+            max_file_size: 0,
+            defines: Vec::new(),
+            identifier: String::new(),
+            filters: ScanFilters::default(),
+            scan_archives: false,
+            archive_limit_mb: 2048,
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl YaraOptions {
+    // Check the options before yara is started
+    pub fn validate(&self, rules: &[RuleFile], targets: &[String]) -> Result<(), String> {
+        self.validate_rules(rules)?;
+        // Missing targets are reported per target, the scan needs at least one existing
+        if targets.is_empty() {
+            return Err("Не выбраны цели сканирования".to_string());
+        }
+        if !targets.iter().any(|target| std::path::Path::new(target).exists()) {
+            return Err(format!("Цели сканирования не найдены: {}", targets.join(", ")));
+        }
+        Ok(())
+    }
+
+    // Rules and options alone, for scans of something else than paths
+    pub fn validate_rules(&self, rules: &[RuleFile]) -> Result<(), String> {
+        let enabled: Vec<&RuleFile> = rules.iter().filter(|rule| rule.enabled).collect();
+        if enabled.is_empty() {
+            return Err("Не выбрано ни одного файла правил".to_string());
+        }
+        if self.compiled_rules && enabled.len() > 1 {
+            return Err("Скомпилированные правила (-C) задаются одним файлом".to_string());
+        }
+
+        for rule in enabled {
+            let content = std::fs::read(&rule.path).map_err(|e| format!("Не удалось прочитать файл правил {}: {}", rule.path, e))?;
+            // Compiled rules start with the "YARA" signature
+            let compiled = content.starts_with(b"YARA");
+            if self.compiled_rules && !compiled {
+                return Err(format!("{} не является скомпилированным файлом правил (yarac)", rule.path));
+            }
+            if !self.compiled_rules && compiled {
+                return Err(format!("{} содержит скомпилированные правила, включите опцию -C", rule.path));
+            }
+            if !rule.namespace.is_empty() && !is_identifier(&rule.namespace) {
+                return Err(format!("Неверное пространство имен '{}' для {}", rule.namespace, rule.path));
+            }
+        }
+
+        if self.threads > YARA_MAX_THREADS {
+            return Err(format!("Число потоков не может превышать {}", YARA_MAX_THREADS));
+        }
+        if self.workers == 0 || self.workers > MAX_WORKERS {
+            return Err(format!("Число параллельных обработчиков должно быть от 1 до {}", MAX_WORKERS));
+        }
+        if !self.identifier.is_empty() && !is_identifier(&self.identifier) {
+            return Err(format!("Неверное имя правила: {}", self.identifier));
+        }
+        for define in &self.defines {
+            if !is_identifier(&define.name) {
+                return Err(format!("Неверное имя внешней переменной: '{}'", define.name));
+            }
+        }
+        if self.scan_archives && self.archive_limit_mb == 0 {
+            return Err("Укажите предел объема распаковки архивов".to_string());
+        }
+        self.filters.validate()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+// Hooks of a running scan: cancellation, pause, live output and progress
+pub trait ScanMonitor: Send + Sync {
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+    // Honoured by file scans between files
+    fn is_paused(&self) -> bool {
+        false
+    }
+    fn output(&self, _stream: OutputStream, _line: &str) {}
+    fn progress(&self, _fraction: f32) {}
+    // File-level progress of YARA scans
+    fn scan_progress(&self, _progress: &ScanProgress) {}
+}
+
+// Used where nobody watches the scan, e.g. by the scheduler service
+pub struct NoMonitor;
+
+impl ScanMonitor for NoMonitor {}
+
+pub const CANCELLED: &str = "Операция отменена пользователем.";
+
+// Collect a pipe while forwarding every line to the monitor
+fn read_lines(pipe: impl Read, stream: OutputStream, monitor: &dyn ScanMonitor) -> String {
+    let mut reader = BufReader::new(pipe);
+    let mut collected = String::new();
+    let mut line = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+        let text = String::from_utf8_lossy(&line);
+        monitor.output(stream, text.trim_end_matches('\n'));
+        collected.push_str(&text);
+        line.clear();
+    }
+    collected
+}
+
+// Run a command, killing it as soon as the scan is cancelled
+pub fn run_command(command: &mut Command, monitor: &dyn ScanMonitor) -> std::io::Result<(String, String, ExitStatus)> {
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    std::thread::scope(|scope| {
+        let stdout = scope.spawn(|| {
+            stdout
+                .map(|pipe| read_lines(pipe, OutputStream::Stdout, monitor))
+                .unwrap_or_default()
+        });
+        let stderr = scope.spawn(|| {
+            stderr
+                .map(|pipe| read_lines(pipe, OutputStream::Stderr, monitor))
+                .unwrap_or_default()
+        });
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if monitor.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, CANCELLED));
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        };
+
+        Ok((
+            stdout.join().unwrap_or_default(),
+            stderr.join().unwrap_or_default(),
+            status,
+        ))
+    })
+}
+
+// Cancellation is not reported as a failure to start the tool
+pub fn launch_error(tool: &str, error: std::io::Error) -> String {
+    if error.kind() == std::io::ErrorKind::Interrupted {
+        CANCELLED.to_string()
+    } else {
+        format!("Ошибка при запуске {}: {}", tool, error)
+    }
+}
+
+// Result of a finished scan. The tool output itself goes to the monitor.
+pub struct ScanOutcome {
+    // Processing notes shown to the user
+    pub notes: Vec<String>,
+    pub findings: Vec<Finding>,
+    pub run: ScanRun,
+    // False when part of the scan failed, so the findings may miss what was not scanned
+    pub complete: bool,
+}
+
+pub fn timestamp() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// Download the vulnerability description (OVAL content)
+pub fn download_content(url: &str, monitor: &dyn ScanMonitor) -> Result<String, String> {
+    let agent = Agent::new();
+
+    let response = agent
+        .get(url)
+        .call()
+        .map_err(|e| format!("Ошибка загрузки файла: {}", e))?;
+
+    let total: Option<usize> = response.header("Content-Length").and_then(|len| len.parse().ok());
+    let mut reader = response.into_reader();
+    let mut content = Vec::new();
+    let mut chunk = [0u8; 64 * 1024];
+    loop {
+        if monitor.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        let read = reader
+            .read(&mut chunk)
+            .map_err(|e| format!("Ошибка чтения содержимого ответа: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        content.extend_from_slice(&chunk[..read]);
+        if let Some(total) = total.filter(|total| *total > 0) {
+            monitor.progress(content.len() as f32 / total as f32);
+        }
+    }
+
+    String::from_utf8(content).map_err(|e| format!("Ошибка чтения содержимого ответа: {}", e))
+}
+
+pub fn oscap_args(options: &OpenScapOptions, xml_path: &str, results_path: &str, report_path: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut option = |name: &str, value: &str| {
+        args.push(name.to_string());
+        if !value.is_empty() {
+            args.push(value.to_string());
+        }
+    };
+
+    // General options go before the module
+    if options.verbosity != Verbosity::Off {
+        option("--verbose", options.verbosity.label());
+        if !options.verbose_log_file.is_empty() {
+            option("--verbose-log-file", &options.verbose_log_file);
+        }
+    }
+
+    option(options.module.command(), "");
+    option("eval", "");
+    option("--results", results_path);
+    option("--report", report_path);
+
+    // Add selected options
+    if options.skip_valid {
+        option("--skip-valid", "");
+    }
+    if options.dont_send_results {
+        option("--dont-send-results", "");
+    }
+    if !options.datastream_id.is_empty() {
+        option("--datastream-id", &options.datastream_id);
+    }
+
+    match options.module {
+        OscapModule::Oval => {
+            if !options.definition_id.is_empty() {
+                option("--id", &options.definition_id);
+            }
+            if !options.variables.is_empty() {
+                option("--variables", &options.variables);
+            }
+            if !options.directives.is_empty() {
+                option("--directives", &options.directives);
+            }
+        }
+        OscapModule::Xccdf => {
+            if !options.profile.is_empty() {
+                option("--profile", &options.profile);
+            }
+            if !options.rule_id.is_empty() {
+                option("--rule", &options.rule_id);
+            }
+            if options.oval_results {
+                option("--oval-results", "");
+            }
+            if options.fetch_remote_resources {
+                option("--fetch-remote-resources", "");
+            }
+            if options.progress {
+                option("--progress", "");
+            }
+        }
+    }
+
+    args.push(xml_path.to_string());
+    args
+}
+
+// Results and report files of one OpenSCAP run, named after the job and unique to the run
+pub fn oscap_files(name: &str) -> (String, String) {
+    let stem = format!(
+        "results/{}-{}-{}-{}",
+        name,
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        std::process::id(),
+        OSCAP_RUNS.fetch_add(1, Ordering::Relaxed)
+    );
+    (
+        storage::path(&format!("{}-results.xml", stem)).to_string_lossy().to_string(),
+        storage::path(&format!("{}-report.html", stem)).to_string_lossy().to_string(),
+    )
+}
+
+// The files are created afresh, so an earlier run or a file planted at the path is never read back
+fn create_output(path: &str) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(format!("Не удалось удалить {}: {}", path, e)),
+        _ => {}
+    }
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .map(|_| ())
+        .map_err(|e| format!("Не удалось создать {}: {}", path, e))
+}
+
+// Evaluate SCAP content, then parse and enrich the results
+pub fn run_oscap(
+    options: &OpenScapOptions,
+    xml_path: &str,
+    results_path: &str,
+    report_path: &str,
+    cve_data_path: Option<&str>,
+    monitor: &dyn ScanMonitor,
+) -> Result<ScanOutcome, String> {
+    options.validate(xml_path)?;
+    let args = oscap_args(options, xml_path, results_path, report_path);
+
+    create_output(results_path)?;
+    create_output(report_path)?;
+    // A failed run leaves no files that could be taken for its results
+    let remove_outputs = || {
+        let _ = std::fs::remove_file(results_path);
+        let _ = std::fs::remove_file(report_path);
+    };
+
+    let started_at = timestamp();
+    let (_, stderr, status) = run_command(Command::new("oscap").args(&args), monitor).map_err(|e| {
+        remove_outputs();
+        launch_error("OpenSCAP", e)
+    })?;
+    // 2 means some rules failed or definitions are true, the results are complete
+    if !matches!(status.code(), Some(0) | Some(2)) {
+        remove_outputs();
+        return Err(format!(
+            "oscap завершился с ошибкой (код {}): {}",
+            status.code().map_or("?".to_string(), |code| code.to_string()),
+            stderr.trim()
+        ));
+    }
+
+    let mut notes = Vec::new();
+
+    let mut run = ScanRun {
+        engine: Engine::Oval,
+        started_at,
+        command: format!("oscap {}", args.join(" ")),
+        content_version: String::new(),
+        targets: Vec::new(),
+    };
+
+    // Parse the results for export
+    let parsed = std::fs::read_to_string(results_path)
+        .map_err(|e| e.to_string())
+        .and_then(|xml| match options.module {
+            OscapModule::Oval => findings::parse_oval_results(&xml),
+            OscapModule::Xccdf => findings::parse_xccdf_results(&xml),
+        });
+
+    let mut parsed = parsed.map_err(|e| {
+        remove_outputs();
+        format!("Не удалось разобрать результаты {}: {}", options.module.command().to_uppercase(), e)
+    })?;
+    notes.push(match options.module {
+        OscapModule::Oval => format!("Уязвимых определений: {}", parsed.findings.len()),
+        OscapModule::Xccdf => format!("Невыполненных правил: {}", parsed.findings.len()),
+    });
+    if let Some(path) = cve_data_path {
+        match cve::CveDatabase::load(path) {
+            Ok(database) => database.enrich(&mut parsed.findings),
+            Err(e) => notes.push(e),
+        }
+    }
+    if let Err(e) = rpm::correlate(&mut parsed.findings) {
+        notes.push(e);
+    }
+    run.content_version = parsed.content_version;
+
+    Ok(ScanOutcome {
+        notes,
+        findings: parsed.findings,
+        complete: true,
+        run,
+    })
+}
+
+// Options and rules given to each of several yara processes. Every process would compile
+// source rules again, so they are compiled once when `precompile` is set. Rules compiled
+// by the validation step are reused while the sources are unchanged.
+pub fn parallel_rules(
+    options: &YaraOptions,
+    rules: &[RuleFile],
+    workers: usize,
+    precompile: bool,
+    notes: &mut Vec<String>,
+    monitor: &dyn ScanMonitor,
+) -> (YaraOptions, Vec<RuleFile>) {
+    let compiled = if options.compiled_rules {
+        None
+    } else if let Some(path) = rules::cached(rules, &options.defines) {
+        notes.push("Использованы скомпилированные правила из кэша".to_string());
+        Some(path)
+    } else if precompile {
+        match rules::compile(rules, &options.defines, monitor) {
+            Ok(Compilation {
+                compiled: Some(path), ..
+            }) => {
+                notes.push("Правила скомпилированы один раз для всех процессов yara".to_string());
+                Some(path)
+            }
+            // Errors in the rules are reported by yara itself
+            Ok(_) => None,
+            Err(e) => {
+                notes.push(format!("Правила не скомпилированы заранее ({}), каждый процесс yara компилирует их сам", e));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let mut parallel = options.clone();
+    // yara threads are shared among the parallel processes
+    if options.threads > 0 {
+        parallel.threads = (options.threads / workers as u32).max(1);
+    }
+    match compiled {
+        Some(path) => {
+            parallel.compiled_rules = true;
+            (parallel, vec![RuleFile::new(&path.to_string_lossy())])
+        }
+        None => (parallel, rules.to_vec()),
+    }
+}
+
+// Files per yara process: small enough for smooth progress and pausing, large enough
+// that starting yara does not dominate the scan
+const MAX_BATCH: usize = 256;
+
+fn batches(count: usize, workers: usize) -> Vec<Range<usize>> {
+    let size = (count / (workers * 20)).clamp(1, MAX_BATCH);
+    (0..count).step_by(size).map(|start| start..(start + size).min(count)).collect()
+}
+
+// yara reads the scan list line by line as text, other names cannot be listed
+fn listable(file: &Path) -> bool {
+    file.to_str().is_some_and(|name| !name.contains('\n'))
+}
+
+// List file for `yara --scan-list`, removed by the caller. The files must be `listable`.
+fn write_scan_list(files: &[PathBuf]) -> Result<PathBuf, String> {
+    let list: String = files.iter().map(|file| format!("{}\n", file.display())).collect();
+    let number = SCAN_LISTS.fetch_add(1, Ordering::Relaxed);
+    let path = storage::path(&format!("scan-lists/{}-{}.txt", std::process::id(), number));
+    std::fs::write(&path, list).map_err(|e| format!("Не удалось записать список файлов {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+// One line per target of a multi-target scan
+pub fn target_notes(results: &[TargetResult]) -> Vec<String> {
+    if results.len() < 2 {
+        return Vec::new();
+    }
+    results
+        .iter()
+        .map(|result| {
+            match result.error {
+                None => format!("{}: файлов {}, совпадений {}", result.path, result.files, result.matches),
+                Some(error) => format!("{}: {}", result.path, error.label()),
+            }
+        })
+        .collect()
+}
+
+// With `scan_list` the target is a file listing the paths to scan
+pub fn yara_args(options: &YaraOptions, rules: &[RuleFile], target: &str, scan_list: bool) -> Vec<String> {
+    // Form command with additional options
+    let mut args = Vec::new();
+
+    let flags = [
+        (options.recursive && !scan_list, "-r"),
+        (options.fast_scan, "-f"),
+        (options.no_warnings, "-w"),
+        (options.print_tags, "-g"),
+        (options.print_strings, "-s"),
+        (options.print_meta, "-m"),
+        (options.print_namespace, "-e"),
+        (options.no_follow_symlinks, "-N"),
+        (options.compiled_rules, "-C"),
+    ];
+    for (enabled, flag) in flags {
+        if enabled {
+            args.push(flag.to_string());
+        }
+    }
+
+    if options.threads > 0 {
+        args.push(format!("--threads={}", options.threads));
+    }
+    if options.timeout > 0 {
+        args.push(format!("--timeout={}", options.timeout));
+    }
+    if options.max_rules > 0 {
+        args.push(format!("--max-rules={}", options.max_rules));
+    }
+    if options.max_file_size > 0 {
+        args.push(format!("--skip-larger={}", options.max_file_size));
+    }
+    for define in &options.defines {
+        args.push(format!("--define={}={}", define.name, define.value));
+    }
+    if !options.identifier.is_empty() {
+        args.push(format!("--identifier={}", options.identifier));
+    }
+
+    // Compiled rules already carry their namespaces
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        if options.compiled_rules {
+            args.push(rule.path.clone());
+        } else {
+            args.push(rule.argument());
+        }
+    }
+    if scan_list {
+        args.push("--scan-list".to_string());
+    }
+    args.push(target.to_string());
+    args
+}
+
+pub fn run_yara(
+    options: &YaraOptions,
+    rules: &[RuleFile],
+    targets: &[String],
+    monitor: &dyn ScanMonitor,
+) -> Result<ScanOutcome, String> {
+    options.validate(rules, targets)?;
+    // Rules compiled by yarac (-C) are only understood by the yara binary
+    #[cfg(feature = "embedded-yara")]
+    {
+        if !options.compiled_rules {
+            // Evidence is collected by the engine while unpacked archives still exist
+            return crate::yara_engine::run(options, rules, targets, monitor);
+        }
+    }
+
+    // Targets are walked by the app so the scan filters apply. The files are split into batches
+    // scanned by parallel yara processes, each given its batch with --scan-list.
+    let walked = walker::walk_targets(targets, options, monitor)?;
+    let (files, unlisted): (Vec<PathBuf>, Vec<PathBuf>) = walked.files.iter().cloned().partition(|file| listable(file));
+    let sizes: Vec<u64> = files.iter().map(|file| file.metadata().map_or(0, |meta| meta.len())).collect();
+    let workers = options.workers.max(1) as usize;
+    let batches = batches(files.len(), workers);
+    let mut notes = Vec::new();
+    if let Some(first) = unlisted.first() {
+        notes.push(format!(
+            "Не просканировано файлов с именами не в UTF-8 или с переводом строки: {} (например, {:?})",
+            unlisted.len(),
+            first
+        ));
+    }
+    let (batch_options, batch_rules) = parallel_rules(options, rules, workers, batches.len() > 1, &mut notes, monitor);
+    let rule_paths: Vec<&str> = rules.iter().filter(|rule| rule.enabled).map(|rule| rule.path.as_str()).collect();
+
+    let started_at = timestamp();
+    let tracker = ProgressTracker::new(monitor, "Файлов", files.len(), sizes.iter().sum());
+    let outputs = pool::run_pool(
+        &batches,
+        workers,
+        || (),
+        |_, batch| {
+            tracker.checkpoint()?;
+            let files = &files[batch.clone()];
+            tracker.start(&match files.len() {
+                1 => files[0].display().to_string(),
+                count => format!("{} и еще {}", files[0].display(), count - 1),
+            });
+
+            let list = write_scan_list(files)?;
+            let args = yara_args(&batch_options, &batch_rules, &list.to_string_lossy(), true);
+            let result = run_command(Command::new("yara").args(&args), monitor).map_err(|e| launch_error("YARA", e));
+            let _ = std::fs::remove_file(&list);
+            let (stdout, stderr, status) = result?;
+
+            tracker.finish(files.len(), sizes[batch.clone()].iter().sum());
+            // Matches printed before the failure are kept, the batch is reported as failed
+            let error = (!status.success()).then(|| {
+                let error = format!(
+                    "yara завершился с ошибкой (код {}) на файлах {}-{}: {}",
+                    status.code().map_or("?".to_string(), |code| code.to_string()),
+                    batch.start + 1,
+                    batch.end,
+                    stderr.trim()
+                );
+                monitor.output(OutputStream::Stderr, &error);
+                error
+            });
+            Ok((stdout, error))
+        },
+    )?;
+    let progress = tracker.done();
+
+    let errors: Vec<&String> = outputs.iter().filter_map(|(_, error)| error.as_ref()).collect();
+    if !errors.is_empty() && errors.len() == outputs.len() {
+        return Err(errors[0].clone());
+    }
+    if let Some(first) = errors.first() {
+        notes.push(format!("Пакетов файлов с ошибкой yara: {} из {}. {}", errors.len(), outputs.len(), first));
+    }
+    let mut findings: Vec<Finding> = outputs.iter().flat_map(|(stdout, _)| findings::parse_yara_output(stdout)).collect();
+    walked.archives.locate(&mut findings);
+    let evidence_notes = evidence::attach(&mut findings, monitor)?;
+    let mut target_results = walked.targets;
+    targets::count_matches(&mut target_results, &findings);
+    notes.splice(
+        0..0,
+        [
+            format!("Совпадений: {}", findings.len()),
+            format!("Файлов для сканирования: {}, исключено фильтрами: {}", files.len(), walked.skipped),
+            format!("Параллельных процессов yara: {}. {}", workers.min(batches.len()).max(1), progress.summary()),
+        ],
+    );
+    notes.extend(target_notes(&target_results));
+    notes.extend(walked.archives.notes.iter().cloned());
+    notes.extend(evidence_notes);
+    let args = yara_args(&batch_options, &batch_rules, "<список файлов>", true);
+    Ok(ScanOutcome {
+        notes,
+        findings,
+        complete: errors.is_empty(),
+        run: ScanRun {
+            engine: Engine::Yara,
+            started_at,
+            command: format!("yara {}", args.join(" ")),
+            content_version: rule_paths.join(", "),
+            targets: target_results,
+        },
+    })
+}
@@ -0,0 +1,244 @@
+// Cron-like schedules: "minute hour day-of-month month day-of-week"
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+// Days searched for the next run: four years reach the next February 29
+const SEARCH_DAYS: u32 = 4 * 366;
+
+pub struct CronSchedule {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days: Vec<u32>,
+    months: Vec<u32>,
+    weekdays: Vec<u32>,
+    // Cron matches either day field when both are restricted
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+// Expand "*", "5", "1-5", "*/15", "1-30/2" and comma separated lists
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+    let mut values = Vec::new();
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u32>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("Неверный шаг: {}", part))?,
+            ),
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // "5/10" means from 5 to the end of the range
+            (value, if step > 1 { max } else { value })
+        };
+
+        if start > end {
+            return Err(format!("Неверный диапазон: {}", part));
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+
+    values.sort_unstable();
+    values.dedup();
+    Ok(values)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|v| (min..=max).contains(v))
+        .ok_or_else(|| format!("Значение {} вне диапазона {}-{}", value, min, max))
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err("Расписание должно содержать 5 полей: минута час день месяц день_недели".to_string());
+        }
+
+        // 7 is an alias of Sunday
+        let mut weekdays: Vec<u32> = parse_field(fields[4], 0, 7)?.into_iter().map(|d| d % 7).collect();
+        weekdays.sort_unstable();
+        weekdays.dedup();
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            // As in cron, "*/2" still counts as unrestricted
+            days_restricted: !fields[2].starts_with('*'),
+            weekdays_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    fn date_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days.contains(&date.day());
+        let weekday = self.weekdays.contains(&date.weekday().num_days_from_sunday());
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        self.months.contains(&date.month()) && day_matches
+    }
+
+    pub fn matches(&self, time: &DateTime<Local>) -> bool {
+        self.minutes.contains(&time.minute()) && self.hours.contains(&time.hour()) && self.date_matches(time.date_naive())
+    }
+
+    // First matching minute after the given time. Whole days are skipped when the date does
+    // not match, so schedules that never fire, such as "0 0 31 2 *", end quickly with None.
+    pub fn next_after(&self, time: &DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = time.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date_naive();
+        for _ in 0..SEARCH_DAYS {
+            if self.date_matches(date) {
+                for hour in &self.hours {
+                    for minute in &self.minutes {
+                        // Times skipped by a daylight saving change do not exist
+                        let Some(candidate) = date.and_hms_opt(*hour, *minute, 0).and_then(|t| Local.from_local_datetime(&t).earliest()) else {
+                            continue;
+                        };
+                        if candidate >= start {
+                            return Some(candidate);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    // systemd OnCalendar= equivalents, the timer fires when any of them matches. systemd
+    // combines the day of month and the weekday with AND, so when both are restricted cron's
+    // "either" takes one expression for the days and one for the weekdays.
+    pub fn to_on_calendar(&self) -> Vec<String> {
+        let list = |values: &[u32], min: u32, max: u32| {
+            if values.len() as u32 == max - min + 1 {
+                "*".to_string()
+            } else {
+                values.iter().map(|v| format!("{:02}", v)).collect::<Vec<_>>().join(",")
+            }
+        };
+        let expression = |days: &str, weekdays: &[u32]| {
+            let date_time = format!(
+                "*-{}-{} {}:{}:00",
+                list(&self.months, 1, 12),
+                days,
+                list(&self.hours, 0, 23),
+                list(&self.minutes, 0, 59)
+            );
+            if weekdays.len() == 7 {
+                date_time
+            } else {
+                let names: Vec<&str> = weekdays.iter().map(|d| WEEKDAYS[*d as usize]).collect();
+                format!("{} {}", names.join(","), date_time)
+            }
+        };
+
+        let days = list(&self.days, 1, 31);
+        if self.days_restricted && self.weekdays_restricted {
+            vec![expression(&days, &[0, 1, 2, 3, 4, 5, 6]), expression("*", &self.weekdays)]
+        } else {
+            vec![expression(&days, &self.weekdays)]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Local> {
+        let time = chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    #[test]
+    fn fields_expand_ranges_steps_and_lists() {
+        assert_eq!(parse_field("*/15", 0, 59).unwrap(), vec![0, 15, 30, 45]);
+        assert_eq!(parse_field("1-5", 0, 59).unwrap(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(parse_field("1-10/3,2", 0, 59).unwrap(), vec![1, 2, 4, 7, 10]);
+        assert_eq!(parse_field("50/5", 0, 59).unwrap(), vec![50, 55]);
+        assert_eq!(parse_field("7", 0, 59).unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        assert!(parse_field("60", 0, 59).is_err());
+        assert!(parse_field("5-1", 0, 59).is_err());
+        assert!(parse_field("*/0", 0, 59).is_err());
+        assert!(parse_field("a", 0, 59).is_err());
+        assert!(CronSchedule::parse("0 3 * *").is_err());
+        assert!(CronSchedule::parse("0 24 * * *").is_err());
+    }
+
+    #[test]
+    fn sunday_is_zero_or_seven() {
+        let schedule = CronSchedule::parse("0 3 * * 7").unwrap();
+        assert_eq!(schedule.weekdays, vec![0]);
+        // 2024-06-02 is a Sunday
+        assert!(schedule.matches(&at("2024-06-02 03:00")));
+        assert!(!schedule.matches(&at("2024-06-03 03:00")));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 1st of the month or any Monday
+        let schedule = CronSchedule::parse("0 0 1 * 1").unwrap();
+        assert!(schedule.matches(&at("2024-06-01 00:00")));
+        assert!(schedule.matches(&at("2024-06-03 00:00")));
+        assert!(!schedule.matches(&at("2024-06-04 00:00")));
+    }
+
+    #[test]
+    fn restricted_day_fields_take_two_calendar_expressions() {
+        let schedule = CronSchedule::parse("0 0 1 * 1").unwrap();
+        assert_eq!(schedule.to_on_calendar(), vec!["*-*-01 00:00:00", "Mon *-*-* 00:00:00"]);
+        let schedule = CronSchedule::parse("30 3 * * 1-5").unwrap();
+        assert_eq!(schedule.to_on_calendar(), vec!["Mon,Tue,Wed,Thu,Fri *-*-* 03:30:00"]);
+    }
+
+    #[test]
+    fn stepped_star_is_not_a_day_restriction() {
+        // Every second day, but only on Mondays
+        let schedule = CronSchedule::parse("0 0 */2 * 1").unwrap();
+        assert!(!schedule.days_restricted);
+        assert!(schedule.matches(&at("2024-06-03 00:00")));
+        assert!(!schedule.matches(&at("2024-06-10 00:00")));
+        assert!(!schedule.matches(&at("2024-06-05 00:00")));
+    }
+
+    #[test]
+    fn next_run_is_found() {
+        let schedule = CronSchedule::parse("30 3 * * *").unwrap();
+        assert_eq!(schedule.next_after(&at("2024-06-02 03:30")), Some(at("2024-06-03 03:30")));
+        assert_eq!(schedule.next_after(&at("2024-06-02 01:10")), Some(at("2024-06-02 03:30")));
+    }
+
+    #[test]
+    fn next_leap_day_is_found() {
+        let schedule = CronSchedule::parse("0 12 29 2 *").unwrap();
+        assert_eq!(schedule.next_after(&at("2024-03-01 00:00")), Some(at("2028-02-29 12:00")));
+    }
+
+    #[test]
+    fn impossible_schedule_never_runs() {
+        let schedule = CronSchedule::parse("0 0 31 2 *").unwrap();
+        assert_eq!(schedule.next_after(&at("2024-01-01 00:00")), None);
+    }
+}
@@ -0,0 +1,357 @@
+// Recurring scan jobs run by the background service or by systemd timers
+use crate::exceptions;
+use crate::export::{self, ExportFormat};
+use crate::rules::{self, RuleFile};
+use crate::findings::Finding;
+use crate::scan::{self, timestamp, OpenScapOptions, ScanMonitor, YaraOptions};
+use crate::schedule::CronSchedule;
+use crate::storage;
+use crate::suppressions;
+use crate::targets;
+use chrono::Timelike;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+const JOBS_FILE: &str = "jobs.json";
+const HISTORY_FILE: &str = "history.json";
+// Older entries are dropped from the history file
+const HISTORY_LIMIT: usize = 500;
+// Minutes looked back after a long run or a suspend, a job due in any of them runs once
+const CATCH_UP_MINUTES: i64 = 24 * 60;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledJob {
+    pub name: String,
+    // Cron expression, see `CronSchedule`
+    pub schedule: String,
+    pub enabled: bool,
+    pub task: JobTask,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JobTask {
+    // Optionally refresh the OVAL content, then evaluate it
+    Oval {
+        content_url: String,
+        refresh_content: bool,
+        content_path: String,
+        options: OpenScapOptions,
+        // Offline CVE data the findings are enriched with, as in the main window
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        cve_data_path: String,
+    },
+    Yara {
+        #[serde(default)]
+        rules: Vec<RuleFile>,
+        // Single rules file of jobs saved before rule lists were supported
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        rules_path: String,
+        #[serde(default)]
+        targets: Vec<String>,
+        // Single scan path of jobs saved before target lists were supported
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        scan_path: String,
+        options: YaraOptions,
+    },
+}
+
+impl JobTask {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobTask::Oval { .. } => "OpenSCAP",
+            JobTask::Yara { .. } => "YARA",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub job: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub success: bool,
+    pub message: String,
+    // Keys of all findings, used to detect new ones on the next run
+    pub finding_keys: Vec<String>,
+    pub new_findings: Vec<String>,
+    // JSON export of the findings of this run
+    pub results_file: String,
+}
+
+pub fn load_jobs() -> Result<Vec<ScheduledJob>, String> {
+    storage::load(JOBS_FILE)
+}
+
+pub fn save_jobs(jobs: &[ScheduledJob]) -> Result<(), String> {
+    storage::save(JOBS_FILE, &jobs)
+}
+
+pub fn load_history() -> Result<Vec<HistoryEntry>, String> {
+    storage::load(HISTORY_FILE)
+}
+
+fn append_history(entry: &HistoryEntry) -> Result<(), String> {
+    let mut history = load_history()?;
+    history.push(entry.clone());
+    let excess = history.len().saturating_sub(HISTORY_LIMIT);
+    history.drain(..excess);
+    storage::save(HISTORY_FILE, &history)
+}
+
+// Job names end up in file and systemd unit names
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err("Имя задания может содержать только латинские буквы, цифры, '-' и '_'".to_string());
+    }
+    Ok(())
+}
+
+fn finding_key(finding: &Finding) -> String {
+    format!("{}|{}|{}", finding.engine.tool_name(), finding.rule_id, finding.target)
+}
+
+fn run_task(job: &ScheduledJob, monitor: &dyn ScanMonitor) -> Result<scan::ScanOutcome, String> {
+    match &job.task {
+        JobTask::Oval {
+            content_url,
+            refresh_content,
+            content_path,
+            options,
+            cve_data_path,
+        } => {
+            if *refresh_content {
+                let content = scan::download_content(content_url, monitor)?;
+                std::fs::write(content_path, content)
+                    .map_err(|e| format!("Не удалось сохранить файл: {}", e))?;
+            }
+            // A failed run must not leave the results of the one before to be read back
+            let (results_path, report_path) = scan::oscap_files(&job.name);
+            scan::run_oscap(
+                options,
+                content_path,
+                &results_path,
+                &report_path,
+                (!cve_data_path.is_empty()).then_some(cve_data_path.as_str()),
+                monitor,
+            )
+        }
+        JobTask::Yara {
+            rules,
+            rules_path,
+            targets,
+            scan_path,
+            options,
+        } => {
+            let mut rules = rules.clone();
+            if !rules_path.is_empty() {
+                rules::add_files(&mut rules, [rules_path.clone()]);
+            }
+            let mut targets = targets.clone();
+            if !scan_path.is_empty() {
+                targets::add(&mut targets, [scan_path.clone()]);
+            }
+            scan::run_yara(options, &rules, &targets, monitor)
+        }
+    }
+}
+
+// Run a job once, record it in the history and alert about new findings. The results are
+// written in the given format to `output`, or to the results directory when it is None.
+pub fn run_job(job: &ScheduledJob, format: ExportFormat, output: Option<&Path>, monitor: &dyn ScanMonitor) -> HistoryEntry {
+    let started_at = timestamp();
+
+    let previous: HashSet<String> = load_history()
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .find(|entry| entry.job == job.name && entry.success)
+        .map(|entry| entry.finding_keys.into_iter().collect())
+        .unwrap_or_default();
+
+    let mut entry = HistoryEntry {
+        job: job.name.clone(),
+        started_at,
+        finished_at: String::new(),
+        success: false,
+        message: String::new(),
+        finding_keys: Vec::new(),
+        new_findings: Vec::new(),
+        results_file: String::new(),
+    };
+
+    match run_task(job, monitor) {
+        Ok(outcome) => {
+            // Findings of a partly failed scan do not become the baseline of the next run
+            entry.success = outcome.complete;
+            // Known false positives neither alert nor count as findings of the job
+            let suppressions = suppressions::load().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                Vec::new()
+            });
+            let (findings, suppressed) = suppressions::apply(&outcome.findings, &suppressions);
+            // Accepted risks are kept in the results but do not alert
+            let exceptions = exceptions::load().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                Vec::new()
+            });
+            let findings = exceptions::apply(&findings, &exceptions);
+            let accepted = exceptions::accepted(&findings).len();
+            entry.finding_keys = findings
+                .iter()
+                .filter(|f| f.exception.is_none())
+                .map(finding_key)
+                .collect();
+            entry.new_findings = entry
+                .finding_keys
+                .iter()
+                .filter(|key| !previous.contains(*key))
+                .cloned()
+                .collect();
+            entry.message = format!(
+                "Находок: {}, новых: {}",
+                entry.finding_keys.len(),
+                entry.new_findings.len()
+            );
+            if accepted > 0 {
+                entry.message.push_str(&format!(", принятых рисков: {}", accepted));
+            }
+            if suppressed > 0 {
+                entry.message.push_str(&format!(", скрыто как ложные: {}", suppressed));
+            }
+            if !outcome.complete {
+                entry.message.push_str("; сканирование выполнено не полностью, новые находки считаются от последнего полного запуска");
+            }
+
+            let results_file = output.map(Path::to_path_buf).unwrap_or_else(|| {
+                storage::path(&format!(
+                    "results/{}-{}.{}",
+                    job.name,
+                    chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                    format.extension()
+                ))
+            });
+            match export::write_to_file(&findings, format, &results_file) {
+                Ok(_) => entry.results_file = results_file.to_string_lossy().to_string(),
+                Err(e) => entry.message.push_str(&format!("; не удалось сохранить результаты: {}", e)),
+            }
+        }
+        Err(e) => entry.message = e,
+    }
+
+    entry.finished_at = timestamp();
+
+    if !entry.new_findings.is_empty() {
+        alert(&entry);
+    }
+    if let Err(e) = append_history(&entry) {
+        eprintln!("{}", e);
+    }
+
+    entry
+}
+
+// Alerts go to the system journal and, when a desktop session is available, to notifications
+fn alert(entry: &HistoryEntry) {
+    let message = format!(
+        "Задание {}: новых находок {} ({})",
+        entry.job,
+        entry.new_findings.len(),
+        entry.new_findings.join(", ")
+    );
+    let _ = Command::new("logger")
+        .args(["-t", "security-scanner", "-p", "auth.warning", &message])
+        .status();
+    let _ = Command::new("notify-send")
+        .args(["Security Scanner", &message])
+        .status();
+}
+
+fn current_minute() -> chrono::DateTime<chrono::Local> {
+    let now = chrono::Local::now();
+    now.with_second(0).and_then(|time| time.with_nanosecond(0)).unwrap_or(now)
+}
+
+// Background service: runs due jobs at the start of every minute. Every minute since the last
+// check is looked at, so jobs that came due while others were running are not lost.
+pub fn run_service(format: ExportFormat) {
+    println!("Планировщик запущен, задания: {}", storage::path(JOBS_FILE).display());
+    let mut last_checked = current_minute() - chrono::Duration::minutes(1);
+    loop {
+        let now = current_minute();
+        // The clock was set back: only the current minute is checked
+        if now <= last_checked {
+            last_checked = now - chrono::Duration::minutes(1);
+        }
+        let missed = (now - last_checked).num_minutes().min(CATCH_UP_MINUTES);
+        let minutes: Vec<_> = (0..missed).rev().map(|back| now - chrono::Duration::minutes(back)).collect();
+        last_checked = now;
+
+        match load_jobs() {
+            Ok(jobs) => {
+                for job in jobs.iter().filter(|job| job.enabled) {
+                    match CronSchedule::parse(&job.schedule) {
+                        Ok(schedule) if minutes.iter().any(|minute| schedule.matches(minute)) => {
+                            let entry = run_job(job, format, None, &scan::NoMonitor);
+                            println!("[{}] {}: {}", entry.finished_at, job.name, entry.message);
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("{}: {}", job.name, e),
+                    }
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+
+        // Sleep until the beginning of the next minute
+        let elapsed = chrono::Local::now().timestamp() % 60;
+        std::thread::sleep(std::time::Duration::from_secs((60 - elapsed) as u64));
+    }
+}
+
+fn unit_name(job: &ScheduledJob) -> String {
+    format!("security-scanner-{}", job.name)
+}
+
+// Write a .service/.timer pair for every enabled job
+pub fn write_systemd_units(jobs: &[ScheduledJob], dir: &Path) -> Result<Vec<String>, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let mut timers = Vec::new();
+
+    for job in jobs.iter().filter(|job| job.enabled) {
+        let schedule = CronSchedule::parse(&job.schedule).map_err(|e| format!("{}: {}", job.name, e))?;
+        let name = unit_name(job);
+
+        let service = format!(
+            "[Unit]\nDescription=Security Scanner job {job}\n\n[Service]\nType=oneshot\nExecStart=\"{exe}\" --run-job {job}\n",
+            job = job.name,
+            exe = exe.display()
+        );
+        let timer = format!(
+            "[Unit]\nDescription=Security Scanner schedule for {job}\n\n[Timer]\n{calendar}Persistent=true\n\n[Install]\nWantedBy=timers.target\n",
+            job = job.name,
+            calendar = schedule
+                .to_on_calendar()
+                .iter()
+                .map(|expression| format!("OnCalendar={}\n", expression))
+                .collect::<String>()
+        );
+
+        std::fs::write(dir.join(format!("{}.service", name)), service)
+            .and_then(|_| std::fs::write(dir.join(format!("{}.timer", name)), timer))
+            .map_err(|e| format!("Не удалось записать unit-файлы: {}", e))?;
+        timers.push(format!("{}.timer", name));
+    }
+
+    // Alternative to the timers: one long-running service using the built-in scheduler
+    let scheduler = format!(
+        "[Unit]\nDescription=Security Scanner scheduler\nAfter=network-online.target\n\n[Service]\nExecStart=\"{}\" --scheduler\nRestart=on-failure\n\n[Install]\nWantedBy=default.target\n",
+        exe.display()
+    );
+    std::fs::write(dir.join("security-scanner-scheduler.service"), scheduler)
+        .map_err(|e| format!("Не удалось записать unit-файлы: {}", e))?;
+
+    Ok(timers)
+}
@@ -0,0 +1,403 @@
+// Scheduler window: job definitions, manual runs and run history
+use crate::export::ExportFormat;
+use crate::jobs::JobManager;
+use crate::rules::{self, RuleFile};
+use crate::scan::{OpenScapOptions, YaraOptions};
+use crate::schedule::CronSchedule;
+use crate::scheduler::{self, HistoryEntry, JobTask, ScheduledJob};
+use crate::storage;
+use crate::targets;
+use eframe::egui;
+use rfd::FileDialog;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Number of history entries shown in the window
+const HISTORY_SHOWN: usize = 50;
+// Group of the manual runs in the jobs panel
+const JOB_GROUP: &str = "Планировщик";
+// Runs of the background service show up after this long
+const HISTORY_RELOAD: Duration = Duration::from_secs(10);
+
+// Settings of the tool panels at the time the window is drawn
+pub struct PanelSettings<'a> {
+    pub content_url: &'a str,
+    pub oscap_options: &'a OpenScapOptions,
+    pub cve_data_path: Option<&'a str>,
+    pub yara_options: &'a YaraOptions,
+    pub yara_rules: &'a [RuleFile],
+    pub scan_targets: &'a [String],
+}
+
+pub struct SchedulerPanel {
+    jobs: Vec<ScheduledJob>,
+    history: Arc<Mutex<Vec<HistoryEntry>>>,
+    history_loaded: Instant,
+    // Next run by schedule text, with the minute it was computed in
+    next_runs: HashMap<String, (i64, Result<String, String>)>,
+    draft: ScheduledJob,
+    message: String,
+}
+
+// Next run of a schedule, computed again once a minute rather than every frame
+fn next_run(cache: &mut HashMap<String, (i64, Result<String, String>)>, schedule: &str, now: &chrono::DateTime<chrono::Local>) -> Result<String, String> {
+    let minute = now.timestamp() / 60;
+    match cache.get(schedule) {
+        Some((computed, next)) if *computed == minute => next.clone(),
+        _ => {
+            let next = CronSchedule::parse(schedule).map(|schedule| {
+                schedule
+                    .next_after(now)
+                    .map_or_else(|| "никогда".to_string(), |next| next.format("%Y-%m-%d %H:%M").to_string())
+            });
+            cache.insert(schedule.to_string(), (minute, next.clone()));
+            next
+        }
+    }
+}
+
+impl Default for SchedulerPanel {
+    fn default() -> Self {
+        let mut message = String::new();
+        let jobs = scheduler::load_jobs().unwrap_or_else(|e| {
+            message = e;
+            Vec::new()
+        });
+        let history = scheduler::load_history().unwrap_or_default();
+
+        Self {
+            jobs,
+            history: Arc::new(Mutex::new(history)),
+            history_loaded: Instant::now(),
+            next_runs: HashMap::new(),
+            draft: ScheduledJob {
+                name: String::new(),
+                schedule: "0 3 * * *".to_string(),
+                enabled: true,
+                task: JobTask::Oval {
+                    content_url: String::new(),
+                    refresh_content: true,
+                    content_path: storage::path("redos.xml").to_string_lossy().to_string(),
+                    options: OpenScapOptions::default(),
+                    cve_data_path: String::new(),
+                },
+            },
+            message,
+        }
+    }
+}
+
+impl SchedulerPanel {
+    fn save(&mut self) {
+        if let Err(e) = scheduler::save_jobs(&self.jobs) {
+            self.message = e;
+        }
+    }
+
+    fn reload_history(&mut self) {
+        match scheduler::load_history() {
+            Ok(history) => *self.history.lock().unwrap() = history,
+            Err(e) => self.message = e,
+        }
+        self.history_loaded = Instant::now();
+    }
+
+    // Current panel options are used for new jobs
+    pub fn show(&mut self, ui: &mut egui::Ui, jobs: &JobManager, panel: PanelSettings) {
+        let PanelSettings {
+            content_url,
+            oscap_options,
+            cve_data_path: panel_cve_data,
+            yara_options,
+            yara_rules,
+            scan_targets,
+        } = panel;
+        ui.label(format!(
+            "Задания выполняются службой `--scheduler` или таймерами systemd, формат результатов задается \
+             параметром `--format json|csv|sarif`. Файл заданий: {}",
+            storage::path("jobs.json").display()
+        ));
+
+        ui.separator();
+        ui.strong("Задания");
+
+        let now = chrono::Local::now();
+        let next_runs: Vec<Result<String, String>> =
+            self.jobs.iter().map(|job| next_run(&mut self.next_runs, &job.schedule, &now)).collect();
+        let mut remove = None;
+        let mut changed = false;
+
+        egui::Grid::new("scheduled_jobs").striped(true).num_columns(7).show(ui, |ui| {
+            ui.strong("Вкл.");
+            ui.strong("Имя");
+            ui.strong("Тип");
+            ui.strong("Расписание");
+            ui.strong("Следующий запуск");
+            ui.strong("Запуск");
+            ui.label("");
+            ui.end_row();
+
+            for ((index, job), next) in self.jobs.iter_mut().enumerate().zip(next_runs) {
+                changed |= ui.checkbox(&mut job.enabled, "").changed();
+                ui.label(&job.name);
+                ui.label(job.task.label());
+                ui.monospace(&job.schedule);
+                match next {
+                    Ok(next) => ui.label(next),
+                    Err(e) => ui.colored_label(egui::Color32::RED, e),
+                };
+
+                if jobs.is_active(JOB_GROUP, &job.name) {
+                    ui.label("Выполняется...");
+                } else if ui.button("Запустить сейчас").clicked() {
+                    let job = job.clone();
+                    let history = Arc::clone(&self.history);
+                    jobs.spawn(JOB_GROUP, &job.name.clone(), move |handle| {
+                        let entry = scheduler::run_job(&job, ExportFormat::Json, None, handle);
+                        let result = if entry.success {
+                            Ok(entry.message.clone())
+                        } else {
+                            Err(entry.message.clone())
+                        };
+                        history.lock().unwrap().push(entry);
+                        result
+                    });
+                }
+                if ui.button("Удалить").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(index) = remove {
+            self.jobs.remove(index);
+            changed = true;
+        }
+        if changed {
+            self.save();
+        }
+
+        ui.separator();
+        ui.strong("Новое задание");
+
+        ui.horizontal(|ui| {
+            ui.label("Имя:");
+            ui.text_edit_singleline(&mut self.draft.name);
+            ui.label("Расписание (мин час день месяц день_недели):");
+            ui.text_edit_singleline(&mut self.draft.schedule);
+        });
+
+        ui.horizontal(|ui| {
+            let is_oval = matches!(self.draft.task, JobTask::Oval { .. });
+            if ui.radio(is_oval, "OpenSCAP").clicked() && !is_oval {
+                self.draft.task = JobTask::Oval {
+                    content_url: content_url.to_string(),
+                    refresh_content: true,
+                    content_path: storage::path("redos.xml").to_string_lossy().to_string(),
+                    options: OpenScapOptions::default(),
+                    cve_data_path: panel_cve_data.unwrap_or_default().to_string(),
+                };
+            }
+            if ui.radio(!is_oval, "YARA").clicked() && is_oval {
+                self.draft.task = JobTask::Yara {
+                    rules: Vec::new(),
+                    rules_path: String::new(),
+                    targets: Vec::new(),
+                    scan_path: String::new(),
+                    options: YaraOptions::default(),
+                };
+            }
+        });
+
+        match &mut self.draft.task {
+            JobTask::Oval {
+                content_url: url,
+                refresh_content,
+                content_path,
+                cve_data_path,
+                ..
+            } => {
+                if url.is_empty() {
+                    *url = content_url.to_string();
+                }
+                ui.checkbox(refresh_content, "Обновлять описание уязвимостей перед проверкой");
+                ui.horizontal(|ui| {
+                    ui.label("URL:");
+                    ui.text_edit_singleline(url);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("XML-файл:");
+                    ui.text_edit_singleline(content_path);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Файл сведений CVE:");
+                    ui.add(egui::TextEdit::singleline(cve_data_path).hint_text("не используется"));
+                    if let Some(path) = panel_cve_data {
+                        if ui.button("Взять из главного окна").clicked() {
+                            *cve_data_path = path.to_string();
+                        }
+                    }
+                });
+            }
+            JobTask::Yara {
+                rules,
+                rules_path,
+                targets,
+                scan_path,
+                ..
+            } => {
+                ui.horizontal(|ui| {
+                    if ui.button("Файлы правил").clicked() {
+                        if let Some(paths) = FileDialog::new()
+                            .add_filter("YARA Rules", &rules::RULE_EXTENSIONS)
+                            .pick_files()
+                        {
+                            rules::add_files(rules, paths.iter().map(|path| path.to_string_lossy().to_string()));
+                        }
+                    }
+                    if ui.button("Папка правил").clicked() {
+                        if let Some(dir) = FileDialog::new().pick_folder() {
+                            if let Ok(paths) = rules::collect_dir(&dir) {
+                                rules::add_files(rules, paths);
+                            }
+                        }
+                    }
+                    if ui.button("Взять из панели YARA").clicked() {
+                        *rules = yara_rules.to_vec();
+                        rules_path.clear();
+                    }
+                });
+                if !rules_path.is_empty() {
+                    ui.label(format!("Файл правил: {}", rules_path));
+                }
+                let mut remove = None;
+                for (index, rule) in rules.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("x").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.label(format!("{}: {}", rule.namespace, rule.path));
+                    });
+                }
+                if let Some(index) = remove {
+                    rules.remove(index);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Папка для сканирования").clicked() {
+                        if let Some(path) = FileDialog::new().pick_folder() {
+                            targets::add(targets, [path.to_string_lossy().to_string()]);
+                        }
+                    }
+                    if ui.button("Файл для сканирования").clicked() {
+                        if let Some(path) = FileDialog::new().pick_file() {
+                            targets::add(targets, [path.to_string_lossy().to_string()]);
+                        }
+                    }
+                    if ui.button("Взять цели из панели YARA").clicked() {
+                        *targets = scan_targets.to_vec();
+                        scan_path.clear();
+                    }
+                });
+                if !scan_path.is_empty() {
+                    ui.label(format!("Путь для сканирования: {}", scan_path));
+                }
+                let mut remove = None;
+                for (index, target) in targets.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("x").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.label(target.as_str());
+                    });
+                }
+                if let Some(index) = remove {
+                    targets.remove(index);
+                }
+            }
+        }
+        ui.label("Опции сканирования берутся из текущих настроек панели инструмента.");
+
+        if ui.button("Добавить задание").clicked() {
+            let mut job = self.draft.clone();
+            match &mut job.task {
+                JobTask::Oval { options, .. } => *options = oscap_options.clone(),
+                JobTask::Yara { options, .. } => *options = yara_options.clone(),
+            }
+
+            let valid = scheduler::validate_name(&job.name)
+                .and_then(|_| CronSchedule::parse(&job.schedule).map(|_| ()))
+                .and_then(|_| {
+                    if self.jobs.iter().any(|j| j.name == job.name) {
+                        Err(format!("Задание {} уже существует", job.name))
+                    } else {
+                        Ok(())
+                    }
+                });
+            match valid {
+                Ok(_) => {
+                    self.message = format!("Задание {} добавлено", job.name);
+                    self.jobs.push(job);
+                    self.save();
+                }
+                Err(e) => self.message = e,
+            }
+        }
+
+        if ui.button("Сгенерировать unit-файлы systemd").clicked() {
+            if let Some(dir) = FileDialog::new()
+                .set_title("Папка для unit-файлов (например, ~/.config/systemd/user)")
+                .pick_folder()
+            {
+                self.message = match scheduler::write_systemd_units(&self.jobs, &dir) {
+                    Ok(timers) => format!(
+                        "Unit-файлы записаны в {}. Включите таймеры: systemctl --user daemon-reload && systemctl --user enable --now {} \
+                         (или одну службу security-scanner-scheduler.service)",
+                        dir.display(),
+                        timers.join(" ")
+                    ),
+                    Err(e) => e,
+                };
+            }
+        }
+
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+
+        ui.separator();
+        if self.history_loaded.elapsed() >= HISTORY_RELOAD {
+            self.reload_history();
+        }
+        ui.horizontal(|ui| {
+            ui.strong("История запусков");
+            if ui.small_button("Обновить").clicked() {
+                self.reload_history();
+            }
+        });
+
+        let history = self.history.lock().unwrap();
+        egui::ScrollArea::vertical()
+            .id_source("scheduler_history")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                for entry in history.iter().rev().take(HISTORY_SHOWN) {
+                    let status = if entry.success { "✔" } else { "✖" };
+                    ui.label(format!(
+                        "{} {} [{} — {}] {}",
+                        status, entry.job, entry.started_at, entry.finished_at, entry.message
+                    ));
+                    if !entry.new_findings.is_empty() {
+                        ui.colored_label(
+                            egui::Color32::RED,
+                            format!("Новые находки: {}", entry.new_findings.join(", ")),
+                        );
+                    }
+                    if !entry.results_file.is_empty() {
+                        ui.small(format!("Результаты: {}", entry.results_file));
+                    }
+                }
+            });
+    }
+}
@@ -0,0 +1,41 @@
+// Files kept between runs in the user's data directory
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+
+pub fn data_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_else(|| PathBuf::from("/var/lib"));
+    base.join("security-scanner")
+}
+
+// Path inside the data directory, creating missing parent directories
+pub fn path(name: &str) -> PathBuf {
+    let path = data_dir().join(name);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    path
+}
+
+// A missing file yields the default value
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> Result<T, String> {
+    let path = path(name);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|e| format!("Ошибка разбора {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("Не удалось прочитать {}: {}", path.display(), e)),
+    }
+}
+
+// Write to a temporary file first so a crash never leaves a truncated file
+pub fn save<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let path = path(name);
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, text)
+        .and_then(|_| std::fs::rename(&tmp, &path))
+        .map_err(|e| format!("Не удалось сохранить {}: {}", path.display(), e))
+}
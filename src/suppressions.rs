@@ -0,0 +1,146 @@
+// Allow-list of YARA matches known to be false positives. Suppressed matches stay in the scan
+// results but are left out of the results view, reports, exports and scheduler alerts.
+use crate::findings::{Engine, Finding};
+use crate::storage;
+use crate::walker::{self, Pattern};
+use serde::{Deserialize, Serialize};
+
+const SUPPRESSIONS_FILE: &str = "suppressions.json";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// A match is suppressed when every criterion that is set applies to it
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Suppression {
+    // SHA-256 of the matched file
+    pub sha256: String,
+    // Glob as in the scan filters: with "/" for the full path, otherwise the file name
+    pub path_pattern: String,
+    // Rule name, with or without the namespace
+    pub rule: String,
+    // Last day the suppression applies, YYYY-MM-DD; empty for no expiry
+    pub expires: String,
+    pub justification: String,
+    pub created_at: String,
+}
+
+pub fn load() -> Result<Vec<Suppression>, String> {
+    storage::load(SUPPRESSIONS_FILE)
+}
+
+pub fn save(suppressions: &[Suppression]) -> Result<(), String> {
+    storage::save(SUPPRESSIONS_FILE, &suppressions)
+}
+
+fn today() -> chrono::NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+impl Suppression {
+    // Prefilled from a match marked as a false positive: the rule and the file hash, or the
+    // executable of a scanned process
+    pub fn for_finding(finding: &Finding) -> Self {
+        let sha256 = finding.file.as_ref().map(|file| file.sha256.clone()).unwrap_or_default();
+        let path = match &finding.process {
+            Some(process) => process.exe.clone(),
+            None => finding.target.clone(),
+        };
+        Self {
+            path_pattern: if sha256.is_empty() && !path.is_empty() { globset::escape(&path) } else { String::new() },
+            sha256,
+            rule: finding.rule_id.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sha256.trim().is_empty() && self.path_pattern.trim().is_empty() && self.rule.trim().is_empty() {
+            return Err("Укажите хеш, шаблон пути или правило".to_string());
+        }
+        let sha256 = self.sha256.trim();
+        if !sha256.is_empty() && (sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit())) {
+            return Err("Хеш SHA-256 должен состоять из 64 шестнадцатеричных цифр".to_string());
+        }
+        walker::compile(std::slice::from_ref(&self.path_pattern))?;
+        self.expiry_date()?;
+        if self.justification.trim().is_empty() {
+            return Err("Укажите обоснование подавления".to_string());
+        }
+        Ok(())
+    }
+
+    fn expiry_date(&self) -> Result<Option<chrono::NaiveDate>, String> {
+        let expires = self.expires.trim();
+        if expires.is_empty() {
+            return Ok(None);
+        }
+        chrono::NaiveDate::parse_from_str(expires, DATE_FORMAT)
+            .map(Some)
+            .map_err(|_| format!("Неверная дата окончания '{}', нужен формат ГГГГ-ММ-ДД", expires))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expiry_date().ok().flatten().is_some_and(|date| date < today())
+    }
+
+    // Criteria that are set, for lists and logs
+    pub fn describe(&self) -> String {
+        let mut criteria = Vec::new();
+        if !self.rule.is_empty() {
+            criteria.push(format!("правило {}", self.rule));
+        }
+        if !self.sha256.is_empty() {
+            criteria.push(format!("SHA-256 {}", self.sha256));
+        }
+        if !self.path_pattern.is_empty() {
+            criteria.push(format!("путь {}", self.path_pattern));
+        }
+        criteria.join(", ")
+    }
+}
+
+// Active suppressions with their path patterns compiled
+struct Compiled<'a> {
+    suppression: &'a Suppression,
+    pattern: Option<Pattern>,
+}
+
+impl Compiled<'_> {
+    fn matches(&self, finding: &Finding) -> bool {
+        let s = self.suppression;
+        let rule_matches = s.rule.is_empty()
+            || finding.rule_id == s.rule
+            || finding.rule_id.rsplit_once(':').is_some_and(|(_, name)| name == s.rule);
+        let hash_matches = s.sha256.is_empty()
+            || finding.file.as_ref().is_some_and(|file| file.sha256.eq_ignore_ascii_case(s.sha256.trim()));
+        // Memory matches are matched by the executable of the process
+        let path = finding.process.as_ref().map_or(finding.target.as_str(), |process| process.exe.as_str());
+        let path_matches = self.pattern.as_ref().is_none_or(|pattern| pattern.matches(path.as_ref()));
+        rule_matches && hash_matches && path_matches
+    }
+}
+
+fn compile(suppressions: &[Suppression]) -> Vec<Compiled<'_>> {
+    suppressions
+        .iter()
+        .filter(|s| !s.is_expired())
+        // Entries broken by hand in the file never match
+        .filter(|s| s.validate().is_ok())
+        .map(|suppression| Compiled {
+            suppression,
+            pattern: walker::compile(std::slice::from_ref(&suppression.path_pattern)).ok().and_then(|mut patterns| patterns.pop()),
+        })
+        .collect()
+}
+
+// Findings to present without the suppressed YARA matches, and how many were suppressed
+pub fn apply(findings: &[Finding], suppressions: &[Suppression]) -> (Vec<Finding>, usize) {
+    let active = compile(suppressions);
+    let visible: Vec<Finding> = findings
+        .iter()
+        .filter(|f| f.engine != Engine::Yara || !active.iter().any(|s| s.matches(f)))
+        .cloned()
+        .collect();
+    let suppressed = findings.len() - visible.len();
+    (visible, suppressed)
+}
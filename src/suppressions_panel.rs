@@ -0,0 +1,148 @@
+// Suppressions window: false positive entries of YARA matches with their justification
+use crate::activity::ActivityLog;
+use crate::findings::Finding;
+use crate::scan::timestamp;
+use crate::suppressions::{self, Suppression};
+use eframe::egui;
+
+pub struct SuppressionsPanel {
+    suppressions: Vec<Suppression>,
+    // Entry being filled in before it is added
+    draft: Suppression,
+    message: String,
+    // Bumped on every change of the list, so cached results can tell it changed
+    revision: u64,
+}
+
+impl Default for SuppressionsPanel {
+    fn default() -> Self {
+        let mut message = String::new();
+        let suppressions = suppressions::load().unwrap_or_else(|e| {
+            message = e;
+            Vec::new()
+        });
+
+        Self {
+            suppressions,
+            draft: Suppression::default(),
+            message,
+            revision: 0,
+        }
+    }
+}
+
+impl SuppressionsPanel {
+    pub fn list(&self) -> &[Suppression] {
+        &self.suppressions
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn save(&mut self) {
+        self.revision += 1;
+        if let Err(e) = suppressions::save(&self.suppressions) {
+            self.message = e;
+        }
+    }
+
+    // Start an entry for a match marked as a false positive in the results view
+    pub fn propose(&mut self, finding: &Finding) {
+        self.draft = Suppression::for_finding(finding);
+        self.message = "Укажите обоснование и срок действия, затем нажмите «Добавить»".to_string();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, activity: &ActivityLog) {
+        egui::Grid::new("suppression_draft").num_columns(2).show(ui, |ui| {
+            ui.label("Правило:");
+            ui.add(egui::TextEdit::singleline(&mut self.draft.rule).hint_text("любое").desired_width(400.0));
+            ui.end_row();
+            ui.label("SHA-256 файла:");
+            ui.add(egui::TextEdit::singleline(&mut self.draft.sha256).hint_text("любой").desired_width(400.0));
+            ui.end_row();
+            ui.label("Шаблон пути:");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.draft.path_pattern)
+                    .hint_text("/opt/app/**/*.js или *.dll")
+                    .desired_width(400.0),
+            );
+            ui.end_row();
+            ui.label("Действует до:");
+            ui.add(egui::TextEdit::singleline(&mut self.draft.expires).hint_text("ГГГГ-ММ-ДД, пусто — бессрочно").desired_width(200.0));
+            ui.end_row();
+            ui.label("Обоснование:");
+            ui.add(egui::TextEdit::multiline(&mut self.draft.justification).desired_rows(2).desired_width(400.0));
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Добавить").clicked() {
+                let mut suppression = self.draft.clone();
+                suppression.sha256 = suppression.sha256.trim().to_lowercase();
+                suppression.path_pattern = suppression.path_pattern.trim().to_string();
+                suppression.rule = suppression.rule.trim().to_string();
+                suppression.created_at = timestamp();
+                match suppression.validate() {
+                    Ok(()) => {
+                        self.message = format!("Добавлено подавление: {}", suppression.describe());
+                        activity.push(format!("{} ({})", self.message, suppression.justification.trim()));
+                        self.suppressions.push(suppression);
+                        self.draft = Suppression::default();
+                        self.save();
+                    }
+                    Err(e) => self.message = e,
+                }
+            }
+            if ui.button("Очистить форму").clicked() {
+                self.draft = Suppression::default();
+            }
+            let expired = self.suppressions.iter().filter(|s| s.is_expired()).count();
+            if ui.add_enabled(expired > 0, egui::Button::new(format!("Удалить истекшие ({})", expired))).clicked() {
+                self.suppressions.retain(|s| !s.is_expired());
+                self.message = format!("Удалено истекших подавлений: {}", expired);
+                activity.push(self.message.clone());
+                self.save();
+            }
+        });
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+
+        ui.separator();
+        if self.suppressions.is_empty() {
+            ui.label("Подавлений нет");
+            return;
+        }
+        let mut remove = None;
+        egui::Grid::new("suppressions").striped(true).num_columns(5).show(ui, |ui| {
+            ui.strong("Условия");
+            ui.strong("Действует до");
+            ui.strong("Обоснование");
+            ui.strong("Добавлено");
+            ui.end_row();
+            for (index, suppression) in self.suppressions.iter().enumerate() {
+                ui.add(egui::Label::new(egui::RichText::new(suppression.describe()).monospace()).wrap(true));
+                if suppression.is_expired() {
+                    ui.colored_label(egui::Color32::RED, format!("{} (истекло)", suppression.expires));
+                } else if suppression.expires.is_empty() {
+                    ui.label("бессрочно");
+                } else {
+                    ui.label(&suppression.expires);
+                }
+                ui.add(egui::Label::new(&suppression.justification).wrap(true));
+                ui.label(&suppression.created_at);
+                if ui.small_button("✖").clicked() {
+                    remove = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(index) = remove {
+            let removed = self.suppressions.remove(index);
+            self.message = format!("Удалено подавление: {}", removed.describe());
+            activity.push(self.message.clone());
+            self.save();
+        }
+    }
+}
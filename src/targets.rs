@@ -0,0 +1,82 @@
+// Scan targets of YARA jobs: several files and folders per scan, lists imported from
+// text files and named presets kept between runs
+use crate::findings::{Finding, TargetResult};
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const PRESETS_FILE: &str = "target-presets.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TargetPreset {
+    pub name: String,
+    pub targets: Vec<String>,
+}
+
+pub fn load_presets() -> Result<Vec<TargetPreset>, String> {
+    storage::load(PRESETS_FILE)
+}
+
+pub fn save_presets(presets: &[TargetPreset]) -> Result<(), String> {
+    storage::save(PRESETS_FILE, &presets)
+}
+
+// One path per line, blank lines and "#" comments are skipped
+pub fn parse_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn import_list(path: &Path) -> Result<Vec<String>, String> {
+    std::fs::read_to_string(path)
+        .map(|text| parse_list(&text))
+        .map_err(|e| format!("Не удалось прочитать список целей {}: {}", path.display(), e))
+}
+
+// "/var/www/" and "/var/www" are the same target
+fn normalize(path: &str) -> String {
+    let trimmed = path.trim();
+    match trimmed.trim_end_matches('/') {
+        "" if trimmed.starts_with('/') => "/".to_string(),
+        stripped => stripped.to_string(),
+    }
+}
+
+// Appends the paths that are not in the list yet, returns how many were added
+pub fn add(targets: &mut Vec<String>, paths: impl IntoIterator<Item = String>) -> usize {
+    let mut added = 0;
+    for path in paths {
+        let path = normalize(&path);
+        if !path.is_empty() && !targets.contains(&path) {
+            targets.push(path);
+            added += 1;
+        }
+    }
+    added
+}
+
+// Target a scanned file came from, the most specific one when targets are nested
+pub fn owner<'a>(targets: &'a [String], file: &str) -> Option<&'a str> {
+    targets
+        .iter()
+        .filter(|target| Path::new(file).starts_with(target.as_str()))
+        .max_by_key(|target| target.len())
+        .map(String::as_str)
+}
+
+// Matches of every target from the findings of the scan
+pub fn count_matches(results: &mut [TargetResult], findings: &[Finding]) {
+    let paths: Vec<String> = results.iter().map(|result| result.path.clone()).collect();
+    for finding in findings {
+        // Matches inside an archive belong to the target of the archive
+        let file = finding.archive.as_ref().map_or(&finding.target, |member| &member.archive);
+        if let Some(target) = owner(&paths, file) {
+            if let Some(result) = results.iter_mut().find(|result| result.path == target) {
+                result.matches += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// Scan target list of the YARA panel with the saved target presets
+use crate::activity::ActivityLog;
+use crate::targets::{self, TargetPreset};
+use eframe::egui;
+use rfd::FileDialog;
+use std::path::Path;
+
+pub struct TargetsPanel {
+    presets: Vec<TargetPreset>,
+    // Index of the preset selected in the combo box
+    preset: Option<usize>,
+    preset_name: String,
+    // Path typed by hand
+    manual: String,
+    message: String,
+}
+
+impl Default for TargetsPanel {
+    fn default() -> Self {
+        let mut message = String::new();
+        let presets = targets::load_presets().unwrap_or_else(|e| {
+            message = e;
+            Vec::new()
+        });
+
+        Self {
+            presets,
+            preset: None,
+            preset_name: String::new(),
+            manual: String::new(),
+            message,
+        }
+    }
+}
+
+impl TargetsPanel {
+    fn save(&mut self) {
+        if let Err(e) = targets::save_presets(&self.presets) {
+            self.message = e;
+        }
+    }
+
+    fn add(&mut self, list: &mut Vec<String>, paths: Vec<String>, activity: &ActivityLog) {
+        let added = targets::add(list, paths);
+        self.message = format!("Добавлено целей: {}, всего: {}", added, list.len());
+        activity.push(format!("Цели сканирования YARA: {}", self.message));
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, list: &mut Vec<String>, activity: &ActivityLog) {
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Добавить файлы").clicked() {
+                if let Some(paths) = FileDialog::new().set_title("Выберите файлы для сканирования").pick_files() {
+                    let paths = paths.iter().map(|path| path.to_string_lossy().to_string()).collect();
+                    self.add(list, paths, activity);
+                }
+            }
+            if ui.button("Добавить папку").clicked() {
+                if let Some(path) = FileDialog::new().set_title("Выберите папку для сканирования").pick_folder() {
+                    self.add(list, vec![path.to_string_lossy().to_string()], activity);
+                }
+            }
+            if ui.button("Импорт списка из файла").clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("Текстовые файлы", &["txt", "lst", "list"])
+                    .add_filter("Все файлы", &["*"])
+                    .set_title("Список целей, по одному пути в строке")
+                    .pick_file()
+                {
+                    match targets::import_list(&path) {
+                        Ok(paths) => self.add(list, paths, activity),
+                        Err(e) => self.message = e,
+                    }
+                }
+            }
+            if ui.add_enabled(!list.is_empty(), egui::Button::new("Очистить список")).clicked() {
+                list.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.manual)
+                    .hint_text("/var/www")
+                    .desired_width(300.0),
+            );
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("Добавить путь").clicked() || entered) && !self.manual.trim().is_empty() {
+                let path = std::mem::take(&mut self.manual);
+                self.add(list, vec![path], activity);
+            }
+        });
+
+        if list.is_empty() {
+            ui.label("Цели сканирования не выбраны");
+        } else {
+            let mut remove = None;
+            egui::Grid::new("yara_targets").striped(true).show(ui, |ui| {
+                for (index, target) in list.iter().enumerate() {
+                    ui.label(target);
+                    let path = Path::new(target);
+                    if path.is_dir() {
+                        ui.label("папка");
+                    } else if path.exists() {
+                        ui.label("файл");
+                    } else {
+                        ui.colored_label(egui::Color32::RED, "не найден");
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(index) = remove {
+                list.remove(index);
+            }
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Набор целей:");
+            let current = self
+                .preset
+                .and_then(|index| self.presets.get(index))
+                .map(|preset| format!("{} ({})", preset.name, preset.targets.len()))
+                .unwrap_or_else(|| "не выбран".to_string());
+            egui::ComboBox::from_id_source("yara_target_preset")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for (index, preset) in self.presets.iter().enumerate() {
+                        ui.selectable_value(&mut self.preset, Some(index), format!("{} ({})", preset.name, preset.targets.len()));
+                    }
+                });
+            if let Some(index) = self.preset.filter(|index| *index < self.presets.len()) {
+                if ui.button("Загрузить").clicked() {
+                    let preset = &self.presets[index];
+                    *list = preset.targets.clone();
+                    self.preset_name = preset.name.clone();
+                    self.message = format!("Загружен набор целей {}", preset.name);
+                    activity.push(self.message.clone());
+                }
+                if ui.button("Удалить набор").clicked() {
+                    self.presets.remove(index);
+                    self.preset = None;
+                    self.save();
+                }
+            }
+
+            ui.add(egui::TextEdit::singleline(&mut self.preset_name).hint_text("Имя набора").desired_width(150.0));
+            if ui.add_enabled(!list.is_empty(), egui::Button::new("Сохранить как набор")).clicked() {
+                let name = self.preset_name.trim().to_string();
+                if name.is_empty() {
+                    self.message = "Введите имя набора целей".to_string();
+                } else {
+                    // Saving under an existing name replaces that preset
+                    let preset = TargetPreset {
+                        name: name.clone(),
+                        targets: list.clone(),
+                    };
+                    match self.presets.iter().position(|preset| preset.name == name) {
+                        Some(index) => {
+                            self.presets[index] = preset;
+                            self.preset = Some(index);
+                        }
+                        None => {
+                            self.presets.push(preset);
+                            self.preset = Some(self.presets.len() - 1);
+                        }
+                    }
+                    self.message = format!("Набор целей {} сохранен", name);
+                    self.save();
+                }
+            }
+        });
+
+        if !self.message.is_empty() {
+            ui.label(&self.message);
+        }
+    }
+}
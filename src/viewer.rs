@@ -0,0 +1,218 @@
+// Report viewer rendered inside the egui window, without an external browser
+use crate::exceptions;
+use crate::findings::{Engine, Finding, ScanRun};
+use crate::report::{oval_by_risk, package_versions, severity_color, severity_rank};
+use eframe::egui;
+
+fn severity_label(ui: &mut egui::Ui, severity: &str) {
+    let (r, g, b) = severity_color(severity);
+    let text = if severity.is_empty() { "Не указана" } else { severity };
+    ui.colored_label(egui::Color32::from_rgb(r, g, b), text);
+}
+
+fn references(ui: &mut egui::Ui, finding: &Finding) {
+    ui.horizontal_wrapped(|ui| {
+        for reference in &finding.references {
+            match finding.reference_url(reference) {
+                Some(url) => {
+                    ui.hyperlink_to(reference, url);
+                }
+                None => {
+                    ui.label(reference);
+                }
+            }
+        }
+    });
+}
+
+// Actions on a finding requested from the results view
+pub enum ViewerAction {
+    // Add a YARA match to the suppressions
+    FalsePositive(Finding),
+    // Accept the risk of an OVAL finding
+    AcceptRisk(Finding),
+}
+
+pub fn show(ui: &mut egui::Ui, findings: &[Finding], runs: &[ScanRun], suppressed: usize) -> Option<ViewerAction> {
+    let mut action = None;
+    ui.heading("Сводка");
+
+    if runs.is_empty() {
+        ui.label("Проверки не выполнялись");
+    }
+    for run in runs {
+        ui.label(format!("{} — {}", run.engine.tool_name(), run.started_at));
+        ui.add(egui::Label::new(egui::RichText::new(&run.command).monospace()).wrap(true));
+        if !run.content_version.is_empty() {
+            ui.label(format!("Содержимое: {}", run.content_version));
+        }
+        if !run.targets.is_empty() {
+            egui::Grid::new(("scan_targets", &run.started_at))
+                .striped(true)
+                .num_columns(4)
+                .show(ui, |ui| {
+                    ui.strong("Цель");
+                    ui.strong("Файлов");
+                    ui.strong("Совпадений");
+                    ui.strong("Состояние");
+                    ui.end_row();
+                    for target in &run.targets {
+                        ui.label(egui::RichText::new(&target.path).monospace());
+                        ui.label(target.files.to_string());
+                        ui.label(target.matches.to_string());
+                        match target.error {
+                            None => ui.label("просканирована"),
+                            Some(error) => ui.colored_label(egui::Color32::RED, error.label()),
+                        };
+                        ui.end_row();
+                    }
+                });
+        }
+    }
+
+    let oval = oval_by_risk(findings);
+    let accepted = exceptions::accepted(findings);
+    let yara: Vec<&Finding> = findings.iter().filter(|f| f.engine == Engine::Yara).collect();
+
+    // Counts per severity in the order of criticality
+    let mut by_severity: Vec<(&str, usize)> = Vec::new();
+    for f in &oval {
+        match by_severity.iter_mut().find(|(severity, _)| *severity == f.severity) {
+            Some((_, count)) => *count += 1,
+            None => by_severity.push((&f.severity, 1)),
+        }
+    }
+    by_severity.sort_by_key(|(severity, _)| severity_rank(severity));
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(format!("Уязвимостей OVAL: {}", oval.len()));
+        for (severity, count) in &by_severity {
+            severity_label(ui, severity);
+            ui.label(format!("{}", count));
+        }
+        if !accepted.is_empty() {
+            ui.label(format!("принятых рисков: {}", accepted.len()));
+        }
+    });
+    if suppressed > 0 {
+        ui.label(format!("Срабатываний YARA: {}, скрыто как ложные: {}", yara.len(), suppressed));
+    } else {
+        ui.label(format!("Срабатываний YARA: {}", yara.len()));
+    }
+
+    ui.separator();
+
+    egui::CollapsingHeader::new(format!("Уязвимости OVAL ({})", oval.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            if oval.is_empty() {
+                ui.label("Нет находок");
+                return;
+            }
+            egui::Grid::new("oval_findings")
+                .striped(true)
+                .num_columns(7)
+                .show(ui, |ui| {
+                    ui.strong("Определение");
+                    ui.strong("Название");
+                    ui.strong("Критичность");
+                    ui.strong("CVSS");
+                    ui.strong("Пакеты (установлено → исправлено)");
+                    ui.strong("Ссылки");
+                    ui.end_row();
+
+                    for f in &oval {
+                        ui.monospace(&f.rule_id);
+                        ui.add(egui::Label::new(&f.title).wrap(true));
+                        severity_label(ui, &f.severity);
+                        match f.cvss_score() {
+                            Some(score) => ui.label(format!("{:.1}", score)),
+                            None => ui.label("—"),
+                        };
+                        ui.add(egui::Label::new(egui::RichText::new(package_versions(f)).monospace()).wrap(true));
+                        references(ui, f);
+                        if ui.small_button("Принять риск").on_hover_text("Добавить исключение для определения").clicked() {
+                            action = Some(ViewerAction::AcceptRisk((*f).clone()));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+    if !accepted.is_empty() {
+        egui::CollapsingHeader::new(format!("Принятые риски ({})", accepted.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("accepted_findings")
+                    .striped(true)
+                    .num_columns(6)
+                    .show(ui, |ui| {
+                        ui.strong("Определение");
+                        ui.strong("Название");
+                        ui.strong("Критичность");
+                        ui.strong("Ответственный");
+                        ui.strong("Причина");
+                        ui.strong("Действует до");
+                        ui.end_row();
+
+                        for (f, exception) in accepted.iter().filter_map(|f| Some((f, f.exception.as_ref()?))) {
+                            ui.monospace(&f.rule_id);
+                            ui.add(egui::Label::new(&f.title).wrap(true));
+                            severity_label(ui, &f.severity);
+                            ui.label(&exception.owner);
+                            ui.add(egui::Label::new(&exception.reason).wrap(true));
+                            ui.label(&exception.expires);
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    egui::CollapsingHeader::new(format!("Срабатывания YARA ({})", yara.len()))
+        .default_open(true)
+        .show(ui, |ui| {
+            if yara.is_empty() {
+                ui.label("Нет находок");
+                return;
+            }
+            egui::Grid::new("yara_findings")
+                .striped(true)
+                .num_columns(5)
+                .show(ui, |ui| {
+                    ui.strong("Правило");
+                    ui.strong("Файл");
+                    ui.strong("Теги");
+                    ui.strong("SHA-256 и пакет");
+                    ui.end_row();
+
+                    for f in &yara {
+                        ui.monospace(&f.rule_id);
+                        let target = ui.add(egui::Label::new(egui::RichText::new(&f.target).monospace()).wrap(true));
+                        if let Some(process) = &f.process {
+                            target.on_hover_text(format!("Пользователь: {}\nКомандная строка: {}", process.user, process.command_line));
+                        } else if let Some(member) = &f.archive {
+                            target.on_hover_text(format!("Архив: {}\nПуть в архиве: {}", member.archive, member.member));
+                        }
+                        ui.label(f.tags.join(", "));
+                        match &f.file {
+                            Some(file) if file.error.is_none() => {
+                                let status = egui::RichText::new(format!("{}…\n{}", &file.sha256[..16], file.package_status()));
+                                let status = if file.is_modified_package_file() { status.color(egui::Color32::RED) } else { status };
+                                ui.label(status).on_hover_text(file.describe());
+                            }
+                            Some(file) => {
+                                ui.label(file.describe());
+                            }
+                            None => {
+                                ui.label("—");
+                            }
+                        }
+                        if ui.small_button("Ложное срабатывание").on_hover_text("Добавить в подавления YARA").clicked() {
+                            action = Some(ViewerAction::FalsePositive((*f).clone()));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    action
+}
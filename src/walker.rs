@@ -0,0 +1,292 @@
+// File walker for YARA directory scans. The filters are applied here rather than by yara -r,
+// so the CLI and the embedded engine see the same files.
+use crate::archives::{self, Unpacked};
+use crate::findings::{TargetError, TargetResult};
+use crate::scan::{OutputStream, ScanMonitor, YaraOptions, CANCELLED};
+use crate::targets;
+use globset::{Glob, GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanFilters {
+    // Glob patterns, one per entry. Patterns with "/" match the full path, others the file name.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    // MIME types to skip, "video/*" matches the whole group
+    pub skip_mime: Vec<String>,
+    // Stay on the file system of the scan path
+    pub one_filesystem: bool,
+    pub skip_hidden: bool,
+}
+
+impl Default for ScanFilters {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: vec!["/proc".to_string(), "/sys".to_string(), "/dev".to_string()],
+            skip_mime: Vec::new(),
+            one_filesystem: false,
+            skip_hidden: false,
+        }
+    }
+}
+
+pub struct Pattern {
+    matcher: GlobMatcher,
+    full_path: bool,
+}
+
+impl Pattern {
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.full_path {
+            self.matcher.is_match(path)
+        } else {
+            path.file_name().is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+}
+
+pub fn compile(patterns: &[String]) -> Result<Vec<Pattern>, String> {
+    patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| {
+            let full_path = pattern.contains('/');
+            // "*" stays within one path component, "**" crosses them
+            let glob = if full_path {
+                GlobBuilder::new(pattern).literal_separator(true).build()
+            } else {
+                Glob::new(pattern)
+            };
+            glob.map(|glob| Pattern {
+                matcher: glob.compile_matcher(),
+                full_path,
+            })
+            .map_err(|e| format!("Неверный шаблон '{}': {}", pattern, e))
+        })
+        .collect()
+}
+
+impl ScanFilters {
+    pub fn validate(&self) -> Result<(), String> {
+        compile(&self.include)?;
+        compile(&self.exclude)?;
+        Ok(())
+    }
+}
+
+// MIME type from the file signature, None when it is not recognised
+pub fn detect_mime(path: &Path) -> Option<&'static str> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut head = [0u8; 0x8006];
+    let mut len = 0;
+    while len < head.len() {
+        match file.read(&mut head[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => len += read,
+        }
+    }
+    let head = &head[..len];
+
+    let signatures: [(&[u8], &str); 17] = [
+        (b"\x7fELF", "application/x-executable"),
+        (b"MZ", "application/x-dosexec"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"BZh", "application/x-bzip2"),
+        (b"\xfd7zXZ\x00", "application/x-xz"),
+        (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (b"\xed\xab\xee\xdb", "application/x-rpm"),
+        (b"%PDF", "application/pdf"),
+        (b"\x89PNG", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+        (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+        (b"!<arch>\n", "application/x-archive"),
+    ];
+    if let Some((_, mime)) = signatures.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return Some(mime);
+    }
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return Some("video/mp4");
+    }
+    if head.len() >= 12 && head.starts_with(b"RIFF") {
+        return match &head[8..12] {
+            b"AVI " => Some("video/x-msvideo"),
+            b"WAVE" => Some("audio/x-wav"),
+            _ => None,
+        };
+    }
+    if head.len() >= 0x8006 && &head[0x8001..0x8006] == b"CD001" {
+        return Some("application/x-iso9660-image");
+    }
+    None
+}
+
+fn mime_matches(mime: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    match pattern.strip_suffix("/*") {
+        Some(group) => mime.split('/').next() == Some(group),
+        None => mime == pattern,
+    }
+}
+
+pub struct WalkResult {
+    pub files: Vec<PathBuf>,
+    // Files and directories left out by the filters
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+// Files under the scan path that pass the filters, sorted
+pub fn walk(root: &Path, options: &YaraOptions, monitor: &dyn ScanMonitor) -> Result<WalkResult, String> {
+    let filters = &options.filters;
+    let include = compile(&filters.include)?;
+    let exclude = compile(&filters.exclude)?;
+    let skip_mime: Vec<&String> = filters.skip_mime.iter().filter(|mime| !mime.trim().is_empty()).collect();
+    let root_device = std::fs::metadata(root).map_err(|e| format!("{}: {}", root.display(), e))?.dev();
+
+    let mut result = WalkResult {
+        files: Vec::new(),
+        skipped: 0,
+        errors: Vec::new(),
+    };
+    // Directories already entered, guards against symlink loops
+    let mut visited = HashSet::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        if monitor.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        let metadata = match std::fs::metadata(&dir) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                result.errors.push(format!("{}: {}", dir.display(), e));
+                continue;
+            }
+        };
+        if !visited.insert((metadata.dev(), metadata.ino())) {
+            continue;
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                result.errors.push(format!("{}: {}", dir.display(), e));
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if (file_type.is_symlink() && options.no_follow_symlinks)
+                || (hidden && filters.skip_hidden)
+                || exclude.iter().any(|pattern| pattern.matches(&path))
+            {
+                result.skipped += 1;
+                continue;
+            }
+
+            // Symlinks are resolved here, when they are followed
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            if filters.one_filesystem && metadata.dev() != root_device {
+                result.skipped += 1;
+                continue;
+            }
+
+            if metadata.is_dir() {
+                if options.recursive {
+                    pending.push(path);
+                }
+                continue;
+            }
+            if !metadata.is_file() {
+                continue;
+            }
+
+            if (!include.is_empty() && !include.iter().any(|pattern| pattern.matches(&path)))
+                || (options.max_file_size > 0 && metadata.len() > options.max_file_size)
+                || (!skip_mime.is_empty()
+                    && detect_mime(&path).is_some_and(|mime| skip_mime.iter().any(|pattern| mime_matches(mime, pattern))))
+            {
+                result.skipped += 1;
+                continue;
+            }
+            result.files.push(path);
+        }
+    }
+
+    result.files.sort();
+    Ok(result)
+}
+
+pub struct TargetWalk {
+    // Files of all targets without duplicates, sorted, then the files unpacked from archives
+    pub files: Vec<PathBuf>,
+    pub skipped: usize,
+    pub targets: Vec<TargetResult>,
+    // Kept until the matches are located, the unpacked files are removed with it
+    pub archives: Unpacked,
+}
+
+// Walk every scan target. A missing target is recorded in its result, the others are still scanned.
+pub fn walk_targets(targets: &[String], options: &YaraOptions, monitor: &dyn ScanMonitor) -> Result<TargetWalk, String> {
+    let mut result = TargetWalk {
+        files: Vec::new(),
+        skipped: 0,
+        targets: Vec::new(),
+        archives: Unpacked::default(),
+    };
+    for target in targets {
+        let path = Path::new(target);
+        let mut target_result = TargetResult {
+            path: target.clone(),
+            ..Default::default()
+        };
+        if path.is_dir() {
+            let walked = walk(path, options, monitor)?;
+            for error in &walked.errors {
+                monitor.output(OutputStream::Stderr, error);
+            }
+            result.skipped += walked.skipped;
+            result.files.extend(walked.files);
+        } else if path.exists() {
+            // A file given explicitly is scanned regardless of the filters
+            result.files.push(path.to_path_buf());
+        } else {
+            target_result.error = Some(TargetError::NotFound);
+            monitor.output(OutputStream::Stderr, &format!("Цель сканирования не найдена: {}", target));
+        }
+        result.targets.push(target_result);
+    }
+
+    // Nested targets such as /var and /var/www share files, each file counts for the most specific one
+    result.files.sort();
+    result.files.dedup();
+    let paths: Vec<String> = result.targets.iter().map(|target| target.path.clone()).collect();
+    for file in &result.files {
+        if let Some(owner) = targets::owner(&paths, &file.to_string_lossy()) {
+            if let Some(target) = result.targets.iter_mut().find(|target| target.path == owner) {
+                target.files += 1;
+            }
+        }
+    }
+    result.archives = archives::unpack(&result.files, options, monitor)?;
+    result.files.extend(result.archives.files.iter().cloned());
+    Ok(result)
+}
@@ -0,0 +1,200 @@
+// In-process YARA scanning with YARA-X, enabled by the `embedded-yara` feature.
+// Rules are compiled once per scan and every file is matched without a subprocess,
+// so progress, cancellation and match data come straight from the engine.
+use crate::evidence;
+use crate::findings::{Engine, Finding, ScanRun};
+use crate::pool::{self, ProgressTracker};
+use crate::rules::RuleFile;
+use crate::scan::{target_notes, timestamp, OutputStream, ScanMonitor, ScanOutcome, YaraOptions};
+use crate::targets;
+use crate::walker;
+use std::path::Path;
+use std::time::Duration;
+use yara_x::{Compiler, MetaValue, Rules, ScanError, Scanner, SourceCode};
+
+// External variables keep the type their value looks like
+fn define(compiler: &mut Compiler, name: &str, value: &str) -> Result<(), String> {
+    let result = if let Ok(number) = value.parse::<i64>() {
+        compiler.define_global(name, number)
+    } else if let Ok(number) = value.parse::<f64>() {
+        compiler.define_global(name, number)
+    } else if let Ok(flag) = value.parse::<bool>() {
+        compiler.define_global(name, flag)
+    } else {
+        compiler.define_global(name, value)
+    };
+    result.map(|_| ()).map_err(|e| format!("Внешняя переменная {}: {}", name, e))
+}
+
+fn meta_text(value: &MetaValue) -> String {
+    match value {
+        MetaValue::Integer(number) => number.to_string(),
+        MetaValue::Float(number) => number.to_string(),
+        MetaValue::Bool(flag) => flag.to_string(),
+        MetaValue::String(text) => format!("\"{}\"", text),
+        MetaValue::Bytes(bytes) => format!("\"{}\"", bytes.escape_ascii()),
+    }
+}
+
+fn compile(options: &YaraOptions, rules: &[RuleFile]) -> Result<Rules, String> {
+    let mut compiler = Compiler::new();
+    for variable in &options.defines {
+        define(&mut compiler, &variable.name, &variable.value)?;
+    }
+    for rule in rules.iter().filter(|rule| rule.enabled) {
+        let source = std::fs::read_to_string(&rule.path)
+            .map_err(|e| format!("Не удалось прочитать файл правил {}: {}", rule.path, e))?;
+        compiler.new_namespace(if rule.namespace.is_empty() { "default" } else { &rule.namespace });
+        compiler
+            .add_source(SourceCode::from(source.as_str()).with_origin(&rule.path))
+            .map_err(|e| format!("Ошибка компиляции {}:\n{}", rule.path, e))?;
+    }
+    Ok(compiler.build())
+}
+
+// Outcome of scanning one file
+#[derive(Default)]
+struct FileResult {
+    findings: Vec<Finding>,
+    skipped: bool,
+    error: bool,
+}
+
+fn scan_file(scanner: &mut Scanner, options: &YaraOptions, target: &Path, monitor: &dyn ScanMonitor) -> FileResult {
+    let mut result = FileResult::default();
+    if options.max_file_size > 0 && target.metadata().map_or(0, |meta| meta.len()) > options.max_file_size {
+        result.skipped = true;
+        return result;
+    }
+
+    let results = match scanner.scan_file(target) {
+        Ok(results) => results,
+        Err(ScanError::Timeout) => {
+            result.error = true;
+            monitor.output(OutputStream::Stderr, &format!("Превышено время сканирования: {}", target.display()));
+            return result;
+        }
+        Err(e) => {
+            result.error = true;
+            if !options.no_warnings {
+                monitor.output(OutputStream::Stderr, &format!("{}: {}", target.display(), e));
+            }
+            return result;
+        }
+    };
+
+    for rule in results.matching_rules() {
+        if !options.identifier.is_empty() && rule.identifier() != options.identifier {
+            continue;
+        }
+        if options.max_rules > 0 && result.findings.len() >= options.max_rules as usize {
+            break;
+        }
+
+        let rule_id = if options.print_namespace {
+            format!("{}:{}", rule.namespace(), rule.identifier())
+        } else {
+            rule.identifier().to_string()
+        };
+        let tags: Vec<String> = rule.tags().map(|tag| tag.identifier().to_string()).collect();
+        let meta: Vec<String> = rule.metadata().map(|(name, value)| format!("{}={}", name, meta_text(&value))).collect();
+        // Lines of one match are sent together so parallel workers do not interleave them
+        let mut lines = vec![format!("{} [{}] [{}] {}", rule_id, tags.join(","), meta.join(","), target.display())];
+        if options.print_strings {
+            for pattern in rule.patterns() {
+                for found in pattern.matches() {
+                    lines.push(format!("0x{:x}:{}: {}", found.range().start, pattern.identifier(), found.data().escape_ascii()));
+                }
+            }
+        }
+        monitor.output(OutputStream::Stdout, &lines.join("\n"));
+
+        result.findings.push(Finding {
+            engine: Engine::Yara,
+            title: if meta.is_empty() { rule_id.clone() } else { meta.join(",") },
+            rule_id,
+            severity: String::new(),
+            target: target.display().to_string(),
+            references: Vec::new(),
+            urls: Vec::new(),
+            tags,
+            cves: Vec::new(),
+            packages: Vec::new(),
+            process: None,
+            file: None,
+            archive: None,
+            exception: None,
+        });
+    }
+    result
+}
+
+pub fn run(options: &YaraOptions, rules: &[RuleFile], scan_targets: &[String], monitor: &dyn ScanMonitor) -> Result<ScanOutcome, String> {
+    let started_at = timestamp();
+    let compiled = compile(options, rules)?;
+
+    let walked = walker::walk_targets(scan_targets, options, monitor)?;
+    let targets = &walked.files;
+    let sizes: Vec<u64> = targets.iter().map(|target| target.metadata().map_or(0, |meta| meta.len())).collect();
+    let workers = options.workers.max(1) as usize;
+    let tracker = ProgressTracker::new(monitor, "Файлов", targets.len(), sizes.iter().sum());
+
+    // Every worker thread has its own scanner over the shared compiled rules
+    let indices: Vec<usize> = (0..targets.len()).collect();
+    let results = pool::run_pool(
+        &indices,
+        workers,
+        || {
+            let mut scanner = Scanner::new(&compiled);
+            if options.timeout > 0 {
+                scanner.set_timeout(Duration::from_secs(options.timeout.into()));
+            }
+            scanner
+        },
+        |scanner, index| {
+            tracker.checkpoint()?;
+            let target = &targets[*index];
+            tracker.start(&target.display().to_string());
+            let result = scan_file(scanner, options, target, monitor);
+            tracker.finish(1, sizes[*index]);
+            Ok(result)
+        },
+    )?;
+    let progress = tracker.done();
+
+    let skipped = results.iter().filter(|result| result.skipped).count();
+    let errors = results.iter().filter(|result| result.error).count();
+    let mut findings: Vec<Finding> = results.into_iter().flat_map(|result| result.findings).collect();
+    walked.archives.locate(&mut findings);
+    let evidence_notes = evidence::attach(&mut findings, monitor)?;
+
+    let mut notes = vec![
+        format!("Совпадений: {}", findings.len()),
+        format!("Проверено файлов: {}, исключено фильтрами: {}", targets.len() - skipped, walked.skipped),
+        format!("Потоков сканирования: {}. {}", workers.min(targets.len()).max(1), progress.summary()),
+    ];
+    let mut target_results = walked.targets;
+    targets::count_matches(&mut target_results, &findings);
+    notes.extend(target_notes(&target_results));
+    if skipped > 0 {
+        notes.push(format!("Пропущено по размеру: {}", skipped));
+    }
+    if errors > 0 {
+        notes.push(format!("Ошибок сканирования: {}", errors));
+    }
+    notes.extend(walked.archives.notes.iter().cloned());
+    notes.extend(evidence_notes);
+    let rule_paths: Vec<&str> = rules.iter().filter(|rule| rule.enabled).map(|rule| rule.path.as_str()).collect();
+    Ok(ScanOutcome {
+        notes,
+        findings,
+        complete: errors == 0,
+        run: ScanRun {
+            engine: Engine::Yara,
+            started_at,
+            command: format!("YARA-X {} (встроенный движок)", scan_targets.join(" ")),
+            content_version: rule_paths.join(", "),
+            targets: target_results,
+        },
+    })
+}
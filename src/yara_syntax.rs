@@ -0,0 +1,318 @@
+// Lightweight reader of YARA source: tokens and the outline of rules, without a full grammar.
+// Regular expressions are recognised after "=" and "matches", where a "/" cannot be a division.
+
+pub const KEYWORDS: [&str; 51] = [
+    "all", "and", "any", "ascii", "at", "base64", "base64wide", "condition", "contains", "defined", "endswith",
+    "entrypoint", "false", "filesize", "for", "fullword", "global", "icontains", "iendswith", "iequals", "import",
+    "in", "include", "int16", "int16be", "int32", "int32be", "int8", "int8be", "istartswith", "matches", "meta",
+    "nocase", "none", "not", "of", "or", "private", "rule", "startswith", "strings", "them", "true", "uint16",
+    "uint16be", "uint32", "uint32be", "uint8", "uint8be", "wide", "xor",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Word,
+    Str,
+    Comment,
+    // Regular expression with its modifiers: /ab{2}c/is
+    Regex,
+    Punct,
+}
+
+#[derive(Clone, Copy)]
+pub struct Token {
+    pub kind: TokenKind,
+    // Byte range in the source
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let bytes = text.as_bytes();
+    let len = bytes.len();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let mut line = 1;
+    // Last token other than a comment
+    let mut previous: Option<Token> = None;
+
+    while pos < len {
+        let start = pos;
+        let start_line = line;
+        let regex_allowed = previous.is_some_and(|token| {
+            let source = &text[token.start..token.end];
+            (token.kind == TokenKind::Punct && source == "=") || (token.kind == TokenKind::Word && source == "matches")
+        });
+        let kind = match bytes[pos] {
+            b'\n' => {
+                line += 1;
+                pos += 1;
+                continue;
+            }
+            c if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                while pos < len && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                TokenKind::Comment
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                pos += 2;
+                while pos < len && !(bytes[pos] == b'*' && bytes.get(pos + 1) == Some(&b'/')) {
+                    if bytes[pos] == b'\n' {
+                        line += 1;
+                    }
+                    pos += 1;
+                }
+                pos = (pos + 2).min(len);
+                TokenKind::Comment
+            }
+            // The body ends at an unescaped "/", braces and quotes inside it are not tokens
+            b'/' if regex_allowed => {
+                pos += 1;
+                while pos < len && bytes[pos] != b'/' && bytes[pos] != b'\n' {
+                    if bytes[pos] == b'\\' && bytes.get(pos + 1).is_some_and(|c| *c != b'\n') {
+                        pos += 1;
+                    }
+                    pos += 1;
+                }
+                if pos < len && bytes[pos] == b'/' {
+                    pos += 1;
+                    while pos < len && bytes[pos].is_ascii_alphabetic() {
+                        pos += 1;
+                    }
+                }
+                TokenKind::Regex
+            }
+            b'"' => {
+                pos += 1;
+                while pos < len && bytes[pos] != b'"' && bytes[pos] != b'\n' {
+                    if bytes[pos] == b'\\' && bytes.get(pos + 1).is_some_and(|c| *c != b'\n') {
+                        pos += 1;
+                    }
+                    pos += 1;
+                }
+                if pos < len && bytes[pos] == b'"' {
+                    pos += 1;
+                }
+                TokenKind::Str
+            }
+            c if c.is_ascii_alphanumeric() || c == b'_' => {
+                while pos < len && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                    pos += 1;
+                }
+                TokenKind::Word
+            }
+            _ => {
+                pos += text[pos..].chars().next().map_or(1, char::len_utf8);
+                TokenKind::Punct
+            }
+        };
+        let token = Token {
+            kind,
+            start,
+            end: pos.min(len),
+            line: start_line,
+        };
+        if kind != TokenKind::Comment {
+            previous = Some(token);
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+// String literal without quotes and escapes
+pub fn unquote(literal: &str) -> String {
+    let inner = literal.strip_prefix('"').unwrap_or(literal);
+    let inner = inner.strip_suffix('"').unwrap_or(inner);
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some(other) => value.push(other),
+            None => {}
+        }
+    }
+    value
+}
+
+#[derive(Clone, Default)]
+pub struct ParsedRule {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub private: bool,
+    pub global: bool,
+    pub meta: Vec<(String, String)>,
+    pub line: usize,
+    // Byte range of the whole rule including its modifiers
+    pub start: usize,
+    pub end: usize,
+    // Identifiers used in the condition, references to other rules among them
+    pub condition_words: Vec<String>,
+}
+
+impl ParsedRule {
+    // Value of the first meta entry with this name, ignoring case
+    pub fn meta_value(&self, name: &str) -> Option<&str> {
+        self.meta
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Default)]
+pub struct ParsedFile {
+    pub imports: Vec<String>,
+    pub includes: Vec<String>,
+    pub rules: Vec<ParsedRule>,
+}
+
+pub fn parse(text: &str) -> ParsedFile {
+    let tokens: Vec<Token> = tokenize(text).into_iter().filter(|t| t.kind != TokenKind::Comment).collect();
+    let source = |index: usize| tokens.get(index).map(|t| &text[t.start..t.end]).unwrap_or("");
+    let kind = |index: usize| tokens.get(index).map(|t| t.kind);
+
+    let mut file = ParsedFile::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        match (kind(i), source(i)) {
+            (Some(TokenKind::Word), keyword @ ("import" | "include")) if kind(i + 1) == Some(TokenKind::Str) => {
+                let value = unquote(source(i + 1));
+                if keyword == "import" {
+                    file.imports.push(value);
+                } else {
+                    file.includes.push(value);
+                }
+                i += 2;
+            }
+            (Some(TokenKind::Word), "private" | "global" | "rule") => {
+                let mut rule = ParsedRule {
+                    line: tokens[i].line,
+                    start: tokens[i].start,
+                    ..Default::default()
+                };
+                while matches!(source(i), "private" | "global") {
+                    rule.private |= source(i) == "private";
+                    rule.global |= source(i) == "global";
+                    i += 1;
+                }
+                if source(i) != "rule" || kind(i + 1) != Some(TokenKind::Word) {
+                    i += 1;
+                    continue;
+                }
+                rule.name = source(i + 1).to_string();
+                i += 2;
+
+                if source(i) == ":" {
+                    i += 1;
+                    while kind(i) == Some(TokenKind::Word) {
+                        rule.tags.push(source(i).to_string());
+                        i += 1;
+                    }
+                }
+                if source(i) != "{" {
+                    continue;
+                }
+
+                let mut depth = 0;
+                let mut section = "";
+                while i < tokens.len() {
+                    match source(i) {
+                        "{" => depth += 1,
+                        "}" => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        i += 1;
+                        break;
+                    }
+
+                    let word = kind(i) == Some(TokenKind::Word);
+                    if depth == 1 && word && source(i + 1) == ":" && matches!(source(i), "meta" | "strings" | "condition") {
+                        section = source(i);
+                        i += 2;
+                        continue;
+                    }
+                    if section == "meta" && word && source(i + 1) == "=" {
+                        let (value, used) = match (source(i + 2), kind(i + 2)) {
+                            ("-", _) => (format!("-{}", source(i + 3)), 4),
+                            (value, Some(TokenKind::Str)) => (unquote(value), 3),
+                            (value, _) => (value.to_string(), 3),
+                        };
+                        rule.meta.push((source(i).to_string(), value));
+                        i += used;
+                        continue;
+                    }
+                    if section == "condition" && word && !matches!(source(i.wrapping_sub(1)), "$" | "#" | "@" | "!") {
+                        rule.condition_words.push(source(i).to_string());
+                    }
+                    i += 1;
+                }
+                rule.end = tokens.get(i.wrapping_sub(1)).map_or(text.len(), |t| t.end);
+                file.rules.push(rule);
+            }
+            _ => i += 1,
+        }
+    }
+    file
+}
+
+// Relative include paths resolved against a directory, lines are kept in place
+pub fn absolute_includes(text: &str, base: &std::path::Path) -> String {
+    let tokens: Vec<Token> = tokenize(text).into_iter().filter(|t| t.kind != TokenKind::Comment).collect();
+    let mut result = String::new();
+    let mut pos = 0;
+    for pair in tokens.windows(2) {
+        let (keyword, path) = (pair[0], pair[1]);
+        if &text[keyword.start..keyword.end] != "include" || path.kind != TokenKind::Str {
+            continue;
+        }
+        let value = unquote(&text[path.start..path.end]);
+        if std::path::Path::new(&value).is_absolute() {
+            continue;
+        }
+        result.push_str(&text[pos..path.start]);
+        result.push_str(&format!("{:?}", base.join(&value).to_string_lossy()));
+        pos = path.end;
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_bodies() {
+        let text = "rule a\n{\n    strings:\n        $re = /\\{[a-z]{2,4}\\/\"}/ nocase\n    condition:\n        $re and filesize / 2 > 10\n}\nrule b { condition: true }\n";
+        let kinds: Vec<TokenKind> = tokenize(text).iter().map(|token| token.kind).collect();
+        assert_eq!(kinds.iter().filter(|kind| **kind == TokenKind::Regex).count(), 1);
+
+        let parsed = parse(text);
+        let names: Vec<&str> = parsed.rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(&text[parsed.rules[0].end - 1..parsed.rules[0].end], "}");
+        assert_eq!(parsed.rules[1].line, 8);
+    }
+
+    #[test]
+    fn matches_operator() {
+        let text = "condition: pe.sections[0].name matches /\\.t{1}ext/is";
+        let regex = tokenize(text).pop().unwrap();
+        assert!(regex.kind == TokenKind::Regex);
+        assert_eq!(&text[regex.start..regex.end], "/\\.t{1}ext/is");
+    }
+}